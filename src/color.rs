@@ -1,6 +1,9 @@
 use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
 use serde::{Deserialize, Serialize};
 
+use crate::config::Config;
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, PartialOrd, Eq, Ord)]
 pub enum Color {
     Red,
@@ -10,6 +13,79 @@ pub enum Color {
     Purple,
 }
 
+impl Color {
+    // All colors, in the order used for sorting and grouping (colors of the rainbow)
+    pub const ALL: [Color; 5] = [Color::Red, Color::Yellow, Color::Green, Color::Blue, Color::Purple];
+
+    // Names of all colors, as given on the command line (e.g. for help texts and messages)
+    pub fn all() -> Vec<&'static str> {
+        Color::ALL.iter().map(Color::name).collect()
+    }
+
+    // Name of the color as given on the command line, e.g. 'red'
+    pub fn name(&self) -> &'static str {
+        match self {
+            Color::Red => "red",
+            Color::Yellow => "yellow",
+            Color::Green => "green",
+            Color::Blue => "blue",
+            Color::Purple => "purple",
+        }
+    }
+
+    // Look up a color by its name. A hex value such as '#ff0000' is mapped to the closest color.
+    pub fn from_name(name: &str) -> Option<Color> {
+        Color::ALL
+            .into_iter()
+            .find(|color| color.name() == name)
+            .or_else(|| Color::closest_to_hex(name))
+    }
+
+    // Parse a color argument: the name of a color (or a hex value), or 'clear' for no color
+    pub fn parse(text: &str) -> Result<Option<Color>, UnknownColor> {
+        match text {
+            "clear" => Ok(None),
+            name => Color::from_name(name).map(Some).ok_or(UnknownColor(name.to_string())),
+        }
+    }
+
+    // RGB value of the color, as defined for the CSS color names
+    fn rgb(&self) -> (u8, u8, u8) {
+        match self {
+            Color::Red => (0xff, 0x00, 0x00),
+            Color::Yellow => (0xff, 0xff, 0x00),
+            Color::Green => (0x00, 0x80, 0x00),
+            Color::Blue => (0x00, 0x00, 0xff),
+            Color::Purple => (0x80, 0x00, 0x80),
+        }
+    }
+
+    // Find the color closest to a hex value such as '#ff0000' or '#f00', by the distance
+    // between the RGB values. Returns None if the value is not a valid hex color.
+    pub fn closest_to_hex(hex: &str) -> Option<Color> {
+        let digits = hex.strip_prefix('#')?;
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digits = match digits.len() {
+            3 => digits.chars().flat_map(|c| [c, c]).collect(),
+            6 => digits.to_string(),
+            _ => return None,
+        };
+        let component = |i: usize| i32::from(u8::from_str_radix(&digits[i..i + 2], 16).unwrap());
+        let (r, g, b) = (component(0), component(2), component(4));
+
+        Color::ALL.into_iter().min_by_key(|color| {
+            let (cr, cg, cb) = color.rgb();
+            (r - i32::from(cr)).pow(2) + (g - i32::from(cg)).pow(2) + (b - i32::from(cb)).pow(2)
+        })
+    }
+}
+
+// A color argument that is not the name of a color or a hex value
+#[derive(Debug, PartialEq)]
+pub struct UnknownColor(pub String);
+
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -28,28 +104,72 @@ pub trait Colorize {
     fn green_fg(&self)  -> String;
     fn blue_fg(&self)   -> String;
     fn purple_fg(&self) -> String;
+    fn bright_red_fg(&self)    -> String;
+    fn bright_yellow_fg(&self) -> String;
+    fn bright_green_fg(&self)  -> String;
+    fn bright_blue_fg(&self)   -> String;
+    fn bright_purple_fg(&self) -> String;
     fn red_bg(&self)    -> String;
     fn yellow_bg(&self) -> String;
     fn green_bg(&self)  -> String;
     fn blue_bg(&self)   -> String;
     fn purple_bg(&self) -> String;
+    // For messages written to stderr, which can be a terminal when stdout is not (or vice versa)
+    fn red_err(&self)    -> String;
+    fn yellow_err(&self) -> String;
 }
 
 impl Colorize for str {
-    fn red_fg(&self)    -> String { add_color(String::from("\x1b[31m"), self) }
-    fn yellow_fg(&self) -> String { add_color(String::from("\x1b[33m"), self) }
-    fn green_fg(&self)  -> String { add_color(String::from("\x1b[32m"), self) }
-    fn blue_fg(&self)   -> String { add_color(String::from("\x1b[34m"), self) }
-    fn purple_fg(&self) -> String { add_color(String::from("\x1b[35m"), self) }
-    fn red_bg(&self)    -> String { add_color(String::from("\x1b[41m"), self) }
-    fn yellow_bg(&self) -> String { add_color(String::from("\x1b[43m"), self) }
-    fn green_bg(&self)  -> String { add_color(String::from("\x1b[42m"), self) }
-    fn blue_bg(&self)   -> String { add_color(String::from("\x1b[44m"), self) }
-    fn purple_bg(&self) -> String { add_color(String::from("\x1b[45m"), self) }
+    // With the bright palette, the bright variants are used instead
+    fn red_fg(&self)    -> String { if bright_palette() { self.bright_red_fg() } else { add_color(String::from("\x1b[31m"), self, colors()) } }
+    fn yellow_fg(&self) -> String { if bright_palette() { self.bright_yellow_fg() } else { add_color(String::from("\x1b[33m"), self, colors()) } }
+    fn green_fg(&self)  -> String { if bright_palette() { self.bright_green_fg() } else { add_color(String::from("\x1b[32m"), self, colors()) } }
+    fn blue_fg(&self)   -> String { if bright_palette() { self.bright_blue_fg() } else { add_color(String::from("\x1b[34m"), self, colors()) } }
+    fn purple_fg(&self) -> String { if bright_palette() { self.bright_purple_fg() } else { add_color(String::from("\x1b[35m"), self, colors()) } }
+    fn bright_red_fg(&self)    -> String { add_color(String::from("\x1b[91m"), self, colors()) }
+    fn bright_yellow_fg(&self) -> String { add_color(String::from("\x1b[93m"), self, colors()) }
+    fn bright_green_fg(&self)  -> String { add_color(String::from("\x1b[92m"), self, colors()) }
+    fn bright_blue_fg(&self)   -> String { add_color(String::from("\x1b[94m"), self, colors()) }
+    fn bright_purple_fg(&self) -> String { add_color(String::from("\x1b[95m"), self, colors()) }
+    fn red_bg(&self)    -> String { add_color(String::from("\x1b[41m"), self, colors()) }
+    fn yellow_bg(&self) -> String { add_color(String::from("\x1b[43m"), self, colors()) }
+    fn green_bg(&self)  -> String { add_color(String::from("\x1b[42m"), self, colors()) }
+    fn blue_bg(&self)   -> String { add_color(String::from("\x1b[44m"), self, colors()) }
+    fn purple_bg(&self) -> String { add_color(String::from("\x1b[45m"), self, colors()) }
+    fn red_err(&self)    -> String { add_color(String::from(if bright_palette() { "\x1b[91m" } else { "\x1b[31m" }), self, error_colors()) }
+    fn yellow_err(&self) -> String { add_color(String::from(if bright_palette() { "\x1b[93m" } else { "\x1b[33m" }), self, error_colors()) }
+}
+
+// Color settings from the config, set once by main with 'configure' before any command runs.
+// Until then (e.g. for an invalid global option), nothing is colored.
+static COLORS: AtomicBool = AtomicBool::new(false);
+static ERROR_COLORS: AtomicBool = AtomicBool::new(false);
+static BRIGHT_PALETTE: AtomicBool = AtomicBool::new(false);
+
+pub fn configure(config: &Config) {
+    COLORS.store(config.colors, Ordering::Relaxed);
+    ERROR_COLORS.store(config.error_colors, Ordering::Relaxed);
+    BRIGHT_PALETTE.store(config.bright_palette, Ordering::Relaxed);
+}
+
+fn colors() -> bool {
+    COLORS.load(Ordering::Relaxed)
+}
+
+fn error_colors() -> bool {
+    ERROR_COLORS.load(Ordering::Relaxed)
+}
+
+fn bright_palette() -> bool {
+    BRIGHT_PALETTE.load(Ordering::Relaxed)
 }
 
-// Color the string
-fn add_color(mut color: String, text: &str) -> String {
+// Color the string, if colors are enabled for the stream it is written to
+fn add_color(mut color: String, text: &str, enabled: bool) -> String {
+    if !enabled {
+        return text.to_string();
+    }
+
     let reset = "\x1b[0m";
     color.push_str(text);
     color.push_str(reset);
@@ -62,17 +182,55 @@ mod tests {
 
     #[test]
     fn test_colors() {
+        assert_eq!(add_color(String::from("\x1b[31m"), "test", true), String::from("\x1b[31mtest\x1b[0m"));
+        assert_eq!(add_color(String::from("\x1b[31m"), "test", false), String::from("test"));
+        // The methods only add the codes when colors are enabled, which depends on where the tests run
+        let colored = |code: &str| add_color(code.to_string(), "test", colors());
         let s = String::from("test");
 
-        assert_eq!(s.red_fg(), String::from("\x1b[31mtest\x1b[0m"));
-        assert_eq!(s.yellow_fg(), String::from("\x1b[33mtest\x1b[0m"));
-        assert_eq!(s.green_fg(), String::from("\x1b[32mtest\x1b[0m"));
-        assert_eq!(s.blue_fg(), String::from("\x1b[34mtest\x1b[0m"));
-        assert_eq!(s.purple_fg(), String::from("\x1b[35mtest\x1b[0m"));
-        assert_eq!(s.red_bg(), String::from("\x1b[41mtest\x1b[0m"));
-        assert_eq!(s.yellow_bg(), String::from("\x1b[43mtest\x1b[0m"));
-        assert_eq!(s.green_bg(), String::from("\x1b[42mtest\x1b[0m"));
-        assert_eq!(s.blue_bg(), String::from("\x1b[44mtest\x1b[0m"));
-        assert_eq!(s.purple_bg(), String::from("\x1b[45mtest\x1b[0m"));
+        assert_eq!(s.red_fg(), colored("\x1b[31m"));
+        assert_eq!(s.yellow_fg(), colored("\x1b[33m"));
+        assert_eq!(s.green_fg(), colored("\x1b[32m"));
+        assert_eq!(s.blue_fg(), colored("\x1b[34m"));
+        assert_eq!(s.purple_fg(), colored("\x1b[35m"));
+        assert_eq!(s.red_bg(), colored("\x1b[41m"));
+        assert_eq!(s.yellow_bg(), colored("\x1b[43m"));
+        assert_eq!(s.green_bg(), colored("\x1b[42m"));
+        assert_eq!(s.blue_bg(), colored("\x1b[44m"));
+        assert_eq!(s.purple_bg(), colored("\x1b[45m"));
+        assert_eq!(s.bright_red_fg(), colored("\x1b[91m"));
+        assert_eq!(s.bright_yellow_fg(), colored("\x1b[93m"));
+        assert_eq!(s.bright_green_fg(), colored("\x1b[92m"));
+        assert_eq!(s.bright_blue_fg(), colored("\x1b[94m"));
+        assert_eq!(s.bright_purple_fg(), colored("\x1b[95m"));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Color::parse("red"), Ok(Some(Color::Red)));
+        assert_eq!(Color::parse("purple"), Ok(Some(Color::Purple)));
+        assert_eq!(Color::parse("#0000ff"), Ok(Some(Color::Blue)));
+        assert_eq!(Color::parse("clear"), Ok(None));
+        assert_eq!(Color::parse("orange"), Err(UnknownColor(String::from("orange"))));
+        assert_eq!(Color::parse("Red"), Err(UnknownColor(String::from("Red"))));
+
+        assert_eq!(Color::all(), vec!["red", "yellow", "green", "blue", "purple"]);
+        for name in Color::all() {
+            assert_eq!(Color::from_name(name).map(|color| color.name()), Some(name));
+        }
+    }
+
+    #[test]
+    fn test_closest_to_hex() {
+        assert_eq!(Color::closest_to_hex("#ff0000"), Some(Color::Red));
+        assert_eq!(Color::closest_to_hex("#FF1010"), Some(Color::Red));
+        assert_eq!(Color::closest_to_hex("#f00"), Some(Color::Red));
+        assert_eq!(Color::closest_to_hex("#ffd700"), Some(Color::Yellow));
+        assert_eq!(Color::closest_to_hex("#228b22"), Some(Color::Green));
+        assert_eq!(Color::closest_to_hex("#1e90ff"), Some(Color::Blue));
+        assert_eq!(Color::closest_to_hex("#9400d3"), Some(Color::Purple));
+        assert_eq!(Color::closest_to_hex("ff0000"), None);
+        assert_eq!(Color::closest_to_hex("#ff00"), None);
+        assert_eq!(Color::closest_to_hex("#gg0000"), None);
     }
 }
@@ -0,0 +1,124 @@
+// The available commands, shared by the dispatch in main.rs and the help. The order of this table
+// is the order in which 'help' lists the commands; the description of each command is taken from
+// its sections in help.txt.
+pub struct CommandInfo {
+    pub name: &'static str,
+    // Whether the tasks need to be saved after the command ran
+    pub modifies_tasks: bool,
+    // Whether the command can be used in a batch file
+    pub in_batch: bool,
+}
+
+const fn command(name: &'static str, modifies_tasks: bool, in_batch: bool) -> CommandInfo {
+    CommandInfo { name, modifies_tasks, in_batch }
+}
+
+pub const COMMANDS: &[CommandInfo] = &[
+    command("add", true, true),
+    command("due", true, true),
+    command("note", true, true),
+    command("edit", true, true),
+    command("color", true, true),
+    command("set", true, true),
+    command("rename", true, true),
+    command("subtask", true, true),
+    command("block", true, true),
+    command("snooze", true, true),
+    command("estimate", true, true),
+    command("url", true, true),
+    command("done", true, true),
+    command("pin", true, true),
+    command("unpin", true, true),
+    command("move-to-list", true, false),
+    command("remove", true, true),
+    command("purge", true, true),
+    command("dedupe", true, true),
+    command("list", false, true),
+    command("show", false, true),
+    command("open", false, true),
+    command("count", false, true),
+    command("notify", false, true),
+    command("done-today", false, true),
+    command("done-this-week", false, true),
+    command("stats", false, true),
+    command("sort", true, true),
+    command("batch", true, false),
+    command("undo", false, false),
+    command("history", false, false),
+    command("export", false, true),
+    command("schema", false, true),
+    command("recover", false, false),
+    command("info", false, false),
+    command("help", false, true),
+];
+
+pub fn find(name: &str) -> Option<&'static CommandInfo> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+// The command closest to a mistyped 'name' (e.g. 'lsit' for 'list'), if any is close enough
+pub fn closest(name: &str) -> Option<&'static str> {
+    let max_distance = if name.chars().count() <= 3 { 1 } else { 2 };
+    COMMANDS
+        .iter()
+        .map(|command| (edit_distance(name, command.name), command.name))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, name)| name)
+}
+
+// Number of single character insertions, deletions, substitutions and swaps of adjacent
+// characters needed to change 'a' into 'b'
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        assert!(find("add").is_some_and(|command| command.modifies_tasks && command.in_batch));
+        assert!(find("list").is_some_and(|command| !command.modifies_tasks));
+        assert!(find("undo").is_some_and(|command| !command.in_batch));
+        assert!(find("foo").is_none());
+    }
+
+    #[test]
+    fn test_closest() {
+        assert_eq!(closest("lsit"), Some("list"));
+        assert_eq!(closest("remvoe"), Some("remove"));
+        assert_eq!(closest("lst"), Some("list"));
+        assert_eq!(closest("ad"), Some("add"));
+        assert_eq!(closest("foobar"), None);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("list", "list"), 0);
+        assert_eq!(edit_distance("lst", "list"), 1);
+        assert_eq!(edit_distance("lsit", "list"), 1);
+        assert_eq!(edit_distance("", "add"), 3);
+    }
+}
@@ -0,0 +1,481 @@
+use std::error;
+use std::fmt;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use chrono::{Local, NaiveDate, Utc, Weekday};
+use chrono_tz::Tz;
+use unicode_width::UnicodeWidthStr;
+
+use crate::color::Color;
+use crate::task::{self, Column};
+
+// Settings that apply to a whole run of the program, from the global options and the TODO_*
+// environment variables. The config is built once in main, before any command runs, and never
+// changes afterwards. It is passed to the commands that depend on it (e.g. for the week end to
+// parse a due date), so those can be tested with any setting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    // Suppress informational messages (global '--quiet' option)
+    pub quiet: bool,
+    // Where debug log messages are written, if anywhere (TODO_LOG and the '--verbose' option)
+    pub log_target: Option<LogTarget>,
+    // Whether output to stdout and to stderr is colored (global '--color' option and NO_COLOR)
+    pub colors: bool,
+    pub error_colors: bool,
+    // Whether the foreground colors use the bright variants (e.g. '\x1b[91m' instead of
+    // '\x1b[31m'), which are easier to read on some dark themes (TODO_COLOR_PALETTE)
+    pub bright_palette: bool,
+    // Timezone used to determine today's date (TODO_TZ). The local timezone is used if not set.
+    pub timezone: Option<Tz>,
+    // Format used to display dates in the task list and details, as a strftime format string
+    // (TODO_DATE_FORMAT, e.g. '%d/%m/%Y'). ISO dates (YYYY-MM-DD) are used if not set. Input is
+    // always YYYY-MM-DD.
+    pub date_format: Option<String>,
+    // Width of the widest date in the date format, to align the date columns
+    pub date_width: usize,
+    // Number of days before the due date from which a due date is highlighted as due soon
+    // (TODO_SOON_DAYS)
+    pub soon_days: u64,
+    // Whether tasks that are done are always listed and sorted after other tasks
+    // (TODO_COMPLETED_LAST)
+    pub completed_last: bool,
+    // Last day of the week for the 'eow' due date, Friday or Sunday (TODO_WEEK_END)
+    pub week_end: Weekday,
+    // Columns of the task list, in the order they are shown (TODO_LIST_COLUMNS)
+    pub list_columns: Vec<Column>,
+    // Width of the terminal in characters, if known (COLUMNS)
+    pub terminal_width: Option<usize>,
+    // Share of the terminal width for the task name column, in percent (TODO_NAME_WIDTH)
+    pub name_width: Option<usize>,
+    // Color of new tasks that are added without a color (TODO_DEFAULT_COLOR)
+    pub default_color: Option<Color>,
+    // Maximum length of a note in characters (TODO_MAX_NOTE_LENGTH)
+    pub max_note_length: usize,
+    // Invalid settings that were ignored instead of stopping the program (e.g. an unknown column
+    // name). They are reported once by main.
+    pub warnings: Vec<String>,
+}
+
+// Global options, as given on the command line before the command
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct GlobalOptions {
+    pub quiet: bool,
+    pub verbose: bool,
+    pub color_when: ColorWhen,
+}
+
+// When to use colored output, as set with the global '--color' option
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum ColorWhen {
+    Always,
+    Never,
+    #[default]
+    Auto,
+}
+
+// Where debug log messages are written: the file in TODO_LOG, or stderr with the global
+// '--verbose' option. The log is never written to stdout, so it does not mix with the output.
+#[derive(Debug, PartialEq, Clone)]
+pub enum LogTarget {
+    File(PathBuf),
+    Stderr,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    Palette(String),
+    Timezone(String),
+    DateFormat(String),
+    SoonDays(String),
+    WeekEnd(String),
+    Flag(String, String),
+}
+
+impl error::Error for ConfigError { }
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Palette(e) => writeln!(f, "Invalid palette in TODO_COLOR_PALETTE: {} (expected \'normal\' or \'bright\')", e),
+            ConfigError::Timezone(e) => writeln!(f, "Invalid timezone in TODO_TZ: {} (expected a name such as \'Europe/Amsterdam\')", e),
+            ConfigError::DateFormat(e) => writeln!(f, "Invalid date format in TODO_DATE_FORMAT: {} (expected a format such as \'%d/%m/%Y\')", e),
+            ConfigError::SoonDays(e) => writeln!(f, "Invalid number of days in TODO_SOON_DAYS: {} (expected 0 or a positive integer)", e),
+            ConfigError::WeekEnd(e) => writeln!(f, "Invalid day in TODO_WEEK_END: {} (expected \'friday\' or \'sunday\')", e),
+            ConfigError::Flag(name, e) => writeln!(f, "Invalid value in {}: {} (expected \'true\' or \'false\')", name, e),
+        }
+    }
+}
+
+impl Config {
+    // Build the config from the global options and the settings returned by 'lookup' (normally
+    // the environment variables)
+    pub fn new<F>(options: GlobalOptions, lookup: F) -> Result<Config, ConfigError>
+    where F: Fn(&str) -> Option<String>
+    {
+        let no_color = lookup("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let date_format = parse_date_format(lookup("TODO_DATE_FORMAT"))?;
+        let mut warnings = vec![];
+        Ok(Config {
+            quiet: options.quiet,
+            log_target: log_target(lookup("TODO_LOG"), options.verbose),
+            colors: colors_enabled(options.color_when, no_color, io::stdout().is_terminal()),
+            error_colors: colors_enabled(options.color_when, no_color, io::stderr().is_terminal()),
+            bright_palette: parse_palette(lookup("TODO_COLOR_PALETTE"))?,
+            timezone: parse_timezone(lookup("TODO_TZ"))?,
+            date_width: date_width(date_format.as_deref()),
+            date_format,
+            soon_days: parse_soon_days(lookup("TODO_SOON_DAYS"))?,
+            completed_last: parse_flag("TODO_COMPLETED_LAST", lookup("TODO_COMPLETED_LAST"))?,
+            week_end: parse_week_end(lookup("TODO_WEEK_END"))?,
+            list_columns: task::list_columns(lookup("TODO_LIST_COLUMNS"), &mut warnings),
+            terminal_width: parse_terminal_width(lookup("COLUMNS")),
+            name_width: parse_percentage("TODO_NAME_WIDTH", lookup("TODO_NAME_WIDTH"), &mut warnings),
+            default_color: parse_default_color(lookup("TODO_DEFAULT_COLOR"), &mut warnings),
+            max_note_length: parse_max_note_length(lookup("TODO_MAX_NOTE_LENGTH"), &mut warnings),
+            warnings,
+        })
+    }
+
+    // Format a date for display in the configured date format
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        format_date_with(date, self.date_format.as_deref())
+    }
+
+    // Today's date in the configured timezone, or in the local timezone
+    pub fn today(&self) -> NaiveDate {
+        match self.timezone {
+            Some(tz) => Utc::now().with_timezone(&tz).date_naive(),
+            None => Local::now().date_naive()
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::new(GlobalOptions::default(), |_| None).unwrap()
+    }
+}
+
+// Check whether colored output is allowed on a stream. With 'auto', color is only used when the
+// stream is a terminal, and is suppressed if NO_COLOR is set to a non-empty value (see
+// https://no-color.org).
+fn colors_enabled(when: ColorWhen, no_color: bool, is_terminal: bool) -> bool {
+    match when {
+        ColorWhen::Always => true,
+        ColorWhen::Never => false,
+        ColorWhen::Auto => !no_color && is_terminal,
+    }
+}
+
+// The log target for the TODO_LOG setting and the '--verbose' option. A log file takes
+// precedence, so '--verbose' does not change where an existing log goes.
+fn log_target(setting: Option<String>, verbose: bool) -> Option<LogTarget> {
+    match setting.filter(|setting| !setting.is_empty()) {
+        Some(path) => Some(LogTarget::File(PathBuf::from(path))),
+        None if verbose => Some(LogTarget::Stderr),
+        None => None,
+    }
+}
+
+// Parse the palette setting: 'normal' (the default) or 'bright'. Returns whether it is bright.
+fn parse_palette(setting: Option<String>) -> Result<bool, ConfigError> {
+    match setting.as_deref() {
+        None | Some("") | Some("normal") => Ok(false),
+        Some("bright") => Ok(true),
+        Some(other) => Err(ConfigError::Palette(other.to_string())),
+    }
+}
+
+fn parse_timezone(name: Option<String>) -> Result<Option<Tz>, ConfigError> {
+    match name.filter(|name| !name.is_empty()) {
+        Some(name) => name.parse::<Tz>().map(Some).map_err(|_| ConfigError::Timezone(name)),
+        None => Ok(None)
+    }
+}
+
+// Check that a date format only uses specifiers that apply to dates (e.g. not '%H')
+fn parse_date_format(format: Option<String>) -> Result<Option<String>, ConfigError> {
+    let Some(format) = format.filter(|format| !format.is_empty()) else {
+        return Ok(None);
+    };
+    let date = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+    let mut formatted = String::new();
+    match fmt::Write::write_fmt(&mut formatted, format_args!("{}", date.format(&format))) {
+        Ok(..) => Ok(Some(format)),
+        Err(..) => Err(ConfigError::DateFormat(format)),
+    }
+}
+
+fn format_date_with(date: NaiveDate, format: Option<&str>) -> String {
+    date.format(format.unwrap_or("%Y-%m-%d")).to_string()
+}
+
+// Try every month and day of the week, as names of months and days differ in length
+fn date_width(format: Option<&str>) -> usize {
+    (1..=12)
+        .flat_map(|month| (22..=28).map(move |day| NaiveDate::from_ymd_opt(2000, month, day).unwrap()))
+        .map(|date| format_date_with(date, format).width())
+        .max()
+        .unwrap()
+}
+
+fn parse_soon_days(setting: Option<String>) -> Result<u64, ConfigError> {
+    let Some(setting) = setting.filter(|setting| !setting.is_empty()) else {
+        return Ok(3);
+    };
+    setting.parse::<u64>().map_err(|_| ConfigError::SoonDays(setting))
+}
+
+fn parse_week_end(setting: Option<String>) -> Result<Weekday, ConfigError> {
+    let Some(setting) = setting.filter(|setting| !setting.is_empty()) else {
+        return Ok(Weekday::Sun);
+    };
+    match setting.to_lowercase().as_str() {
+        "friday" | "fri" => Ok(Weekday::Fri),
+        "sunday" | "sun" => Ok(Weekday::Sun),
+        _ => Err(ConfigError::WeekEnd(setting)),
+    }
+}
+
+// Parse an on/off setting, which is off if not set
+fn parse_flag(name: &str, setting: Option<String>) -> Result<bool, ConfigError> {
+    let Some(setting) = setting.filter(|setting| !setting.is_empty()) else {
+        return Ok(false);
+    };
+    match setting.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(ConfigError::Flag(name.to_string(), setting)),
+    }
+}
+
+// The terminal width, which is unknown if COLUMNS is not a number
+fn parse_terminal_width(setting: Option<String>) -> Option<usize> {
+    setting.and_then(|setting| setting.trim().parse::<usize>().ok())
+}
+
+// Parse a percentage such as '50%' or '50' (1 to 100). An invalid value is ignored, and added to
+// 'warnings'.
+fn parse_percentage(name: &str, setting: Option<String>, warnings: &mut Vec<String>) -> Option<usize> {
+    let setting = setting.filter(|setting| !setting.trim().is_empty())?;
+    let number = setting.trim().trim_end_matches('%');
+    match number.parse::<usize>() {
+        Ok(percentage) if (1..=100).contains(&percentage) => Some(percentage),
+        _ => {
+            warnings.push(format!("Ignoring invalid percentage in {}: {}", name, setting));
+            None
+        }
+    }
+}
+
+// An invalid color does not prevent tasks from being added, so it is ignored, and added to
+// 'warnings'
+fn parse_default_color(setting: Option<String>, warnings: &mut Vec<String>) -> Option<Color> {
+    let setting = setting.filter(|setting| !setting.is_empty())?;
+    let color = Color::from_name(&setting);
+    if color.is_none() {
+        warnings.push(format!("Ignoring invalid color in TODO_DEFAULT_COLOR: {}", setting));
+    }
+    color
+}
+
+// The maximum note length protects the tasks file against e.g. accidentally pasting a huge text.
+// An invalid value is ignored in favor of the default, and added to 'warnings'.
+fn parse_max_note_length(setting: Option<String>, warnings: &mut Vec<String>) -> usize {
+    let default = 10_000;
+    let Some(setting) = setting.filter(|setting| !setting.is_empty()) else {
+        return default;
+    };
+    match setting.parse::<usize>() {
+        Ok(length) if length > 0 => length,
+        _ => {
+            warnings.push(format!("Ignoring invalid length in TODO_MAX_NOTE_LENGTH: {}", setting));
+            default
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colors_enabled() {
+        assert!(colors_enabled(ColorWhen::Always, true, false));
+        assert!(!colors_enabled(ColorWhen::Never, false, true));
+        assert!(colors_enabled(ColorWhen::Auto, false, true));
+        assert!(!colors_enabled(ColorWhen::Auto, false, false));
+        assert!(!colors_enabled(ColorWhen::Auto, true, true));
+    }
+
+    #[test]
+    fn test_log_target() {
+        assert_eq!(log_target(None, false), None);
+        assert_eq!(log_target(Some(String::new()), false), None);
+        assert_eq!(log_target(None, true), Some(LogTarget::Stderr));
+        assert_eq!(log_target(Some(String::from("todo.log")), true), Some(LogTarget::File(PathBuf::from("todo.log"))));
+    }
+
+    #[test]
+    fn test_palette() {
+        assert_eq!(parse_palette(None), Ok(false));
+        assert_eq!(parse_palette(Some(String::from("normal"))), Ok(false));
+        assert_eq!(parse_palette(Some(String::from("bright"))), Ok(true));
+        assert_eq!(parse_palette(Some(String::from("dark"))), Err(ConfigError::Palette(String::from("dark"))));
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(parse_timezone(None), Ok(None));
+        assert_eq!(parse_timezone(Some(String::new())), Ok(None));
+        assert_eq!(parse_timezone(Some(String::from("Europe/Amsterdam"))), Ok(Some(chrono_tz::Europe::Amsterdam)));
+        assert!(matches!(
+            parse_timezone(Some(String::from("Mars/Olympus_Mons"))),
+            Result::Err(ConfigError::Timezone(..))
+        ));
+    }
+
+    #[test]
+    fn test_date_format() {
+        assert_eq!(parse_date_format(None), Ok(None));
+        assert_eq!(parse_date_format(Some(String::new())), Ok(None));
+        assert_eq!(parse_date_format(Some(String::from("%d/%m/%Y"))), Ok(Some(String::from("%d/%m/%Y"))));
+        assert_eq!(parse_date_format(Some(String::from("%H:%M"))), Err(ConfigError::DateFormat(String::from("%H:%M"))));
+        assert_eq!(parse_date_format(Some(String::from("%Q"))), Err(ConfigError::DateFormat(String::from("%Q"))));
+
+        let date = NaiveDate::from_ymd_opt(2025, 7, 4).unwrap();
+        assert_eq!(format_date_with(date, None), "2025-07-04");
+        assert_eq!(format_date_with(date, Some("%d/%m/%Y")), "04/07/2025");
+        assert_eq!(format_date_with(date, Some("%a %e %b")), "Fri  4 Jul");
+
+        assert_eq!(date_width(None), 10);
+        assert_eq!(date_width(Some("%B %d")), 12); // September 22
+        assert_eq!(date_width(Some("%A")), 9); // Wednesday
+    }
+
+    #[test]
+    fn test_parse_soon_days() {
+        assert_eq!(parse_soon_days(None), Ok(3));
+        assert_eq!(parse_soon_days(Some(String::new())), Ok(3));
+        assert_eq!(parse_soon_days(Some(String::from("7"))), Ok(7));
+        assert_eq!(parse_soon_days(Some(String::from("0"))), Ok(0));
+        assert_eq!(parse_soon_days(Some(String::from("-1"))), Err(ConfigError::SoonDays(String::from("-1"))));
+    }
+
+    #[test]
+    fn test_parse_week_end() {
+        assert_eq!(parse_week_end(None), Ok(Weekday::Sun));
+        assert_eq!(parse_week_end(Some(String::from("Friday"))), Ok(Weekday::Fri));
+        assert_eq!(parse_week_end(Some(String::from("sun"))), Ok(Weekday::Sun));
+        assert_eq!(parse_week_end(Some(String::from("monday"))), Err(ConfigError::WeekEnd(String::from("monday"))));
+    }
+
+    #[test]
+    fn test_parse_flag() {
+        let name = "TODO_COMPLETED_LAST";
+        assert_eq!(parse_flag(name, None), Ok(false));
+        assert_eq!(parse_flag(name, Some(String::new())), Ok(false));
+        assert_eq!(parse_flag(name, Some(String::from("true"))), Ok(true));
+        assert_eq!(parse_flag(name, Some(String::from("1"))), Ok(true));
+        assert_eq!(parse_flag(name, Some(String::from("Off"))), Ok(false));
+        assert_eq!(
+            parse_flag(name, Some(String::from("maybe"))),
+            Err(ConfigError::Flag(String::from("TODO_COMPLETED_LAST"), String::from("maybe")))
+        );
+    }
+
+    #[test]
+    fn test_parse_terminal_width() {
+        assert_eq!(parse_terminal_width(None), None);
+        assert_eq!(parse_terminal_width(Some(String::from(" 120 "))), Some(120));
+        assert_eq!(parse_terminal_width(Some(String::from("foobar"))), None);
+    }
+
+    #[test]
+    fn test_parse_percentage() {
+        let mut warnings = vec![];
+        assert_eq!(parse_percentage("TODO_NAME_WIDTH", None, &mut warnings), None);
+        assert_eq!(parse_percentage("TODO_NAME_WIDTH", Some(String::from(" ")), &mut warnings), None);
+        assert_eq!(parse_percentage("TODO_NAME_WIDTH", Some(String::from("50%")), &mut warnings), Some(50));
+        assert_eq!(parse_percentage("TODO_NAME_WIDTH", Some(String::from("30")), &mut warnings), Some(30));
+        assert_eq!(parse_percentage("TODO_NAME_WIDTH", Some(String::from("100%")), &mut warnings), Some(100));
+        assert!(warnings.is_empty());
+
+        assert_eq!(parse_percentage("TODO_NAME_WIDTH", Some(String::from("150%")), &mut warnings), None);
+        assert_eq!(parse_percentage("TODO_NAME_WIDTH", Some(String::from("half")), &mut warnings), None);
+        assert_eq!(parse_percentage("TODO_NAME_WIDTH", Some(String::from("0")), &mut warnings), None);
+        assert_eq!(warnings, vec![
+            String::from("Ignoring invalid percentage in TODO_NAME_WIDTH: 150%"),
+            String::from("Ignoring invalid percentage in TODO_NAME_WIDTH: half"),
+            String::from("Ignoring invalid percentage in TODO_NAME_WIDTH: 0"),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_default_color() {
+        let mut warnings = vec![];
+        assert_eq!(parse_default_color(None, &mut warnings), None);
+        assert_eq!(parse_default_color(Some(String::new()), &mut warnings), None);
+        assert_eq!(parse_default_color(Some(String::from("blue")), &mut warnings), Some(Color::Blue));
+        assert!(warnings.is_empty());
+
+        assert_eq!(parse_default_color(Some(String::from("magenta")), &mut warnings), None);
+        assert_eq!(warnings, vec![String::from("Ignoring invalid color in TODO_DEFAULT_COLOR: magenta")]);
+    }
+
+    #[test]
+    fn test_parse_max_note_length() {
+        let mut warnings = vec![];
+        assert_eq!(parse_max_note_length(None, &mut warnings), 10_000);
+        assert_eq!(parse_max_note_length(Some(String::new()), &mut warnings), 10_000);
+        assert_eq!(parse_max_note_length(Some(String::from("500")), &mut warnings), 500);
+        assert!(warnings.is_empty());
+
+        assert_eq!(parse_max_note_length(Some(String::from("0")), &mut warnings), 10_000);
+        assert_eq!(parse_max_note_length(Some(String::from("lots")), &mut warnings), 10_000);
+        assert_eq!(warnings, vec![
+            String::from("Ignoring invalid length in TODO_MAX_NOTE_LENGTH: 0"),
+            String::from("Ignoring invalid length in TODO_MAX_NOTE_LENGTH: lots"),
+        ]);
+    }
+
+    #[test]
+    fn test_config_new() {
+        let options = GlobalOptions { quiet: true, verbose: true, color_when: ColorWhen::Never };
+        let config = Config::new(options, |name| (name == "TODO_TZ").then(|| String::from("Asia/Tokyo"))).unwrap();
+        assert!(config.quiet);
+        assert_eq!(config.log_target, Some(LogTarget::Stderr));
+        assert!(!config.colors && !config.error_colors);
+        assert!(!config.bright_palette);
+        assert_eq!(config.timezone, Some(chrono_tz::Asia::Tokyo));
+        assert_eq!(config.date_format, None);
+        assert_eq!(config.date_width, 10);
+        assert_eq!(config.soon_days, 3);
+        assert!(!config.completed_last);
+        assert_eq!(config.week_end, Weekday::Sun);
+        assert_eq!(config.list_columns, task::list_columns(None, &mut vec![]));
+        assert!(config.warnings.is_empty());
+
+        // Ignored settings are kept as warnings
+        let config = Config::new(options, |name| (name == "TODO_LIST_COLUMNS").then(|| String::from("id,priority"))).unwrap();
+        assert_eq!(config.list_columns, vec![Column::Id]);
+        assert_eq!(config.warnings, vec![String::from("Ignoring unknown column in TODO_LIST_COLUMNS: priority")]);
+
+        let config = Config::new(options, |name| (name == "TODO_DATE_FORMAT").then(|| String::from("%B %d"))).unwrap();
+        assert_eq!(config.format_date(NaiveDate::from_ymd_opt(2025, 7, 4).unwrap()), "July 04");
+        assert_eq!(config.date_width, 12);
+
+        // NO_COLOR only applies to automatic colors
+        let no_color = |name: &str| (name == "NO_COLOR").then(|| String::from("1"));
+        let config = Config::new(GlobalOptions::default(), no_color).unwrap();
+        assert!(!config.colors && !config.error_colors);
+        let always = GlobalOptions { color_when: ColorWhen::Always, ..options };
+        let config = Config::new(always, no_color).unwrap();
+        assert!(config.colors && config.error_colors);
+
+        assert_eq!(
+            Config::new(options, |name| (name == "TODO_TZ").then(|| String::from("Nowhere"))),
+            Err(ConfigError::Timezone(String::from("Nowhere")))
+        );
+    }
+}
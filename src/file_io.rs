@@ -1,11 +1,16 @@
+use std::env;
 use std::error;
 use std::fmt;
-use std::fs::{File, read_to_string, rename, create_dir};
-use std::io::Write;
-use std::path::PathBuf;
+use std::fs::{File, read_dir, read_to_string, remove_file, rename, create_dir};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
-use crate::task::Task;
-use dirs::data_dir;
+use crate::config::Config;
+use crate::output::{info, log, log_error};
+use crate::task::{Task, assign_missing_ids, diff_tasks, note_file_ids, summarize_changes, tasks_added_or_removed};
+use chrono::{DateTime, Local};
+use dirs::{data_dir, home_dir};
+use serde::{Deserialize, Serialize};
 
 // -- Error handling --
 type Result<T> = std::result::Result<T, FileError>;
@@ -14,9 +19,13 @@ type Result<T> = std::result::Result<T, FileError>;
 pub enum FileError {
     Deserialize(serde_json::Error),
     Serialize(serde_json::Error),
+    DeserializeYaml(serde_yaml::Error),
+    SerializeYaml(serde_yaml::Error),
     Io(std::io::Error),
     CreateDir(std::io::Error),
     BackupMissing,
+    BackupNotFound(usize, usize),
+    InvalidPath(String),
 }
 
 impl error::Error for FileError { }
@@ -26,16 +35,25 @@ impl fmt::Display for FileError {
         match self {
             FileError::Deserialize(e) => write!(f, "Unable to deserialize save file contents. Details:\n    {}", e),
             FileError::Serialize(e) => write!(f, "Unable to serialize data for saving. Details:\n    {}", e),
+            FileError::DeserializeYaml(e) => write!(f, "Unable to deserialize save file contents. Details:\n    {}", e),
+            FileError::SerializeYaml(e) => write!(f, "Unable to serialize data for saving. Details:\n    {}", e),
             FileError::Io(e) => write!(f, "Unable to save data. Details:\n    {}", e),
             FileError::CreateDir(e) => write!(f, "Unable to create directory for saving data. Details:\n    {}", e),
-            FileError::BackupMissing => write!(f, "Unable to undo. No undos are available")
+            FileError::BackupMissing => write!(f, "Unable to undo. No undos are available"),
+            FileError::BackupNotFound(steps, available) => write!(f, "Unable to recover. Only {} undos are available, but {} was requested", available, steps),
+            FileError::InvalidPath(e) => write!(f, "Unable to expand the path of the tasks file: {}", e)
         }
     }
 }
 // -- End error handling --
 
-// Builds the filename (with full path)
-pub fn get_filename() -> PathBuf {
+// Builds the filename (with full path). A custom path (from '--file' or TODO_FILE) is used as
+// given, after expansion. Otherwise, tasks are saved as JSON in the data directory, unless a
+// YAML tasks file exists there (and a JSON one does not)
+pub fn get_filename(custom_path: Option<String>) -> Result<PathBuf> {
+    if let Some(path) = custom_path.filter(|path| !path.is_empty()) {
+        return expand_path(&path, home_dir(), |name| env::var(name).ok());
+    }
 
     let directory = data_dir();
     if directory.is_none() {
@@ -46,40 +64,252 @@ pub fn get_filename() -> PathBuf {
     filename.push("todo-rs");
     filename.push("tasks.json");
 
-    filename
+    let mut filename_yaml = filename.clone();
+    filename_yaml.set_extension("yaml");
+    if !filename.exists() && filename_yaml.exists() {
+        return Ok(filename_yaml);
+    }
+
+    Ok(filename)
 }
 
-// Read tasks from the json file, if available
-pub fn load_tasks(filename: &PathBuf, tasks: &mut Vec<Task>) -> Result<()> {
-    if filename.exists() {
-        let json_string = read_to_string(filename).map_err(FileError::Io)?;
-        let mut loaded_tasks: Vec<Task> = serde_json::from_str(json_string.as_str()).map_err(FileError::Deserialize)?;
-        tasks.append(&mut loaded_tasks);
+// Expand a leading '~' to the home directory, and environment variables ('$VAR' or '${VAR}')
+// to their values. Undefined variables are an error, rather than being left in the path.
+fn expand_path<F>(path: &str, home: Option<PathBuf>, lookup: F) -> Result<PathBuf>
+where
+    F: Fn(&str) -> Option<String> {
+    let mut expanded = String::new();
+    let mut rest = path;
+    if let Some(after_tilde) = path.strip_prefix('~') {
+        if after_tilde.is_empty() || after_tilde.starts_with(['/', '\\']) {
+            let home = home.ok_or(FileError::InvalidPath(String::from("home directory not found")))?;
+            expanded.push_str(&home.to_string_lossy());
+            rest = after_tilde;
+        }
+    }
+
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
+
+        let name: String = if chars.next_if_eq(&'{').is_some() {
+            let mut name = String::new();
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(c) => name.push(c),
+                    None => return Err(FileError::InvalidPath(format!("missing '}}' in {}", path))),
+                }
+            }
+            name
+        } else {
+            let mut name = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                name.push(c);
+            }
+            name
+        };
+
+        if name.is_empty() {
+            expanded.push('$'); // A lone '$' is kept as is
+            continue;
+        }
+        let value = lookup(&name).ok_or(FileError::InvalidPath(format!("environment variable {} is not set", name)))?;
+        expanded.push_str(&value);
+    }
+
+    Ok(PathBuf::from(expanded))
+}
+
+// Filename of a named list, which is kept next to the given tasks file (e.g. 'work.json')
+pub fn list_filename(filename: &Path, name: &str) -> PathBuf {
+    let extension = filename.extension().map_or(String::from("json"), |e| e.to_string_lossy().to_string());
+    filename.with_file_name(format!("{}.{}", name, extension))
+}
+
+// Names of all lists stored next to the tasks file, i.e. the files with the same extension in
+// the same directory that can be read as a tasks file. Other files with that extension (e.g. a
+// 'package.json') are not lists. Backups have a numbered extension, so are not included.
+pub fn list_names(filename: &Path) -> Vec<String> {
+    let directory = filename.parent().unwrap_or(Path::new("."));
+    let Ok(entries) = read_dir(directory) else {
+        return vec![];
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension() == filename.extension())
+        .filter(|path| read_to_string(path).is_ok_and(|contents| parse_tasks(&contents, is_yaml(path)).is_ok()))
+        .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+        .collect();
+    names.sort();
+    names
+}
+
+// Check whether a file should be read and written as YAML, based on its extension
+fn is_yaml(filename: &Path) -> bool {
+    matches!(filename.extension().and_then(|e| e.to_str()), Some("yaml" | "yml"))
+}
+
+// Version of the save file format, stored in the metadata of the save file
+const SCHEMA_VERSION: u32 = 1;
+
+// Metadata of the save file, which is not used by the program itself but kept for hand-editing
+// (e.g. a title and notes about the list). Unknown keys are kept as well.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Meta {
+    #[serde(default)]
+    pub schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+// Contents of the save file. Besides the tasks, the counter for the stable task ids is stored
+// so that ids of removed tasks are never reused.
+#[derive(Serialize)]
+struct SaveData<'a> {
+    meta: Meta,
+    next_id: u64,
+    tasks: &'a [Task],
+}
+
+// Save file formats that can be read. Older versions saved only the list of tasks, or no metadata.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LoadData {
+    Current {
+        #[serde(default)]
+        meta: Meta,
+        next_id: u64,
+        tasks: Vec<Task>,
+    },
+    Legacy(Vec<Task>),
+}
+
+// Tasks, next stable id and metadata as read from a save file
+#[derive(Debug, Default)]
+pub struct LoadedList {
+    pub tasks: Vec<Task>,
+    pub next_id: u64,
+    pub meta: Meta,
+}
+
+// Read tasks, the next stable id and the metadata from the tasks file, if available (a missing
+// file is an empty list). If the file cannot be deserialized (e.g. after a crash during writing), fall back to the most recent valid
+// backup file.
+pub fn load_tasks(filename: &PathBuf) -> Result<LoadedList> {
+    let mut list = if filename.exists() {
+        let contents = read_to_string(filename).map_err(FileError::Io)?;
+        let list = match parse_tasks(&contents, is_yaml(filename)) {
+            Ok(loaded) => loaded,
+            Err(e) => match load_from_backup(filename) {
+                Some((loaded, backup)) => {
+                    log_error(&e);
+                    log(format!("load backup={:?}", backup));
+                    info(format!("Tasks file could not be read. Loaded backup file {:?} instead\n", backup));
+                    loaded
+                },
+                None => return Err(e),
+            }
+        };
+        log(format!("load file={:?} tasks={}", filename, list.tasks.len()));
+        list
     } else {
-        println!("No previous tasks file found. Is this the first time you run this program?\n")
+        LoadedList::default()
     };
 
-    Ok(())
+    // Tasks saved by older versions have no stable id yet
+    assign_missing_ids(&mut list.tasks, &mut list.next_id);
+
+    Ok(list)
 }
 
-// Serialize data and save file
-pub fn save_file(filename: &PathBuf, tasks: &Vec<Task>) -> Result<()> {
-    let data_json = serde_json::to_string(&tasks).map_err(FileError::Serialize)?;
+// Deserialize tasks, the next stable id and the metadata from JSON or YAML. An empty (or
+// whitespace-only) file is treated as an empty task list. For the legacy format, the next stable
+// id is returned as 0.
+fn parse_tasks(contents: &str, yaml: bool) -> Result<LoadedList> {
+    if contents.trim().is_empty() {
+        return Ok(LoadedList::default());
+    }
+    let data = if yaml {
+        serde_yaml::from_str(contents).map_err(FileError::DeserializeYaml)?
+    } else {
+        serde_json::from_str(contents).map_err(FileError::Deserialize)?
+    };
+    match data {
+        LoadData::Current { meta, next_id, tasks } => Ok(LoadedList { tasks, next_id, meta }),
+        LoadData::Legacy(tasks) => Ok(LoadedList { tasks, ..LoadedList::default() }),
+    }
+}
+
+// Find the newest backup file that can be deserialized. Returns the loaded data and the backup used.
+fn load_from_backup(filename: &PathBuf) -> Option<(LoadedList, PathBuf)> {
+    let max_undos = 10;
+
+    for i in 0..max_undos+1 {
+        let mut backup = PathBuf::from(filename);
+        backup.set_extension( format!("{:03}", i) );
+
+        if let Ok(contents) = read_to_string(&backup) {
+            if let Ok(loaded) = parse_tasks(&contents, is_yaml(filename)) {
+                return Some((loaded, backup));
+            }
+        }
+    }
+
+    None
+}
+
+// Serialize data and save file. The metadata is saved with the current schema version.
+pub fn save_file(filename: &PathBuf, tasks: &[Task], next_id: u64, meta: &Meta) -> Result<()> {
+    let meta = Meta { schema_version: SCHEMA_VERSION, ..meta.clone() };
+    let data = SaveData { meta, next_id, tasks };
+    let contents = if is_yaml(filename) {
+        serde_yaml::to_string(&data).map_err(FileError::SerializeYaml)?
+    } else {
+        serde_json::to_string(&data).map_err(FileError::Serialize)?
+    };
 
     // Create directory if it does not yet exist
     let parent_dir = filename.parent().unwrap();
     if !parent_dir.exists() {
         create_dir(parent_dir).map_err(FileError::CreateDir)?;
-        println!("Creating tasks file: {:?}\n", filename)
+        info(format!("Creating tasks file: {:?}\n", filename))
     };
 
-    // Create a backup
+    save_contents(filename, &contents, write_contents)
+}
+
+fn write_contents(filename: &Path, contents: &str) -> io::Result<()> {
+    let mut file = File::create(filename)?;
+    write!(file, "{contents}")
+}
+
+// Back up the tasks file and write the new contents with 'write_file'. If writing fails (e.g.
+// the disk is full), the backup is put back, so the tasks file is never left empty or missing.
+fn save_contents<W>(filename: &PathBuf, contents: &str, write_file: W) -> Result<()>
+where
+    W: FnOnce(&Path, &str) -> io::Result<()> {
+    let existed = filename.exists();
     create_backup(filename)?;
 
-    // Save the file
-    let mut file = File::create(filename).map_err(FileError::Io)?;
-    write!(file, "{data_json}").map_err(FileError::Io)?;
+    if let Err(e) = write_file(filename, contents) {
+        log(format!("save failed file={:?} error={:?}", filename, e));
+        if filename.exists() {
+            let _ = remove_file(filename); // Partially written
+        }
+        let restored = restore_backups(filename, existed);
+        log(format!("rollback file={:?} restored={}", filename, restored.is_ok()));
+        return Err(FileError::Io(e));
+    }
 
+    log(format!("save file={:?} bytes={}", filename, contents.len()));
     Ok(())
 }
 
@@ -105,12 +335,70 @@ fn create_backup(filename: &PathBuf) -> Result<()> {
     let mut backup_newest = PathBuf::from(filename);
     backup_newest.set_extension("000");
     if filename.exists() {
-        rename(filename, backup_newest).map_err(FileError::Io)?;
+        rename(filename, &backup_newest).map_err(FileError::Io)?;
+        log(format!("backup file={:?} backups={}", backup_newest, count_backups(filename)));
+    }
+
+    Ok(())
+}
+
+// Undo 'create_backup' after a failed save: the newest backup becomes the tasks file again (if
+// there was one before saving), and the other backups move back one place. The oldest backup was
+// overwritten by 'create_backup', so that undo step is lost.
+fn restore_backups(filename: &PathBuf, restore_file: bool) -> Result<()> {
+    let max_undos = 10;
+    let backup_path = |i: usize| {
+        let mut backup = PathBuf::from(filename);
+        backup.set_extension( format!("{:03}", i) );
+        backup
+    };
+
+    if restore_file {
+        rename(backup_path(0), filename).map_err(FileError::Io)?;
+    }
+    for i in 0..max_undos {
+        if backup_path(i + 1).exists() {
+            rename(backup_path(i + 1), backup_path(i)).map_err(FileError::Io)?;
+        }
     }
 
     Ok(())
 }
 
+// Restore the state from before the latest 'steps' changes, as numbered by 'undo --list'. The
+// current state is backed up first, so recovering can itself be undone. Returns a summary of
+// the changes made to the tasks. With 'dry_run', nothing is written.
+pub fn recover_backup(filename: &PathBuf, steps: usize, dry_run: bool) -> Result<String> {
+    let backup_path = |i: usize| {
+        let mut backup = PathBuf::from(filename);
+        backup.set_extension( format!("{:03}", i) );
+        backup
+    };
+    let available = count_backups(filename);
+    if available == 0 {
+        return Err(FileError::BackupMissing);
+    } else if steps == 0 || steps > available {
+        return Err(FileError::BackupNotFound(steps, available));
+    }
+
+    let yaml = is_yaml(filename);
+    let contents = read_to_string(backup_path(steps - 1)).map_err(FileError::Io)?;
+    let recovered = parse_tasks(&contents, yaml)?.tasks;
+    let current = read_to_string(filename)
+        .ok()
+        .and_then(|contents| parse_tasks(&contents, yaml).ok())
+        .map(|list| list.tasks)
+        .unwrap_or_default();
+
+    // Saved like any change, so a failed write puts the current tasks back
+    if !dry_run {
+        save_contents(filename, &contents, write_contents)?;
+        log(format!("recover file={:?} steps={}", filename, steps));
+    }
+
+    Ok(summarize_changes(&current, &recovered))
+}
+
 // Undo last operation by rolling back files
 pub fn roll_back_file(filename: &PathBuf) -> Result<()> {
     let max_undos = 10;
@@ -139,5 +427,514 @@ pub fn roll_back_file(filename: &PathBuf) -> Result<()> {
         }   
     }
 
+    log(format!("undo file={:?} backups={}", filename, count_backups(filename)));
+    match count_backups(filename) {
+        0 => info("No more undos available"),
+        1 => info("1 undo step remaining"),
+        n => info(format!("{} undo steps remaining", n)),
+    }
+
+    Ok(())
+}
+
+// Count the backup files that are available as undo steps
+fn count_backups(filename: &Path) -> usize {
+    let max_undos = 10;
+
+    (0..max_undos+1)
+        .take_while(|i| {
+            let mut backup = PathBuf::from(filename);
+            backup.set_extension( format!("{:03}", i) );
+            backup.exists()
+        })
+        .count()
+}
+
+// Stable ids of the tasks with a note file in any of the backups. An undo or recover can bring
+// these tasks back, so their note files are kept. Backups that cannot be read are skipped.
+pub fn backup_note_ids(filename: &Path) -> Vec<u64> {
+    let max_undos = 10;
+
+    let mut ids = Vec::new();
+    for i in 0..max_undos+1 {
+        let mut backup = PathBuf::from(filename);
+        backup.set_extension( format!("{:03}", i) );
+
+        if let Some(loaded) = read_to_string(&backup).ok().and_then(|contents| parse_tasks(&contents, is_yaml(filename)).ok()) {
+            ids.extend(note_file_ids(&loaded.tasks));
+        }
+    }
+    ids
+}
+
+// Describe what the next undo would change, one line per task. See 'diff_tasks'.
+pub fn preview_undo(filename: &PathBuf, config: &Config) -> Result<Vec<String>> {
+    let (current, older) = undo_states(filename)?;
+    Ok(diff_tasks(&current, &older, config))
+}
+
+// Number of tasks that the next undo would add or remove, e.g. all tasks when undoing 'purge'.
+// Changes to tasks are not counted. Without backups, the undo changes nothing.
+pub fn undo_task_count(filename: &PathBuf) -> Result<usize> {
+    match undo_states(filename) {
+        Ok((current, older)) => Ok(tasks_added_or_removed(&current, &older)),
+        Err(FileError::BackupMissing) => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+// The current tasks, and the tasks in the newest backup that the next undo restores
+fn undo_states(filename: &PathBuf) -> Result<(Vec<Task>, Vec<Task>)> {
+    let mut backup = PathBuf::from(filename);
+    backup.set_extension("000");
+    if !backup.exists() {
+        return Err(FileError::BackupMissing);
+    }
+
+    let yaml = is_yaml(filename);
+    let older = parse_tasks(&read_to_string(&backup).map_err(FileError::Io)?, yaml)?.tasks;
+    let current = read_to_string(filename)
+        .ok()
+        .and_then(|contents| parse_tasks(&contents, yaml).ok())
+        .map(|list| list.tasks)
+        .unwrap_or_default();
+
+    Ok((current, older))
+}
+
+// Show the available undo steps: for each backup file, when it was made and what changed
+// between it and the next newer state
+pub fn list_undo_steps(filename: &PathBuf, config: &Config) -> Result<()> {
+    let max_undos = 10;
+
+    let read_state = |path: &PathBuf| -> Option<Vec<Task>> {
+        let contents = read_to_string(path).ok()?;
+        parse_tasks(&contents, is_yaml(filename)).ok().map(|list| list.tasks)
+    };
+
+    let mut newer_state = read_state(filename).unwrap_or_default();
+    let mut number_of_steps = 0;
+    for i in 0..max_undos+1 {
+        let mut backup = PathBuf::from(filename);
+        backup.set_extension( format!("{:03}", i) );
+        if !backup.exists() {
+            break;
+        }
+
+        let modified = backup
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .map_err(FileError::Io)?;
+        let modified = DateTime::<Local>::from(modified).format("%Y-%m-%d %H:%M:%S");
+
+        let older_state = read_state(&backup);
+        let changes = match &older_state {
+            Some(older_state) => summarize_changes(older_state, &newer_state),
+            None => String::from("Unreadable backup file"),
+        };
+
+        if number_of_steps == 0 {
+            println!("  Undos  Saved                Changes undone");
+        }
+        println!("  {:>5}  {:<20} {}", i + 1, modified, changes);
+        if let Some(older_state) = &older_state {
+            for line in diff_tasks(&newer_state, older_state, config) {
+                println!("{:>31}{}", "", line);
+            }
+        }
+
+        newer_state = older_state.unwrap_or_default();
+        number_of_steps += 1;
+    }
+
+    if number_of_steps == 0 {
+        println!("No undos are available");
+    }
+    println!();
+
     Ok(())
 }
+
+// The changes between the current file and each of its backups, newest first. Each entry is the
+// time the newer file was saved and a summary of the changes made at that time.
+fn history_entries(filename: &PathBuf) -> Result<Vec<(String, String)>> {
+    let max_undos = 10;
+
+    let read_state = |path: &PathBuf| -> Option<Vec<Task>> {
+        let contents = read_to_string(path).ok()?;
+        parse_tasks(&contents, is_yaml(filename)).ok().map(|list| list.tasks)
+    };
+    let modified = |path: &PathBuf| -> Result<String> {
+        let modified = path
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .map_err(FileError::Io)?;
+        Ok(DateTime::<Local>::from(modified).format("%Y-%m-%d %H:%M:%S").to_string())
+    };
+
+    let mut entries = vec![];
+    if !filename.exists() {
+        return Ok(entries);
+    }
+    let mut newer = filename.clone();
+    let mut newer_state = read_state(&newer);
+    for i in 0..max_undos+1 {
+        let mut backup = PathBuf::from(filename);
+        backup.set_extension( format!("{:03}", i) );
+        if !backup.exists() {
+            break;
+        }
+
+        let older_state = read_state(&backup);
+        let changes = match (&older_state, &newer_state) {
+            (Some(older_state), Some(newer_state)) => summarize_changes(older_state, newer_state),
+            _ => String::from("Unreadable file"),
+        };
+        entries.push((modified(&newer)?, changes));
+
+        newer = backup;
+        newer_state = older_state;
+    }
+
+    Ok(entries)
+}
+
+pub fn show_history(filename: &PathBuf) -> Result<()> {
+    let entries = history_entries(filename)?;
+    if entries.is_empty() {
+        println!("No history is available");
+        return Ok(());
+    }
+
+    println!("  Saved                Changes");
+    for (modified, changes) in entries {
+        println!("  {:<20} {}", modified, changes);
+    }
+    println!();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{remove_dir_all, create_dir_all, write};
+
+    // Create an empty directory in the system's temp dir and return the tasks filename in it
+    fn test_filename(name: &str) -> PathBuf {
+        let mut directory = std::env::temp_dir();
+        directory.push(format!("todo-rs-test-{}-{}", name, std::process::id()));
+        if directory.exists() {
+            remove_dir_all(&directory).unwrap();
+        }
+        create_dir_all(&directory).unwrap();
+        directory.push("tasks.json");
+        directory
+    }
+
+    #[test]
+    fn test_list_names() {
+        let filename = test_filename("list_names");
+        assert!(list_names(&filename).is_empty());
+
+        write(&filename, "[]").unwrap();
+        write(list_filename(&filename, "work"), "[]").unwrap();
+        write(filename.with_extension("000"), "[]").unwrap();
+        write(filename.with_extension("yaml"), "[]").unwrap();
+        write(list_filename(&filename, "package"), "{\"name\": \"app\", \"version\": \"1.0.0\"}").unwrap();
+        assert_eq!(list_names(&filename), vec![String::from("tasks"), String::from("work")]);
+
+        remove_dir_all(filename.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_expand_path() {
+        let home = Some(PathBuf::from("/home/user"));
+        let lookup = |name: &str| if name == "DIR" { Some(String::from("/data")) } else { None };
+
+        assert_eq!(expand_path("/tmp/tasks.json", home.clone(), lookup).unwrap(), PathBuf::from("/tmp/tasks.json"));
+        assert_eq!(expand_path("~/todos/work.json", home.clone(), lookup).unwrap(), PathBuf::from("/home/user/todos/work.json"));
+        assert_eq!(expand_path("~other/tasks.json", home.clone(), lookup).unwrap(), PathBuf::from("~other/tasks.json"));
+        assert_eq!(expand_path("$DIR/tasks.json", home.clone(), lookup).unwrap(), PathBuf::from("/data/tasks.json"));
+        assert_eq!(expand_path("${DIR}_old/tasks.json", home.clone(), lookup).unwrap(), PathBuf::from("/data_old/tasks.json"));
+        assert_eq!(expand_path("/tmp/$/tasks.json", home.clone(), lookup).unwrap(), PathBuf::from("/tmp/$/tasks.json"));
+
+        assert!(matches!(
+            expand_path("$UNSET/tasks.json", home.clone(), lookup),
+            Result::Err(FileError::InvalidPath(..))
+        ));
+        assert!(matches!(
+            expand_path("${DIR/tasks.json", home.clone(), lookup),
+            Result::Err(FileError::InvalidPath(..))
+        ));
+        assert!(matches!(
+            expand_path("~/tasks.json", None, lookup),
+            Result::Err(FileError::InvalidPath(..))
+        ));
+    }
+
+    const TASKS_JSON: &str = r#"[{"name":"test","creation_date":"2025-01-01","due_date":null,"color":null,"note":""}]"#;
+
+    #[test]
+    fn test_load_tasks_empty_file() {
+        let filename = test_filename("empty");
+        write(&filename, " \n").unwrap();
+
+        let list = load_tasks(&filename).unwrap();
+        assert!(list.tasks.is_empty());
+        assert_eq!(list.next_id, 1);
+
+        remove_dir_all(filename.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_load_tasks_corrupt_file() {
+        let filename = test_filename("corrupt");
+        write(&filename, r#"[{"name":"te"#).unwrap();
+
+        // No backups available
+        assert!(matches!(
+            load_tasks(&filename),
+            Result::Err(FileError::Deserialize(..))
+        ));
+
+        // Newest backup is corrupt as well, older one is valid
+        let mut backup_newest = filename.clone();
+        backup_newest.set_extension("000");
+        write(&backup_newest, "{").unwrap();
+        let mut backup_older = filename.clone();
+        backup_older.set_extension("001");
+        write(&backup_older, TASKS_JSON).unwrap();
+
+        assert_eq!(load_tasks(&filename).unwrap().tasks.len(), 1);
+
+        remove_dir_all(filename.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_load_tasks_legacy_format() {
+        let filename = test_filename("legacy");
+        write(&filename, TASKS_JSON).unwrap();
+
+        // Stable ids are assigned on first load
+        let list = load_tasks(&filename).unwrap();
+        assert_eq!(list.tasks.len(), 1);
+        assert_eq!(list.next_id, 2);
+
+        remove_dir_all(filename.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_save_file_next_id() {
+        let filename = test_filename("next-id");
+        write(&filename, TASKS_JSON).unwrap();
+
+        let LoadedList { mut tasks, meta, .. } = load_tasks(&filename).unwrap();
+
+        // The counter is saved, so ids of removed tasks are not reused
+        tasks.clear();
+        assert!(matches!(
+            save_file(&filename, &tasks, 7, &meta),
+            Result::Ok(..)
+        ));
+
+        let loaded = load_tasks(&filename).unwrap();
+        assert!(loaded.tasks.is_empty());
+        assert_eq!(loaded.next_id, 7);
+
+        remove_dir_all(filename.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_save_contents_failure() {
+        let filename = test_filename("save-failure");
+        let mut backup = filename.clone();
+        backup.set_extension("000");
+        write(&filename, "current").unwrap();
+        write(&backup, "previous").unwrap();
+
+        // A write that fails halfway leaves the file and its backups as they were
+        let result = save_contents(&filename, "new", |filename, _| {
+            write(filename, "ne")?;
+            Err(io::Error::other("disk full"))
+        });
+        assert!(matches!(result, Result::Err(FileError::Io(..))));
+        assert_eq!(read_to_string(&filename).unwrap(), "current");
+        assert_eq!(read_to_string(&backup).unwrap(), "previous");
+        assert_eq!(count_backups(&filename), 1);
+
+        // Without a tasks file yet, no file is left behind
+        remove_file(&filename).unwrap();
+        let result = save_contents(&filename, "new", |_, _| Err(io::Error::other("no permission")));
+        assert!(matches!(result, Result::Err(FileError::Io(..))));
+        assert!(!filename.exists());
+        assert_eq!(read_to_string(&backup).unwrap(), "previous");
+
+        assert!(matches!(save_contents(&filename, "new", |filename, contents| write(filename, contents)), Result::Ok(..)));
+        assert_eq!(read_to_string(&filename).unwrap(), "new");
+
+        remove_dir_all(filename.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_save_file_meta() {
+        let filename = test_filename("meta");
+        write(&filename, r#"{"meta":{"title":"Work","notes":["Hand-edited"],"owner":"me"},"next_id":1,"tasks":[]}"#).unwrap();
+
+        let LoadedList { tasks, next_id, meta } = load_tasks(&filename).unwrap();
+        assert_eq!(meta.title, Some(String::from("Work")));
+        assert_eq!(meta.notes, vec![String::from("Hand-edited")]);
+        assert_eq!(meta.schema_version, 0);
+
+        // Metadata, including unknown keys, survives a round-trip and gets the schema version
+        save_file(&filename, &tasks, next_id, &meta).unwrap();
+        let loaded_meta = load_tasks(&filename).unwrap().meta;
+        assert_eq!(loaded_meta, Meta { schema_version: SCHEMA_VERSION, ..meta });
+        assert_eq!(loaded_meta.other.get("owner"), Some(&serde_json::json!("me")));
+
+        remove_dir_all(filename.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_yaml_format() {
+        let mut filename = test_filename("yaml");
+        filename.set_extension("yaml");
+        write(&filename, "").unwrap();
+
+        let LoadedList { mut next_id, meta, .. } = load_tasks(&filename).unwrap();
+
+        // Write JSON-derived tasks as YAML and read them back
+        let mut json_tasks = parse_tasks(TASKS_JSON, false).unwrap().tasks;
+        assign_missing_ids(&mut json_tasks, &mut next_id);
+        assert!(matches!(
+            save_file(&filename, &json_tasks, next_id, &meta),
+            Result::Ok(..)
+        ));
+        assert!(read_to_string(&filename).unwrap().contains("next_id: 2"));
+
+        let loaded = load_tasks(&filename).unwrap();
+        assert_eq!(loaded.tasks, json_tasks);
+        assert_eq!(loaded.next_id, 2);
+
+        remove_dir_all(filename.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_list_undo_steps() {
+        let filename = test_filename("undo-list");
+
+        // No backups available
+        assert!(matches!(
+            list_undo_steps(&filename, &Config::default()),
+            Result::Ok(..)
+        ));
+        assert!(matches!(
+            preview_undo(&filename, &Config::default()),
+            Result::Err(FileError::BackupMissing)
+        ));
+
+        save_file(&filename, &[], 0, &Meta::default()).unwrap();
+        write(&filename, TASKS_JSON).unwrap();
+        let LoadedList { mut tasks, next_id, meta } = load_tasks(&filename).unwrap();
+        save_file(&filename, &tasks, next_id, &meta).unwrap();
+
+        assert!(matches!(
+            list_undo_steps(&filename, &Config::default()),
+            Result::Ok(..)
+        ));
+
+        // After removing the task, the next undo restores it
+        tasks.clear();
+        save_file(&filename, &tasks, next_id, &meta).unwrap();
+        let preview = preview_undo(&filename, &Config::default()).unwrap();
+        assert_eq!(preview.len(), 1);
+        assert!(preview[0].contains("+ test"));
+        assert_eq!(undo_task_count(&filename).unwrap(), 1);
+
+        remove_dir_all(filename.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_history_entries() {
+        let filename = test_filename("history");
+        assert!(history_entries(&filename).unwrap().is_empty());
+
+        save_file(&filename, &[], 0, &Meta::default()).unwrap();
+        assert!(history_entries(&filename).unwrap().is_empty());
+
+        let other_filename = filename.with_file_name("other.json");
+        write(&other_filename, TASKS_JSON).unwrap();
+        let LoadedList { mut tasks, next_id, meta } = load_tasks(&other_filename).unwrap();
+        save_file(&filename, &tasks, next_id, &meta).unwrap();
+        tasks.clear();
+        save_file(&filename, &tasks, next_id, &meta).unwrap();
+
+        let changes: Vec<String> = history_entries(&filename)
+            .unwrap()
+            .into_iter()
+            .map(|(_, changes)| changes)
+            .collect();
+        assert_eq!(changes, vec![String::from("1 removed"), String::from("1 added")]);
+
+        remove_dir_all(filename.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_recover_backup() {
+        let filename = test_filename("recover");
+
+        assert!(matches!(
+            recover_backup(&filename, 1, false),
+            Result::Err(FileError::BackupMissing)
+        ));
+
+        // Save versions with 0, 0, 1 and 2 tasks
+        save_file(&filename, &[], 1, &Meta::default()).unwrap();
+        save_file(&filename, &[], 1, &Meta::default()).unwrap();
+        write(&filename, TASKS_JSON).unwrap();
+        let LoadedList { mut tasks, next_id, meta } = load_tasks(&filename).unwrap();
+        save_file(&filename, &tasks, next_id, &meta).unwrap();
+        tasks.push(tasks[0].clone());
+        save_file(&filename, &tasks, next_id, &meta).unwrap();
+
+        assert!(matches!(
+            recover_backup(&filename, 0, false),
+            Result::Err(FileError::BackupNotFound(0, 3))
+        ));
+        assert!(matches!(
+            recover_backup(&filename, 4, false),
+            Result::Err(FileError::BackupNotFound(4, 3))
+        ));
+
+        // A dry run does not change anything
+        let contents = read_to_string(&filename).unwrap();
+        recover_backup(&filename, 3, true).unwrap();
+        assert_eq!(read_to_string(&filename).unwrap(), contents);
+
+        // Going back three steps, the current state becomes the newest backup
+        assert_eq!(recover_backup(&filename, 3, false).unwrap(), String::from("2 removed"));
+        assert!(load_tasks(&filename).unwrap().tasks.is_empty());
+        assert_eq!(count_backups(&filename), 4);
+        roll_back_file(&filename).unwrap();
+        assert_eq!(count_backups(&filename), 3);
+        assert_eq!(load_tasks(&filename).unwrap().tasks.len(), 2);
+
+        remove_dir_all(filename.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_backup_note_ids() {
+        let filename = test_filename("backup-note-ids");
+        let note_task = |id: u64| format!(r#"[{{"id":{id},"name":"test","creation_date":"2025-01-01","due_date":null,"color":null,"note":"","note_file":true}}]"#);
+        assert!(backup_note_ids(&filename).is_empty());
+
+        write(&filename, note_task(1)).unwrap();
+        write(filename.with_extension("000"), note_task(2)).unwrap();
+        write(filename.with_extension("001"), TASKS_JSON).unwrap();
+        write(filename.with_extension("002"), "corrupt").unwrap();
+        write(filename.with_extension("010"), note_task(3)).unwrap();
+        assert_eq!(backup_note_ids(&filename), vec![2, 3]);
+
+        remove_dir_all(filename.parent().unwrap()).unwrap();
+    }
+}
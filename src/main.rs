@@ -1,11 +1,21 @@
 mod color;
+mod commands;
+mod config;
 mod file_io;
+mod notes;
+mod output;
 mod task;
 
 use std::env;
-use std::path::PathBuf;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use chrono::NaiveDate;
 
+use color::Colorize;
+use config::{ColorWhen, Config, GlobalOptions};
 use file_io::get_filename;
+use notes::get_notes_dir;
+use task::{ArgError, ListScope};
 
 const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -15,53 +25,182 @@ const PKG_LICENSE: &str = env!("CARGO_PKG_LICENSE");
 
 
 fn main() {
-    let filename: PathBuf = get_filename();
-    let mut tasks: Vec<task::Task> = vec![];
-
-    // Load tasks if any
-    match file_io::load_tasks(&filename, &mut tasks) {
-        Ok(data) => data,
+    // Read global options, followed by the command argument
+    let mut dry_run = false;
+    let mut custom_filename = env::var("TODO_FILE").ok();
+    let mut list_name = None;
+    let mut explicit_file = false;
+    let mut options = GlobalOptions::default();
+    let mut args_iter = env::args().skip(1).peekable(); // Skip the first argument
+    while let Some(option) = args_iter.next_if(|arg| arg.starts_with('-')) {
+        match option.as_str() {
+            "--quiet" | "-q" => options.quiet = true,
+            "--dry-run" => dry_run = true,
+            "--verbose" => options.verbose = true,
+            "--version" | "-V" => {
+                println!("{}", version_info());
+                std::process::exit(0);
+            },
+            "--help" | "-h" => {
+                let _ = task::show_help(std::iter::empty());
+                std::process::exit(0);
+            },
+            "--file" => {
+                match args_iter.next() {
+                    Some(path) => {
+                        custom_filename = Some(path);
+                        explicit_file = true;
+                    },
+                    None => {
+                        eprintln!("{}", "Expected a path after '--file'\n".red_err());
+                        std::process::exit(1);
+                    }
+                }
+            },
+            "--list" => {
+                match args_iter.next() {
+                    Some(name) => list_name = Some(name),
+                    None => {
+                        eprintln!("{}", "Expected a list name after '--list'\n".red_err());
+                        std::process::exit(1);
+                    }
+                }
+            },
+            "--color" => {
+                options.color_when = match args_iter.next().as_deref() {
+                    Some("always") => ColorWhen::Always,
+                    Some("never") => ColorWhen::Never,
+                    Some("auto") => ColorWhen::Auto,
+                    _ => {
+                        eprintln!("{}", "Expected 'always', 'never' or 'auto' after '--color'\n".red_err());
+                        std::process::exit(1);
+                    }
+                };
+            },
+            other => {
+                eprintln!("{}", format!("Unknown option given: {}\n", other).red_err());
+                std::process::exit(1);
+            }
+        }
+    }
+    let config = match Config::new(options, |name| env::var(name).ok()) {
+        Ok(config) => config,
         Err(e) => {
-            eprintln!("{}", e);
+            eprintln!("{}", e.to_string().red_err());
             std::process::exit(1);
         }
     };
+    color::configure(&config);
+    output::configure(&config);
+    for warning in &config.warnings {
+        eprintln!("{}", warning.yellow_err());
+    }
 
-    // Read the command argument
-    let mut args_iter = env::args();
-    let _ = args_iter.next(); // Skip the first argument
     let command: String = match args_iter.next() {
         Some(arg) => arg,
         None => {
-            eprintln!("No arguments given. Specify \'todo help\' to learn how to use this program\n");
+            eprintln!("{}", "No arguments given. Specify \'todo help\' to learn how to use this program\n".red_err());
             std::process::exit(1);
         }
     };
 
-    // Call the corresponding method
+    let default_list = custom_filename.is_none() && list_name.is_none();
+    let explicit_list = explicit_file || list_name.is_some();
+    let filename: PathBuf = match get_filename(custom_filename) {
+        Ok(filename) => filename,
+        Err(e) => {
+            eprintln!("{}", e.to_string().red_err());
+            std::process::exit(1);
+        }
+    };
+    let filename = match list_name {
+        Some(name) => match task::validate_list_name(&name) {
+            Ok(..) => file_io::list_filename(&filename, &name),
+            Err(e) => {
+                eprintln!("{}", e.to_string().red_err());
+                std::process::exit(1);
+            }
+        },
+        None => filename,
+    };
+    output::log(format!("command name={} file={:?} args={:?}", command, filename, env::args().skip(1).collect::<Vec<String>>()));
+    let first_run = default_list && !filename.exists();
+    if !filename.exists() {
+        output::info(format!("No tasks file found yet. It will be created at {:?} when tasks are added\n", filename));
+    }
+
+    // Load tasks if any
+    let file_io::LoadedList { mut tasks, mut next_id, meta } = match file_io::load_tasks(&filename) {
+        Ok(list) => list,
+        Err(e) => {
+            output::log_error(&e);
+            eprintln!("{}", e.to_string().red_err());
+            std::process::exit(1);
+        }
+    };
+
+    // Help new users get started with an example task. It is saved with the first change.
+    let today = config.today();
+    if first_run && task::example_task_enabled(env::var("TODO_EXAMPLE_TASK").ok()) {
+        task::add_example_task(&mut tasks, &mut next_id, today);
+    }
+
     let command_str = command.as_str();
+    let list = ListScope { filename: filename.clone(), explicit: explicit_list, list_names: file_io::list_names, load_tasks: read_tasks };
+    let (loaded_tasks, loaded_next_id) = (tasks.clone(), next_id);
+    let mut moved_to = None;
+
+    // Call the corresponding method
     let result = match command_str {
-        "add"     => task::create_task(&mut tasks, args_iter),
-
-        "due"     => task::add_duedate(&mut tasks, args_iter),
-        "note"    => task::add_note(&mut tasks, args_iter),
-        "color"   => task::set_task_color(&mut tasks, args_iter),
-        "rename"  => task::rename_task(&mut tasks, args_iter),
-        "remove"  => task::delete_task(&mut tasks, args_iter),
-
-        "list"    => task::list_tasks(&tasks, args_iter),
-        "show"    => task::show_task(&tasks, args_iter),
-        "sort"    => task::sort_tasks(&mut tasks, args_iter),
-        "undo"    => task::check_for_more_args(args_iter), // Only check args, nothing else to do
         "info" => {
-            println!("{PKG_NAME} version {PKG_VERSION}, written by {PKG_AUTHORS} and released under the {PKG_LICENSE} license\n{PKG_REPOSITORY}");
+            println!("{}", version_info());
+            println!("{}", task::info_summary(&tasks, today));
             std::process::exit(0);
         }
-        "help"    => task::show_help(args_iter),
-        other     => {
-            eprintln!("Unknown command given: {}\n", other);
-            std::process::exit(1);
+        "batch"   => run_batch(&mut tasks, &mut next_id, &list, &config, args_iter),
+        "undo" if args_iter.next_if_eq("--list").is_some() => {
+            // Read-only, so exit directly instead of rolling back
+            let result = task::check_for_more_args(args_iter)
+                .map_err(|e| e.to_string())
+                .and_then(|_| file_io::list_undo_steps(&filename, &config).map_err(|e| e.to_string()));
+            if let Err(e) = result {
+                eprintln!("{}", e.red_err());
+                std::process::exit(1);
+            }
+            std::process::exit(0);
         }
+        "history" => {
+            // Read-only, so exit directly instead of rolling back
+            let result = task::check_for_more_args(args_iter)
+                .map_err(|e| e.to_string())
+                .and_then(|_| file_io::show_history(&filename).map_err(|e| e.to_string()));
+            if let Err(e) = result {
+                eprintln!("{}", e.red_err());
+                std::process::exit(1);
+            }
+            std::process::exit(0);
+        }
+        "move-to-list" => move_to_list(&mut tasks, &filename, today, args_iter).map(|target| moved_to = Some(target)),
+        "recover" => {
+            // Works on the tasks file directly, so exit instead of saving
+            let result = task::parse_undo_steps(args_iter)
+                .map_err(|e| e.to_string())
+                .and_then(|steps| file_io::recover_backup(&filename, steps, dry_run).map_err(|e| e.to_string()));
+            match result {
+                Ok(changes) if dry_run => println!("{}", format!("Dry run: no changes were saved ({})", changes).yellow_fg()),
+                Ok(changes) => output::info(format!("Recovered earlier tasks: {}", changes).green_fg()),
+                Err(e) => {
+                    eprintln!("{}", e.red_err());
+                    std::process::exit(1);
+                }
+            }
+            std::process::exit(0);
+        }
+        "undo" => {
+            let task_count = file_io::undo_task_count(&filename).unwrap_or(0);
+            task::confirm_undo(task_count, dry_run, args_iter)
+        }
+        other     => run_command(other, &mut tasks, &mut next_id, &list, &config, args_iter),
     };
 
     // Check if method ran successfully and set flag for saving/undo
@@ -69,24 +208,62 @@ fn main() {
     let mut save_flag = false;
     match result {
         Ok(..) => {
-            if matches!(command_str, "add" | "due" | "note" | "color" | "rename" | "remove" | "sort") {
-                save_flag = true;
+            // Nothing is saved (or backed up) if the command left the tasks as they were
+            if modifies_tasks(command_str) {
+                save_flag = tasks != loaded_tasks || next_id != loaded_next_id;
             } else if matches!(command_str, "undo") {
                 undo_flag = true;
             }
         },
         Err(e) => {
-            eprintln!("{}", e);
+            output::log_error(&e);
+            eprintln!("{}", e.to_string().red_err());
             std::process::exit(1);
         }
     }
 
+    // For a dry run, show the resulting tasks instead of saving them
+    if dry_run && (save_flag || undo_flag) {
+        if save_flag {
+            let _ = task::list_tasks(&tasks, &list, today, &config, std::iter::empty());
+        } else {
+            match file_io::preview_undo(&filename, &config) {
+                Ok(lines) if lines.is_empty() => println!("Undo would not change any tasks"),
+                Ok(lines) => println!("Undo would make the following changes:\n{}", lines.join("\n")),
+                Err(e) => {
+                    eprintln!("{}", e.to_string().red_err());
+                    std::process::exit(1);
+                }
+            }
+        }
+        println!("{}", "Dry run: no changes were saved".yellow_fg());
+        return;
+    }
+
     // Save tasks to file OR roll back previous version of file (undo)
     if save_flag {
-        match file_io::save_file(&filename, &tasks) {
-            Ok(..) => (),
+        match file_io::save_file(&filename, &tasks, next_id, &meta) {
+            Ok(..) => {
+                // The other list of 'move-to-list' is saved after the current one. If that fails,
+                // the current list is rolled back, so the task is not lost from both lists.
+                if let Some((target_filename, target)) = moved_to {
+                    if let Err(e) = file_io::save_file(&target_filename, &target.tasks, target.next_id, &target.meta) {
+                        output::log_error(&e);
+                        let _ = file_io::roll_back_file(&filename);
+                        eprintln!("{}", ArgError::UnwritableFile(format!("{} ({})", target_filename.display(), e)).to_string().red_err());
+                        std::process::exit(1);
+                    }
+                }
+
+                // Notes in separate files that no task refers to anymore (e.g. of removed tasks),
+                // also not in the backups that an undo can restore
+                let mut used = task::note_file_ids(&tasks);
+                used.extend(file_io::backup_note_ids(&filename));
+                notes::remove_unused_notes(&get_notes_dir(&filename), &used);
+            },
             Err(e) => {
-                eprintln!("{}", e);
+                output::log_error(&e);
+                eprintln!("{}", e.to_string().red_err());
                 std::process::exit(1);
             }
         }
@@ -94,9 +271,199 @@ fn main() {
         match file_io::roll_back_file(&filename) {
             Ok(..) => (),
             Err(e) => {
-                eprintln!("{}", e);
+                output::log_error(&e);
+                eprintln!("{}", e.to_string().red_err());
                 std::process::exit(1);
             }
         }
     }
 }
+
+// Version, authors, license and repository of the program, as shown by 'info' and '--version'
+fn version_info() -> String {
+    format!("{PKG_NAME} version {PKG_VERSION}, written by {PKG_AUTHORS} and released under the {PKG_LICENSE} license\n{PKG_REPOSITORY}")
+}
+
+// Run a single command on the tasks
+fn run_command<T>(command: &str, tasks: &mut Vec<task::Task>, next_id: &mut u64, list: &ListScope, config: &Config, args_iter: T) -> Result<(), ArgError>
+where
+    T: Iterator<Item = String> {
+    let notes_dir = &get_notes_dir(&list.filename);
+    let today = config.today();
+    match command {
+        "add"     => task::create_task(tasks, next_id, today, config, args_iter),
+
+        "due"     => task::add_duedate(tasks, today, config, args_iter),
+        "note"    => task::add_note(tasks, today, config, args_iter),
+        "edit"    => task::edit_note(tasks, notes_dir, env::var("EDITOR").ok(), today, args_iter),
+        "color"   => task::set_task_color(tasks, today, args_iter),
+        "set"     => task::set_attributes(tasks, today, config, args_iter),
+        "rename"  => task::rename_task(tasks, today, config, args_iter),
+        "remove"  => task::delete_task(tasks, list, today, args_iter),
+        "subtask" => task::edit_subtasks(tasks, today, args_iter),
+        "block"   => task::block_task(tasks, today, args_iter),
+        "snooze"  => task::snooze_task(tasks, today, config, args_iter),
+        "estimate" => task::estimate_task(tasks, today, args_iter),
+        "url"     => task::set_url(tasks, today, args_iter),
+        "done"    => task::complete_task(tasks, today, config, args_iter),
+        "pin"     => task::pin_task(tasks, true, today, args_iter),
+        "unpin"   => task::pin_task(tasks, false, today, args_iter),
+        "purge"   => task::purge_tasks(tasks, list, args_iter),
+        "dedupe"  => task::dedupe_tasks(tasks, today, args_iter),
+
+        "list"    => task::list_tasks(tasks, list, today, config, args_iter),
+        "show"    => task::show_task(tasks, notes_dir, today, config, args_iter),
+        "open"    => task::open_url(tasks, args_iter),
+        "count"   => task::count_tasks(tasks, today, args_iter),
+        "notify"  => task::notify_tasks(tasks, today, args_iter),
+        "done-today"     => task::report_done(tasks, false, today, config, args_iter),
+        "done-this-week" => task::report_done(tasks, true, today, config, args_iter),
+        "stats"   => task::show_stats(tasks, today, config, args_iter),
+        "sort"    => task::sort_tasks(tasks, config.completed_last, args_iter),
+        "export"  => task::export_tasks(tasks, notes_dir, args_iter),
+        "schema"  => task::show_schema(args_iter),
+        "help"    => task::show_help(args_iter),
+        other     => Err(ArgError::UnknownCommand(other.to_string(), commands::closest(other).map(String::from))),
+    }
+}
+
+// Check whether a command changes the tasks, in which case they need to be saved
+fn modifies_tasks(command: &str) -> bool {
+    commands::find(command).is_some_and(|command| command.modifies_tasks)
+}
+
+// Read the tasks of a list, for commands that read the tasks file again (e.g. 'list --watch')
+fn read_tasks(filename: &Path) -> Result<Vec<task::Task>, ArgError> {
+    file_io::load_tasks(&filename.to_path_buf())
+        .map(|list| list.tasks)
+        .map_err(|e| ArgError::UnreadableFile(format!("{} ({})", filename.display(), e)))
+}
+
+// Move a task to another list, which is kept in a file next to the current tasks file. The
+// task gets a new stable id in the other list. Returns the filename and the tasks of the other
+// list, which are saved by the caller after the current list.
+fn move_to_list<T>(tasks: &mut Vec<task::Task>, filename: &Path, today: NaiveDate, args_iter: T) -> Result<(PathBuf, file_io::LoadedList), ArgError>
+where
+    T: Iterator<Item = String> {
+    let (task_id, list_name) = task::parse_move_args(tasks, args_iter)?;
+    let target_filename = file_io::list_filename(filename, &list_name);
+    if target_filename == filename {
+        return Err(ArgError::SameList(list_name));
+    }
+
+    let mut target = file_io::load_tasks(&target_filename)
+        .map_err(|e| ArgError::UnreadableFile(format!("{} ({})", target_filename.display(), e)))?;
+
+    let position = task::move_task(tasks, task_id, &get_notes_dir(filename), &mut target.tasks, &mut target.next_id, today);
+    task::report_move(&target.tasks, position, &list_name);
+
+    Ok((target_filename, target))
+}
+
+// Run all commands from a batch file, one command per line. Lines starting with '#' are
+// comments. Errors are reported per line; with '--stop-on-error' the batch is aborted at the
+// first error, without saving any changes.
+fn run_batch<T>(tasks: &mut Vec<task::Task>, next_id: &mut u64, list: &ListScope, config: &Config, args_iter: T) -> Result<(), ArgError>
+where
+    T: Iterator<Item = String> {
+    let mut args_iter = args_iter.peekable();
+    let stop_on_error = args_iter.next_if_eq("--stop-on-error").is_some();
+    let batch_filename = args_iter.next().ok_or(ArgError::ArgMissing(String::from("batch file")))?;
+    task::check_for_more_args(args_iter)?;
+
+    let contents = read_to_string(&batch_filename)
+        .map_err(|e| ArgError::UnreadableFile(format!("{} ({})", batch_filename, e)))?;
+
+    let mut number_of_errors = 0;
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut line_args = line.split_whitespace().map(String::from);
+        let command = line_args.next().unwrap();
+        let result = match command.as_str() {
+            name if commands::find(name).is_some_and(|command| !command.in_batch) => Err(ArgError::NotAllowedInBatch(command)),
+            _ => run_command(&command, tasks, next_id, list, config, line_args),
+        };
+
+        if let Err(e) = result {
+            output::log_error(&e);
+            eprint!("{}", format!("Line {}: {}", i + 1, e).red_err());
+            if stop_on_error {
+                return Err(ArgError::BatchAborted(i + 1));
+            }
+            number_of_errors += 1;
+        }
+    }
+
+    if number_of_errors > 0 {
+        eprintln!("{}", format!("Batch finished with {} error(s)", number_of_errors).red_err());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{remove_file, write};
+
+    #[test]
+    fn test_run_batch() {
+        let mut batch_filename = env::temp_dir();
+        batch_filename.push(format!("todo-rs-test-batch-{}.txt", std::process::id()));
+        write(&batch_filename, "# Comment\nadd Task 1\n\nadd Task 2\nfoo 1\nundo\ndue 2 2025-12-12\n").unwrap();
+        let batch_filename_string = batch_filename.to_str().unwrap().to_string();
+
+        let list = ListScope { filename: env::temp_dir().join("tasks.json"), explicit: false, list_names: |_| vec![], load_tasks: |_| Ok(vec![]) };
+        let mut tasks: Vec<task::Task> = vec![];
+        let mut next_id = 1;
+
+        // Errors are reported, but all other lines are executed
+        let args_iter_continue = vec![batch_filename_string.clone()].into_iter();
+        assert!(matches!(
+            run_batch(&mut tasks, &mut next_id, &list, &Config::default(), args_iter_continue),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks.len(), 2);
+
+        // Batch is aborted at the first error
+        let mut tasks: Vec<task::Task> = vec![];
+        let args_iter_stop = vec![String::from("--stop-on-error"), batch_filename_string].into_iter();
+        assert_eq!(
+            run_batch(&mut tasks, &mut next_id, &list, &Config::default(), args_iter_stop),
+            Err(ArgError::BatchAborted(5))
+        );
+
+        let args_iter_missing = vec![String::from("--stop-on-error")].into_iter();
+        assert!(matches!(
+            run_batch(&mut tasks, &mut next_id, &list, &Config::default(), args_iter_missing),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+
+        remove_file(&batch_filename).unwrap();
+
+        let args_iter_unreadable = vec![batch_filename.to_str().unwrap().to_string()].into_iter();
+        assert!(matches!(
+            run_batch(&mut tasks, &mut next_id, &list, &Config::default(), args_iter_unreadable),
+            Result::Err(ArgError::UnreadableFile(..))
+        ));
+    }
+
+    #[test]
+    fn test_commands_dispatched() {
+        // Commands that can be used in a batch are run by 'run_command', the others by 'main'
+        let run_by_main = ["move-to-list", "batch", "undo", "history", "recover", "info"];
+        let list = ListScope { filename: env::temp_dir().join("tasks.json"), explicit: false, list_names: |_| vec![], load_tasks: |_| Ok(vec![]) };
+        for command in commands::COMMANDS {
+            let mut tasks: Vec<task::Task> = vec![];
+            let mut next_id = 1;
+            let args_iter = vec![String::from("--unknown-option")].into_iter();
+            let result = run_command(command.name, &mut tasks, &mut next_id, &list, &Config::default(), args_iter);
+            assert_eq!(matches!(result, Err(ArgError::UnknownCommand(..))), !command.in_batch, "{}", command.name);
+            assert_eq!(run_by_main.contains(&command.name), !command.in_batch, "{}", command.name);
+        }
+    }
+}
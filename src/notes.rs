@@ -0,0 +1,81 @@
+use std::fs::{read_dir, read_to_string, remove_file};
+use std::path::{Path, PathBuf};
+
+use crate::output::log;
+
+// Directory in which notes that are stored in separate files are kept. Other lists than the
+// default 'tasks' list get their own directory, as stable ids are only unique within a list.
+pub fn get_notes_dir(filename: &Path) -> PathBuf {
+    let mut notes_dir = filename.parent().map_or(PathBuf::new(), PathBuf::from);
+    match filename.file_stem().and_then(|stem| stem.to_str()) {
+        Some("tasks") | None => notes_dir.push("notes"),
+        Some(stem) => notes_dir.push(format!("notes-{}", stem)),
+    }
+    notes_dir
+}
+
+// Filename of the note of a task, based on its stable id
+pub fn note_filename(notes_dir: &Path, id: u64) -> PathBuf {
+    let mut filename = PathBuf::from(notes_dir);
+    filename.push(format!("{}.md", id));
+    filename
+}
+
+// Read the note of a task from its separate file. A missing file is treated as an empty note.
+pub fn read_note(notes_dir: &Path, id: u64) -> String {
+    read_to_string(note_filename(notes_dir, id)).unwrap_or_default()
+}
+
+// Delete the note files that none of the given stable ids refer to, e.g. of tasks that were
+// removed. The ids of the tasks in the backups are included by the caller, so an undo finds the
+// notes of the tasks it restores. Other files in the directory are left alone, as is a file
+// that cannot be deleted, as the tasks themselves were saved.
+pub fn remove_unused_notes(notes_dir: &Path, used_ids: &[u64]) {
+    let Ok(entries) = read_dir(notes_dir) else {
+        return;
+    };
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        let id = path.extension()
+            .filter(|extension| *extension == "md")
+            .and_then(|_| path.file_stem()?.to_str()?.parse::<u64>().ok());
+        if id.is_some_and(|id| !used_ids.contains(&id)) && remove_file(&path).is_ok() {
+            log(format!("remove note={:?}", path));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    #[test]
+    fn test_get_notes_dir() {
+        assert_eq!(get_notes_dir(Path::new("/data/todo-rs/tasks.json")), PathBuf::from("/data/todo-rs/notes"));
+        assert_eq!(get_notes_dir(Path::new("/data/todo-rs/tasks.yaml")), PathBuf::from("/data/todo-rs/notes"));
+        assert_eq!(get_notes_dir(Path::new("/data/todo-rs/work.json")), PathBuf::from("/data/todo-rs/notes-work"));
+    }
+
+    #[test]
+    fn test_remove_notes() {
+        let mut notes_dir = std::env::temp_dir();
+        notes_dir.push(format!("todo-rs-test-remove-notes-{}", std::process::id()));
+        create_dir_all(&notes_dir).unwrap();
+        write(note_filename(&notes_dir, 1), "kept").unwrap();
+        write(note_filename(&notes_dir, 2), "removed").unwrap();
+        write(note_filename(&notes_dir, 3), "in a backup").unwrap();
+        let other = notes_dir.join("other.md");
+        write(&other, "not a note").unwrap();
+
+        remove_unused_notes(&notes_dir, &[1, 3, 4]);
+        assert_eq!(read_note(&notes_dir, 1), "kept");
+        assert!(!note_filename(&notes_dir, 2).exists());
+        assert_eq!(read_note(&notes_dir, 3), "in a backup");
+        assert!(other.exists());
+
+        // A missing directory is not an error
+        remove_unused_notes(&notes_dir.join("missing"), &[]);
+
+        remove_dir_all(&notes_dir).unwrap();
+    }
+}
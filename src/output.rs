@@ -0,0 +1,72 @@
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::{DateTime, Local};
+
+use crate::config::{Config, LogTarget};
+
+// Output settings from the config, set once by main with 'configure' before any command runs
+static QUIET: AtomicBool = AtomicBool::new(false);
+static LOG_TARGET: Mutex<Option<LogTarget>> = Mutex::new(None);
+
+pub fn configure(config: &Config) {
+    QUIET.store(config.quiet, Ordering::Relaxed);
+    if let Ok(mut target) = LOG_TARGET.lock() {
+        *target = config.log_target.clone();
+    }
+}
+
+// Print an informational message (e.g. a confirmation or notice), unless the user asked for
+// quiet output. Requested output such as the task list and errors are always printed.
+pub fn info<T: fmt::Display>(message: T) {
+    if !QUIET.load(Ordering::Relaxed) {
+        println!("{}", message);
+    }
+}
+
+// Write a debug log message, if logging is enabled. Messages are an event name followed by
+// 'key=value' fields, e.g. 'save file="tasks.json"'. Failing to write the log is ignored, as
+// it should never stop a command.
+pub fn log<T: fmt::Display>(message: T) {
+    let Some(target) = LOG_TARGET.lock().ok().and_then(|target| target.clone()) else {
+        return;
+    };
+
+    let line = log_line(Local::now(), &message.to_string());
+    match target {
+        LogTarget::Stderr => eprintln!("{}", line),
+        LogTarget::File(path) => {
+            let _ = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{}", line));
+        },
+    }
+}
+
+// Log an error with its variant (e.g. 'Io(...)'), which the user-facing message does not show
+pub fn log_error<E: fmt::Debug>(error: &E) {
+    log(format!("error value={:?}", error));
+}
+
+fn log_line(time: DateTime<Local>, message: &str) -> String {
+    format!("{} {}", time.format("%Y-%m-%dT%H:%M:%S%.3f%:z"), message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_log_line() {
+        let time = Local.with_ymd_and_hms(2025, 7, 17, 9, 30, 0).unwrap();
+        let line = log_line(time, "save file=\"tasks.json\"");
+        assert!(line.starts_with("2025-07-17T09:30:00.000"));
+        assert!(line.ends_with(" save file=\"tasks.json\""));
+    }
+}
@@ -1,58 +1,280 @@
+use std::cmp::Ordering;
 use std::env;
 use std::error;
 use std::fmt;
-use chrono::Datelike;
+use std::fs::{create_dir_all, metadata, read_to_string, remove_file, write};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{self, SystemTime};
+use chrono::{Datelike, Weekday};
 use serde::{Deserialize, Serialize};
-use chrono::{Local, NaiveDate};
+use chrono::{DateTime, Days, Duration, Months, NaiveDate, Utc};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 use crate::color::*;
+use crate::commands::{self, COMMANDS};
+use crate::config::Config;
+use crate::notes::{note_filename, read_note};
+use crate::output::info;
 
+mod filter;
+mod sort;
+
+use filter::TaskFilter;
+
+// Tasks are addressed by their position in the list, which changes when tasks are removed or
+// sorted. The 'id' field is a stable identifier that never changes and is never reused. It can
+// be given on the command line as 'id:N' and is used to refer from one task to another (e.g.
+// 'blocked_by'). An id of 0 means no id has been assigned yet.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Task {
+    #[serde(default)]
+    id: u64,
     name: String,
     creation_date: NaiveDate,
     due_date: Option<NaiveDate>,
     color: Option<Color>,
-    note: String
+    note: String,
+    #[serde(default)]
+    blocked_by: Option<u64>,
+    #[serde(default)]
+    subtasks: Vec<Subtask>,
+    #[serde(default)]
+    note_file: bool,
+    #[serde(default)]
+    start_date: Option<NaiveDate>,
+    #[serde(default)]
+    pinned: bool,
+    #[serde(default)]
+    modified_date: Option<NaiveDate>,
+    #[serde(default)]
+    completed_date: Option<NaiveDate>,
+    #[serde(default)]
+    estimate: Option<u32>,
+    #[serde(default)]
+    url: Option<String>
 }
 
-impl Task {
-    fn new(name: String) -> Self {
-        let dt = Local::now();
-        let creation_date = NaiveDate::from_ymd_opt(dt.year(), dt.month(), dt.day()).unwrap();
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Subtask {
+    text: String,
+    done: bool
+}
 
+impl Task {
+    fn new(name: String, today: NaiveDate) -> Self {
         Task{
+            id: 0,
             name,
-            creation_date,
+            creation_date: today,
             due_date: None,
             color: None,
-            note: String::new()
+            note: String::new(),
+            blocked_by: None,
+            subtasks: vec![],
+            note_file: false,
+            start_date: None,
+            pinned: false,
+            modified_date: Some(today),
+            completed_date: None,
+            estimate: None,
+            url: None
         }
     }
 
-    fn string_from_creation_date(&self) -> String {
-        self.creation_date.format("%Y-%m-%d").to_string()
+    // Record that the task was changed on 'today'
+    fn touch(&mut self, today: NaiveDate) {
+        self.modified_date = Some(today);
+    }
+
+    // Date of the last change. Tasks saved by older versions count as changed on creation.
+    fn last_modified(&self) -> NaiveDate {
+        self.modified_date.unwrap_or(self.creation_date)
     }
 
-    fn string_from_due_date(&self) -> String {
+    fn string_from_creation_date(&self, config: &Config) -> String {
+        config.format_date(self.creation_date)
+    }
+
+    // Format the due date either as YYYY-MM-DD or relative to today (e.g. 'in 3d')
+    fn string_from_due_date(&self, relative: bool, today: NaiveDate, config: &Config) -> String {
+        let due_date = self.plain_string_from_due_date(relative, today, config);
+        // Color the string red if due date is in the past, and yellow if it is due soon
+        if filter::overdue(today)(self) {
+            due_date.red_fg()
+        } else if filter::due_soon(today, config.soon_days)(self) {
+            due_date.yellow_fg()
+        } else {
+            due_date
+        }
+    }
+
+    // Same as string_from_due_date, but without coloring
+    fn plain_string_from_due_date(&self, relative: bool, today: NaiveDate, config: &Config) -> String {
         match self.due_date {
-            Some(date) => {
-                let mut due_date = date
-                    .format("%Y-%m-%d")
-                    .to_string();
-                // Color the string red if due date is in the past
-                let dt = Local::now();
-                let today = NaiveDate::from_ymd_opt(dt.year(), dt.month(), dt.day()).unwrap();
-                if date < today {
-                    due_date = due_date.red_fg();
-                }
-                due_date
-            }
+            Some(date) if relative => relative_date_string(date, today),
+            Some(date) => config.format_date(date),
             None => String::new()
         }
     }
 }
 
+// Stable ids of the tasks whose note is stored in a separate file
+pub fn note_file_ids(tasks: &[Task]) -> Vec<u64> {
+    tasks.iter().filter(|task| task.note_file).map(|task| task.id).collect()
+}
+
+// Assign stable ids to tasks that do not have one yet (e.g. tasks saved by an older version).
+// Also makes sure that 'next_id' is higher than any id in use.
+pub fn assign_missing_ids(tasks: &mut [Task], next_id: &mut u64) {
+    let max_id = tasks.iter().map(|task| task.id).max().unwrap_or(0);
+    *next_id = (*next_id).max(max_id + 1);
+
+    for task in tasks.iter_mut().filter(|task| task.id == 0) {
+        task.id = *next_id;
+        *next_id += 1;
+    }
+}
+
+// Check whether an example task is added on first run. It can be disabled by setting
+// TODO_EXAMPLE_TASK to '0', 'false', 'no' or 'off'.
+pub fn example_task_enabled(setting: Option<String>) -> bool {
+    !setting.is_some_and(|value| matches!(value.to_lowercase().as_str(), "0" | "false" | "no" | "off"))
+}
+
+// Add an example task for new users, which shows how tasks are listed and shown. It is a normal
+// task that can be removed.
+pub fn add_example_task(tasks: &mut Vec<Task>, next_id: &mut u64, today: NaiveDate) {
+    let mut task = Task::new(String::from("Welcome to ToDo! Run 'todo help' to learn more"), today);
+    task.note = String::from("This is an example task. Remove it with 'todo remove 1'.");
+    task.id = *next_id;
+    *next_id += 1;
+    tasks.push(task);
+}
+
+// Find the position of the task that blocks a task. Returns None if the task is not blocked or
+// if the blocking task no longer exists.
+fn blocker_position(tasks: &[Task], task: &Task) -> Option<usize> {
+    let blocker_id = task.blocked_by?;
+    tasks.iter().position(|t| t.id == blocker_id)
+}
+
+// Summarize the differences between two versions of the task list, e.g. '1 added, 2 changed'.
+// Tasks are matched by their stable id (or by name for tasks saved by older versions).
+pub fn summarize_changes(older: &[Task], newer: &[Task]) -> String {
+    let added = newer.iter().filter(|n| !older.iter().any(|o| same_task(o, n))).count();
+    let removed = older.iter().filter(|o| !newer.iter().any(|n| same_task(o, n))).count();
+    let changed = newer
+        .iter()
+        .filter(|n| older.iter().any(|o| same_task(o, n) && o != *n))
+        .count();
+    let moved = added == 0 && removed == 0 && changed == 0 && older != newer;
+
+    let mut summary = vec![];
+    for (count, description) in [(added, "added"), (removed, "removed"), (changed, "changed")] {
+        if count > 0 {
+            summary.push(format!("{} {}", count, description));
+        }
+    }
+    if moved {
+        summary.push(String::from("reordered"));
+    }
+
+    if summary.is_empty() {
+        String::from("No changes")
+    } else {
+        summary.join(", ")
+    }
+}
+
+// Check whether two versions of a task are the same task, by their stable id (or by name for
+// tasks saved by older versions)
+fn same_task(a: &Task, b: &Task) -> bool {
+    if a.id != 0 && b.id != 0 { a.id == b.id } else { a.name == b.name }
+}
+
+// Describe the differences between two versions of the task list, one line per task, e.g. to
+// preview an undo. Tasks only in 'to' are shown as added (green), tasks only in 'from' as
+// removed (red), and changed tasks with their changed fields, of which the new value is
+// highlighted. The markers '+', '-' and '~' keep the output readable without colors.
+pub fn diff_tasks(from: &[Task], to: &[Task], config: &Config) -> Vec<String> {
+    let mut lines = vec![];
+    for task in to.iter().filter(|t| !from.iter().any(|f| same_task(f, t))) {
+        lines.push(format!("+ {}", task.name).green_fg());
+    }
+    for task in from.iter().filter(|f| !to.iter().any(|t| same_task(f, t))) {
+        lines.push(format!("- {}", task.name).red_fg());
+    }
+    for task in to {
+        let Some(old_task) = from.iter().find(|f| same_task(f, task) && *f != task) else {
+            continue;
+        };
+        let changes: Vec<String> = diff_fields(old_task, config)
+            .into_iter()
+            .zip(diff_fields(task, config))
+            .filter(|((_, old_value, _), (_, new_value, _))| old_value != new_value)
+            .map(|((field, old_value, shown), (_, new_value, _))| if shown {
+                format!("{} {} -> {}", field, old_value, new_value.yellow_fg())
+            } else {
+                format!("{} {}", field, "changed".yellow_fg())
+            })
+            .collect();
+        if changes.is_empty() {
+            continue; // Only fields that are not shown, such as the modification date
+        }
+        lines.push(format!("~ {}: {}", old_task.name, changes.join(", ")));
+    }
+    lines
+}
+
+// Number of tasks that are only in 'from' or only in 'to', i.e. that were added or removed
+pub fn tasks_added_or_removed(from: &[Task], to: &[Task]) -> usize {
+    let added = to.iter().filter(|t| !from.iter().any(|f| same_task(f, t))).count();
+    let removed = from.iter().filter(|f| !to.iter().any(|t| same_task(f, t))).count();
+    added + removed
+}
+
+// Fields of a task compared by 'diff_tasks', with their values and whether the values are shown.
+// Values that are too long to show (e.g. notes) are only reported as changed.
+fn diff_fields(task: &Task, config: &Config) -> Vec<(&'static str, String, bool)> {
+    let date_string = |date: Option<NaiveDate>| date.map_or(String::from("none"), |d| config.format_date(d));
+    vec![
+        ("name", task.name.clone(), true),
+        ("due date", date_string(task.due_date), true),
+        ("color", task.color.as_ref().map_or("none", |c| c.name()).to_string(), true),
+        ("estimate", task.estimate.map_or(String::from("none"), |m| minutes_string_from(m as u64)), true),
+        ("url", task.url.clone().unwrap_or(String::from("none")), true),
+        ("snoozed until", date_string(task.start_date), true),
+        ("completed", date_string(task.completed_date), true),
+        ("pinned", String::from(if task.pinned { "yes" } else { "no" }), true),
+        ("blocked by", task.blocked_by.map_or(String::from("none"), |id| format!("id:{}", id)), true),
+        ("note", format!("{}{}", task.note_file, task.note), false),
+        ("subtasks", format!("{:?}", task.subtasks), false),
+    ]
+}
+
+// Describe a date relative to today, e.g. 'in 3d', 'today' or '5d ago'
+fn relative_date_string(date: NaiveDate, today: NaiveDate) -> String {
+    let days = (date - today).num_days();
+    match days {
+        0 => String::from("today"),
+        d if d > 0 => format!("in {}d", d),
+        d => format!("{}d ago", -d),
+    }
+}
+
+// Format a number of days, e.g. '1 day' or '12 days'
+fn days_string(days: i64) -> String {
+    if days == 1 || days == -1 {
+        format!("{} day", days)
+    } else {
+        format!("{} days", days)
+    }
+}
+
 // -- Error handling --
 type Result<T> = std::result::Result<T, ArgError>;
 
@@ -64,10 +286,58 @@ pub enum ArgError {
     TaskNotFound,
     IncorrectDateFormat,
     InvalidColor(String),
+    UnknownOption(String),
+    NotConfirmed,
+    NoTerminal,
+    FutureCreationDate,
+    CircularBlock,
+    AmbiguousTask(String),
+    DuplicateTask(usize),
+    DateOutOfRange,
+    NoteInFile,
+    InvalidTaskName,
+    InvalidSubtaskText,
+    EditorNotSet,
+    EditorNotFound(String),
+    UnknownExportFormat(String),
+    InvalidUndoSteps(String),
+    InvalidListName(String),
+    SameList(String),
+    ListNotExplicit(String, usize),
+    InvalidGrouping(String),
+    InvalidSortKey(String),
+    InvalidField(String),
+    EditorFailed(String),
+    InvalidUrl(String),
+    UrlNotSet,
+    OpenerFailed(String),
+    UnwritableFile(String),
+    UnknownHelpTopic(String, Option<String>, String),
+    UnknownCommand(String, Option<String>),
+    UnreadableFile(String),
+    NotAllowedInBatch(String),
+    BatchAborted(usize),
+    InvalidSubtaskAction(String),
+    InvalidSubtaskNumber(String),
+    InvalidDayCount(String),
+    InvalidEstimate(String),
+    NoteTooLong(usize, usize),
+    InvalidJson(String),
+    InvalidAge(String),
+    SubtaskNotFound,
+    ConflictingOptions(String, String),
+    OptionRequires(String, String),
+    MultipleErrors(Vec<ArgError>),
 }
 
 impl error::Error for ArgError { }
 
+impl From<UnknownColor> for ArgError {
+    fn from(error: UnknownColor) -> Self {
+        ArgError::InvalidColor(error.0)
+    }
+}
+
 impl fmt::Display for ArgError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -75,28 +345,192 @@ impl fmt::Display for ArgError {
             ArgError::TooManyArgs(e) => writeln!(f, "Too many arguments provided: {}", e),
             ArgError::InvalidTaskId(e) => writeln!(f, "Invalid task id provided: {}", e),
             ArgError::TaskNotFound => writeln!(f, "Task not found"),
+            ArgError::AmbiguousTask(e) => writeln!(f, "Multiple tasks match the given name: {}", e),
+            ArgError::DuplicateTask(e) => writeln!(f, "A task with the same name already exists (ID {}). Use \'--force\' to add it anyway", e),
+            ArgError::UnknownCommand(e, Some(closest)) => writeln!(f, "Unknown command given: {}. Did you mean \'{}\'?", e, closest),
+            ArgError::UnknownCommand(e, None) => writeln!(f, "Unknown command given: {}", e),
+            ArgError::UnreadableFile(e) => writeln!(f, "Unable to read file: {}", e),
+            ArgError::NotAllowedInBatch(e) => writeln!(f, "Command cannot be used in a batch file: {}", e),
+            ArgError::BatchAborted(e) => writeln!(f, "Batch aborted at line {}. No changes were saved", e),
+            ArgError::InvalidSubtaskAction(e) => writeln!(f, "Invalid subtask action provided (should be add, done or rm): {}", e),
+            ArgError::InvalidSortKey(e) => writeln!(f, "Invalid sort key provided: {} (expected \'key\', \'key:asc\' or \'key:desc\', where key is one of: {})", e, sort::SortKey::FIELDS.join(", ")),
+            ArgError::InvalidAge(e) => writeln!(f, "Invalid date or age provided (expected YYYY-MM-DD, Nd or Nw): {}", e),
+            ArgError::InvalidDayCount(e) => writeln!(f, "Invalid number of days provided (must be a positive integer): {}", e),
+            ArgError::InvalidEstimate(e) => writeln!(f, "Invalid estimate provided (must be a positive number of minutes): {}", e),
+            ArgError::InvalidJson(e) => writeln!(f, "Unable to read the task from JSON. Details:\n    {}", e),
+            ArgError::NoteTooLong(length, max) => writeln!(f, "The note would be {} characters long, which exceeds the maximum of {}. Use \'note --truncate\' to shorten it, or set TODO_MAX_NOTE_LENGTH", length, max),
+            ArgError::InvalidSubtaskNumber(e) => writeln!(f, "Invalid subtask number provided: {}", e),
+            ArgError::SubtaskNotFound => writeln!(f, "Subtask not found"),
             ArgError::IncorrectDateFormat => writeln!(f, "Incorrectly formatted date (should be of YYYY-MM-DD format)"),
-            ArgError::InvalidColor(e) => writeln!(f, "The requested color is not available: {}", e),
+            ArgError::UnknownHelpTopic(e, Some(closest), _) => writeln!(f, "No help available for: {}. Did you mean \'{}\'?", e, closest),
+            ArgError::UnknownHelpTopic(e, None, topics) => writeln!(f, "No help available for: {}. Available topics are: {}", e, topics),
+            ArgError::InvalidTaskName => writeln!(f, "Task names may not contain control characters, such as newlines or escape codes"),
+            ArgError::InvalidSubtaskText => writeln!(f, "Subtasks may not contain control characters, such as newlines or escape codes"),
+            ArgError::NoteInFile => writeln!(f, "The note of this task is stored in a separate file. Use \'todo edit\' to change it"),
+            ArgError::EditorNotSet => writeln!(f, "No editor available. Set the EDITOR environment variable (e.g. EDITOR=nano)"),
+            ArgError::InvalidListName(e) => writeln!(f, "Invalid list name: {} (only letters, digits, \'-\' and \'_\' are allowed)", e),
+            ArgError::SameList(e) => writeln!(f, "The task is already in list \'{}\'", e),
+            ArgError::ListNotExplicit(command, lists) => writeln!(f, "There are {} lists. Name the list explicitly with \'--list [name]\' to use \'{}\'", lists, command),
+            ArgError::InvalidField(e) => writeln!(f, "Unknown field: {}. Available fields are: {}", e, Column::DEFAULT.map(|column| column.name()).join(", ")),
+            ArgError::InvalidGrouping(e) => writeln!(f, "Invalid grouping: {} (expected \'color\' or \'week\')", e),
+            ArgError::InvalidUndoSteps(e) => writeln!(f, "Invalid number of undos provided (must be a positive integer): {}", e),
+            ArgError::UnknownExportFormat(e) => writeln!(f, "Unknown export format: {} (available formats: ical)", e),
+            ArgError::EditorNotFound(e) => writeln!(f, "Editor \'{}\' was not found. Set the EDITOR environment variable to an installed editor", e),
+            ArgError::EditorFailed(e) => writeln!(f, "Unable to run the editor: {}", e),
+            ArgError::InvalidUrl(e) => writeln!(f, "Invalid URL provided (should start with http:// or https://): {}", e),
+            ArgError::UrlNotSet => writeln!(f, "The task has no URL. Set one with \'todo url [task_id] [url]\'"),
+            ArgError::OpenerFailed(e) => writeln!(f, "Unable to open the URL: {}", e),
+            ArgError::UnwritableFile(e) => writeln!(f, "Unable to write file: {}", e),
+            ArgError::DateOutOfRange => writeln!(f, "The resulting date is out of range"),
+            ArgError::InvalidColor(e) => writeln!(f, "The requested color is not available: {}. Available colors are: {}", e, Color::all().join(", ")),
+            ArgError::UnknownOption(e) => writeln!(f, "Unknown option provided: {}", e),
+            ArgError::NotConfirmed => writeln!(f, "Operation cancelled"),
+            ArgError::FutureCreationDate => writeln!(f, "The creation date cannot be in the future"),
+            ArgError::CircularBlock => writeln!(f, "A task cannot be blocked by itself, directly or indirectly"),
+            ArgError::NoTerminal => writeln!(f, "Unable to ask for confirmation (not running in a terminal). Use '--yes' to skip confirmation"),
+            ArgError::ConflictingOptions(a, b) => writeln!(f, "Option \'{}\' cannot be combined with \'{}\'", a, b),
+            ArgError::OptionRequires(a, b) => writeln!(f, "Option \'{}\' can only be used together with \'{}\'", a, b),
+            ArgError::MultipleErrors(errors) => errors.iter().try_for_each(|e| write!(f, "{}", e)),
         }
     }
 }
 // -- End error handling --
 
-// Parse the task ID and check it is valid and exists. Return task_id - 1.
+// Parse the task ID and check it is valid and exists. Return task_id - 1. A stable id can be
+// given as 'id:N', in which case the current position of that task is returned. '$' and '^'
+// refer to the last and first task, as do 'last' and 'first' unless a task name has them as a word.
+// Anything else that is not a number is matched against the task names.
 fn parse_task_id(tasks: &[Task], task_id_opt: &Option<String>) -> Result<usize> {
     let task_id_string = match task_id_opt {
         None => return Err(ArgError::ArgMissing(String::from("task id"))),
         Some(task_id_string) => task_id_string.to_owned()
     };
 
-    let task_id = task_id_string
-        .parse::<usize>()
-        .map_err(|_| ArgError::InvalidTaskId(task_id_string))?;
+    // A task named e.g. 'Last call' is found by name, rather than being replaced by the last task.
+    // Only whole words count, so e.g. 'Blast radius' or 'First-aid kit' do not hide the keywords.
+    let names_contain = |word: &str| tasks.iter().any(|task| task.name
+        .split_whitespace()
+        .any(|name_word| name_word.trim_matches(|c: char| !c.is_alphanumeric()).eq_ignore_ascii_case(word)));
+    let last = tasks.len().checked_sub(1).ok_or(ArgError::TaskNotFound);
+    match task_id_string.as_str() {
+        "$" => return last,
+        "^" => return last.map(|_| 0),
+        "last" if !names_contain("last") => return last,
+        "first" if !names_contain("first") => return last.map(|_| 0),
+        _ => (),
+    }
 
-    if task_id > tasks.len() || task_id == 0 {
+    if let Some(stable_id_string) = task_id_string.strip_prefix("id:") {
+        let stable_id = parse_id_number(stable_id_string).ok_or(ArgError::InvalidTaskId(task_id_string.clone()))?;
+        return tasks
+            .iter()
+            .position(|task| task.id == stable_id)
+            .ok_or(ArgError::TaskNotFound);
+    }
+
+    // Anything that looks like a number is an ID, even if it is not written as one. Otherwise
+    // e.g. '+3' or ' 3' would silently be used to search the task names instead.
+    if !looks_like_number(&task_id_string) {
+        return find_task_by_name(tasks, &task_id_string);
+    }
+    let task_id = parse_id_number(&task_id_string).ok_or(ArgError::InvalidTaskId(task_id_string.clone()))?;
+
+    if task_id > tasks.len() as u64 || task_id == 0 {
         Err(ArgError::TaskNotFound)
     } else {
-        Ok(task_id - 1)
+        Ok(task_id as usize - 1)
+    }
+}
+
+// Parse an ID, which must be written as plain decimal digits: signs, spaces and leading zeros
+// (e.g. '+3', ' 3' or '007') are not accepted, so every ID has exactly one spelling
+fn parse_id_number(string: &str) -> Option<u64> {
+    let plain = !string.is_empty()
+        && string.bytes().all(|b| b.is_ascii_digit())
+        && (string == "0" || !string.starts_with('0'));
+    if plain { string.parse::<u64>().ok() } else { None }
+}
+
+// Digits, possibly with a sign or surrounding spaces
+fn looks_like_number(string: &str) -> bool {
+    let string = string.trim();
+    let digits = string.strip_prefix(['+', '-']).unwrap_or(string);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+// The list that a command works on. Commands that remove many tasks at once use it to name the
+// list in their confirmation, and to refuse to run on a list that was not named explicitly when
+// there are several lists.
+#[derive(Debug, Clone)]
+pub struct ListScope {
+    pub filename: PathBuf,
+    // Whether the list was chosen with '--list' or '--file'
+    pub explicit: bool,
+    // Names of the lists next to the tasks file (see 'file_io::list_names')
+    pub list_names: fn(&Path) -> Vec<String>,
+    // Read the tasks of the list again, e.g. for 'list --watch' (see 'file_io::load_tasks')
+    pub load_tasks: fn(&Path) -> Result<Vec<Task>>,
+}
+
+impl ListScope {
+    // Name of the list, as shown to the user (e.g. 'work' for 'work.json')
+    pub fn name(&self) -> String {
+        self.filename.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().to_string())
+    }
+
+    // With more than one list, 'command' may only run on an explicitly named list, so tasks are
+    // not removed from the default list by accident
+    fn check_explicit(&self, command: &str) -> Result<()> {
+        if self.explicit {
+            return Ok(());
+        }
+        match (self.list_names)(&self.filename).len() {
+            lists if lists > 1 => Err(ArgError::ListNotExplicit(command.to_string(), lists)),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Ask the user a y/N question on stdin. Anything other than 'y' or 'yes' counts as no.
+fn confirm(question: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Err(ArgError::NoTerminal);
+    }
+
+    print!("{} [y/N] ", question);
+    io::stdout().flush().map_err(|_| ArgError::NoTerminal)?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).map_err(|_| ArgError::NoTerminal)?;
+
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+// Find the single task whose name contains 'text' (case-insensitive). Return its position.
+fn find_task_by_name(tasks: &[Task], text: &str) -> Result<usize> {
+    if text.is_empty() {
+        return Err(ArgError::InvalidTaskId(text.to_string()));
+    }
+
+    let text = text.to_lowercase();
+    let matches: Vec<usize> = tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, task)| task.name.to_lowercase().contains(&text))
+        .map(|(i, _)| i)
+        .collect();
+
+    match matches[..] {
+        [] => Err(ArgError::TaskNotFound),
+        [task_id] => Ok(task_id),
+        _ => {
+            let candidates = matches
+                .iter()
+                .map(|&i| format!("{} (\'{}\')", i + 1, tasks[i].name))
+                .collect::<Vec<String>>()
+                .join(", ");
+            Err(ArgError::AmbiguousTask(candidates))
+        }
     }
 }
 
@@ -114,584 +548,4884 @@ where
     }
 }
 
-// Print all tasks the screen in a formatted way
-pub fn list_tasks(tasks: &[Task], args_iter: env::Args) -> Result<()> {
-    check_for_more_args(args_iter)?;
+// Determine the width of the task name column in the list from the terminal width. The other
+// columns have a fixed width ('other_columns_width'), the name column takes up the remaining
+// space, or the percentage of the terminal width set in TODO_NAME_WIDTH (e.g. 50). If the
+// terminal width is unknown, the name column takes the space left in a terminal of 143 columns,
+// which is 75 with all columns shown.
+fn name_column_width(columns: Option<usize>, percentage: Option<usize>, other_columns_width: usize) -> usize {
+    let default_columns: usize = 143;
+    let (min_width, max_width) = (20, 150);
 
-    println!("   ID  Task name                                                                   Creation date  Due date    Note");
+    let Some(columns) = columns else {
+        return default_columns.saturating_sub(other_columns_width).clamp(min_width, max_width);
+    };
+    // A percentage never makes the rows wider than the terminal
+    let remaining = columns.saturating_sub(other_columns_width);
+    let width = match percentage {
+        Some(percentage) => (columns * percentage / 100).min(remaining),
+        None => remaining,
+    };
+    width.clamp(min_width, max_width)
+}
 
-    for (i, task) in tasks.iter().enumerate() {
-        let name = if task.name.len() >= 75{
-            &format!("{:.71}...", task.name)
-        } else {
-            &task.name
-        };
+// Width of all columns except the name column, including the spaces between the columns. One
+// more column is left free, as some terminals wrap a line that fills the last column.
+fn other_columns_width(columns: &[Column], config: &Config) -> usize {
+    let widths: usize = columns.iter().filter_map(|column| column.width(config)).sum();
+    widths + columns.len().saturating_sub(1) + 1
+}
 
-        let color = match task.color {
-            Some(Color::Red) => " ".red_bg(),
-            Some(Color::Yellow) => " ".yellow_bg(),
-            Some(Color::Green) => " ".green_bg(),
-            Some(Color::Blue) => " ".blue_bg(),
-            Some(Color::Purple) => " ".purple_bg(),
-            None => String::from(" "),
-        };
+// Show the list, and show it again whenever the tasks file changes, until interrupted (Ctrl-C).
+// The file is polled, which also notices a file that was replaced rather than changed. A change
+// is only shown once the file has stopped changing, so a save in progress is not read. The
+// screen is only cleared when writing to a terminal, so the output can also be logged. Dates
+// are shown relative to 'today', the day the watch was started.
+fn watch_tasks(list: &ListScope, today: NaiveDate, config: &Config, args: Vec<String>) -> Result<()> {
+    let mut shown_stamp = None;
+    let mut previous_stamp = file_stamp(&list.filename);
+    let mut first = true;
+    loop {
+        let stamp = file_stamp(&list.filename);
+        if first || (stamp.is_some() && stamp == previous_stamp && stamp != shown_stamp) {
+            let result = (list.load_tasks)(&list.filename).and_then(|tasks| {
+                if io::stdout().is_terminal() {
+                    print!("\x1b[2J\x1b[H"); // Clear the screen
+                }
+                list_tasks(&tasks, list, today, config, args.clone().into_iter())
+            });
+            match result {
+                Err(e) if first => return Err(e),
+                Err(e) => eprintln!("{}", e.to_string().red_err()),
+                Ok(..) => println!("{}", "Watching for changes. Press Ctrl-C to stop".yellow_fg()),
+            }
+            shown_stamp = stamp;
+            first = false;
+        }
+        previous_stamp = stamp;
+        thread::sleep(time::Duration::from_millis(500));
+    }
+}
 
-        let creation_date = task.string_from_creation_date();
-        let due_date = task.string_from_due_date();
+// Modification time and size of a file, to notice changes. None if the file does not exist.
+fn file_stamp(filename: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = metadata(filename).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
 
-        let note = if !task.note.is_empty() {
-            String::from("✓")
-        } else {
-            String::new()
-        };
+// Options of 'list' that select the tasks to show, and the order to show them in
+#[derive(Debug, Default)]
+struct ListSelection {
+    filter: TaskFilter,
+    all: bool,
+    hide_blocked: bool,
+    older_than: Option<NaiveDate>,
+    newer_than: Option<NaiveDate>,
+    since: Option<NaiveDate>,
+    reverse: bool,
+}
 
-        println!("{} {:>3}  {:<75} {:14} {:11} {}", color, i+1, name, creation_date, due_date, note)
+impl ListSelection {
+    // Positions of the selected tasks, in the order to show them. Snoozed tasks are hidden until
+    // their start date, unless all tasks are selected.
+    fn positions(&self, tasks: &[Task], today: NaiveDate, completed_last: bool) -> Vec<usize> {
+        let is_blocked = filter::blocked(tasks);
+        let is_snoozed = filter::snoozed(today);
+        let mut positions: Vec<usize> = (0..tasks.len())
+            .filter(|&i| !(self.hide_blocked && is_blocked(&tasks[i])))
+            .filter(|&i| self.all || !is_snoozed(&tasks[i]))
+            .filter(|&i| self.older_than.is_none_or(|date| filter::created_before(date)(&tasks[i])))
+            .filter(|&i| self.newer_than.is_none_or(|date| filter::created_after(date)(&tasks[i])))
+            .filter(|&i| self.since.is_none_or(|date| filter::modified_since(date)(&tasks[i])))
+            .filter(|&i| self.filter.matches(&tasks[i], today))
+            .collect();
+        if self.reverse {
+            positions.reverse(); // Only the display order, the IDs still refer to the actual positions
+        }
+        if completed_last {
+            positions.sort_by(|&a, &b| sort::completed_last(&tasks[a], &tasks[b]));
+        }
+        positions
     }
-    println!();
+}
 
-    Ok(())
+// The IDs printed by 'list --ids-only', one per line. Stable IDs are given as 'id:N', so they
+// can be passed to any command that expects a task ID.
+fn id_lines(tasks: &[Task], positions: &[usize], stable: bool) -> Vec<String> {
+    positions
+        .iter()
+        .map(|&i| if stable { format!("id:{}", tasks[i].id) } else { (i + 1).to_string() })
+        .collect()
 }
 
-// Create task and add to vector
-pub fn create_task<T>(tasks: &mut Vec<Task>, args_iter: T) -> Result<()>
+// Print all tasks the screen in a formatted way. With 'completed_last' in the config, tasks that
+// are done are listed after the other tasks.
+pub fn list_tasks<T>(tasks: &[Task], list: &ListScope, today: NaiveDate, config: &Config, args_iter: T) -> Result<()>
 where
     T: Iterator<Item = String> {
-    let task_name = args_iter.collect::<Vec<String>>().join(" ");
-    if task_name.is_empty() {
-        return Err(ArgError::ArgMissing(String::from("task name")));
-    };
+    // Parse options. The arguments are kept to show the list again with '--watch'.
+    let args: Vec<String> = args_iter.collect();
+    let mut args_iter = args.iter().cloned();
+    let mut watch = false;
+    let mut relative = false;
+    let mut by_week = false;
+    let mut by_color = false;
+    let mut legend = false;
+    let mut plain = false;
+    let mut porcelain = false;
+    let mut note_preview = false;
+    let mut fields = None;
+    let mut ids_only = false;
+    let mut stable = false;
+    let mut selection = ListSelection::default();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--ids-only" => ids_only = true,
+            "--stable" => stable = true,
+            "--watch" => watch = true,
+            "--fields" => {
+                let names = args_iter.next().ok_or(ArgError::ArgMissing(String::from("fields")))?;
+                fields = Some(parse_fields(&names)?);
+            },
+            "--note-preview" => note_preview = true,
+            "--since" => {
+                let date_string = args_iter.next().ok_or(ArgError::ArgMissing(String::from("date")))?;
+                selection.since = Some(NaiveDate::parse_from_str(&date_string, "%Y-%m-%d").map_err(|_| ArgError::IncorrectDateFormat)?);
+            },
+            "--plain" => plain = true,
+            "--porcelain" => porcelain = true,
+            "--older-than" => selection.older_than = Some(parse_age(args_iter.next(), today)?),
+            "--newer-than" => selection.newer_than = Some(parse_age(args_iter.next(), today)?),
+            "--all" => selection.all = true,
+            "--reverse" => selection.reverse = true,
+            "--legend" => legend = true,
+            "--relative" => relative = true,
+            "--hide-blocked" => selection.hide_blocked = true,
+            "--by-week" => by_week = true,
+            "--group-by" => {
+                let group_by = args_iter.next().ok_or(ArgError::ArgMissing(String::from("grouping")))?;
+                match group_by.as_str() {
+                    "color" => by_color = true,
+                    "week" => by_week = true,
+                    _ => return Err(ArgError::InvalidGrouping(group_by)),
+                }
+            },
+            other => if !selection.filter.parse_option(other, &mut args_iter)? {
+                return Err(ArgError::UnknownOption(other.to_string()));
+            },
+        }
+    }
+    if stable && !ids_only {
+        return Err(ArgError::OptionRequires(String::from("--stable"), String::from("--ids-only")));
+    }
+    if watch {
+        let args = args.into_iter().filter(|arg| arg != "--watch").collect();
+        return watch_tasks(list, today, config, args);
+    }
 
-    tasks.push(Task::new(task_name));
-    println!("Task created with ID {}", tasks.len());
+    let positions = selection.positions(tasks, today, config.completed_last);
 
-    Ok(())
-}
+    // Porcelain output is for scripts, so it has no messages and its format never changes
+    if porcelain {
+        for &i in &positions {
+            match &fields {
+                Some(fields) => println!("{}", field_values(tasks, i, fields, false, today, config, true).join("\t")),
+                None => println!("{}", porcelain_task_row(&tasks[i], i)),
+            }
+        }
+        return Ok(());
+    }
 
-// Provide a summary of the task
-pub fn show_task(tasks: &[Task], mut args_iter: env::Args) -> Result<()> {
-    let task_id = parse_task_id(tasks, &args_iter.next())?;
-    check_for_more_args(args_iter)?;
-    let task = &tasks[task_id];
+    // Only the IDs, one per line, so they can be passed to another command (e.g. with xargs)
+    if ids_only {
+        for line in id_lines(tasks, &positions, stable) {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
 
-    // Format dates
-    let creation_date = tasks[task_id].string_from_creation_date();
-    let due_date = task.string_from_due_date();
+    if tasks.is_empty() {
+        println!("You have no tasks. Add one with \'todo add [task name]\'\n");
+        return Ok(());
+    } else if positions.is_empty() {
+        println!("No tasks match the given options\n");
+        return Ok(());
+    }
 
-    // Format color
-    let mut color = task.color
-        .as_ref()
-        .map_or(String::from("None"), |c| c.to_string());
-    color = match task.color {
-        Some(Color::Red) => color.red_fg(),
-        Some(Color::Yellow) => color.yellow_fg(),
-        Some(Color::Green) => color.green_fg(),
-        Some(Color::Blue) => color.blue_fg(),
-        Some(Color::Purple) => color.purple_fg(),
-        None => color
-    };
+    // Plain output has no header, summary or decorations, and names are never truncated
+    if plain {
+        let width = positions.iter().map(|&i| tasks[i].name.width()).max().unwrap_or(0);
+        for &i in &positions {
+            match &fields {
+                Some(fields) => println!("{}", plain_fields_row(tasks, i, fields, width, relative, today, config)),
+                None => println!("{}", plain_task_row(&tasks[i], i, width, relative, today, config)),
+            }
+        }
+        return Ok(());
+    }
 
-    // Print all to screen
-    let width = 75;
-    println!("{:>15} {:<width$}", "ID:", task_id + 1);
-    println!("{:>15} {:<width$}", "Name:", task.name);
-    println!("{:>15} {:<width$}", "Creation date:", creation_date);
-    println!("{:>15} {:<width$}", "Due date:", due_date);
-    println!("{:>15} {:<width$}", "Color:", color);
+    let mut columns = fields.unwrap_or_else(|| config.list_columns.clone());
+    if note_preview && columns.contains(&Column::Note) {
+        columns = columns
+            .into_iter()
+            .map(|column| if column == Column::Note { Column::NotePreview } else { column })
+            .collect();
+    }
+    // The name column takes the space that the shown columns leave, so the rows keep their width
+    let width = name_column_width(config.terminal_width, config.name_width, other_columns_width(&columns, config));
+    let headers: Vec<String> = columns.iter().map(|column| column.header(width, config)).collect();
+    println!("{}", headers.join(" ").trim_end());
 
-    // Print the note as well
-    let mut identifier = String::from("Note:");
-    for line in task.note.split('\n') {
-        let mut printline = String::new();
-        for word in line.split(' ') {
-            if printline.is_empty() {
-                printline.push_str(word);
-            } else if !printline.is_empty() && (printline.len() + word.len() < width) {
-                printline.push(' ');
-                printline.push_str(word);
-            } else {
-                println!("{:>15} {:<width$}", identifier, printline);
-                printline = String::from(word); // New line
-                if !identifier.is_empty() {
-                    identifier = String::new(); // Don't show 'Note:' more than once
-                }
+    if by_week {
+        for group in WeekGroup::ALL {
+            let group_positions: Vec<&usize> = positions
+                .iter()
+                .filter(|&&i| WeekGroup::from_due_date(tasks[i].due_date, today) == group)
+                .collect();
+            if group_positions.is_empty() {
+                continue;
+            }
+
+            println!("{}:", group);
+            for &i in group_positions {
+                print_task_row(tasks, i, &columns, width, relative, today, config);
             }
         }
-        println!("{:>15} {:<width$}", identifier, printline);
-        identifier = String::new(); // Don't show 'Note:' more than once
+    } else if by_color {
+        for (color, group_positions) in color_groups(tasks, &positions, config.completed_last) {
+            let header = match color {
+                Some(Color::Red) => "Red:".red_fg(),
+                Some(Color::Yellow) => "Yellow:".yellow_fg(),
+                Some(Color::Green) => "Green:".green_fg(),
+                Some(Color::Blue) => "Blue:".blue_fg(),
+                Some(Color::Purple) => "Purple:".purple_fg(),
+                None => String::from("No color:"),
+            };
+            println!("{}", header);
+            for i in group_positions {
+                print_task_row(tasks, i, &columns, width, relative, today, config);
+            }
+        }
+    } else {
+        for &i in &positions {
+            print_task_row(tasks, i, &columns, width, relative, today, config);
+        }
     }
-
-    // Finally, an empty line
     println!();
+    println!("{}\n", list_summary(tasks, &positions, today));
+
+    if legend {
+        print_legend();
+    }
 
     Ok(())
 }
 
-// Delete a task from the Vec
-pub fn delete_task<T>(tasks: &mut Vec<Task>, mut args_iter: T) -> Result<()>
+// Print the number of tasks, optionally filtered, without any other output (e.g. for scripts)
+pub fn count_tasks<T>(tasks: &[Task], today: NaiveDate, args_iter: T) -> Result<()>
 where
     T: Iterator<Item = String> {
-    let task_id = parse_task_id(tasks, &args_iter.next())?;
-
-    check_for_more_args(args_iter)?;
-
-    let task_name = tasks[task_id].name.to_owned();
-    tasks.remove(task_id);
-    println!("Removed task \'{}\'", task_name);
-
+    println!("{}", number_of_tasks(tasks, today, args_iter)?);
     Ok(())
 }
 
-// Set or clear a task color
-pub fn set_task_color<T>(tasks: &mut [Task], mut args_iter: T) -> Result<()>
+// Count the tasks that match the filter options of the 'count' command
+fn number_of_tasks<T>(tasks: &[Task], today: NaiveDate, mut args_iter: T) -> Result<usize>
 where
     T: Iterator<Item = String> {
-    let task_id = parse_task_id(tasks, &args_iter.next())?;
-
-    // Get the color string from the argument and look up the color. Change the string
-    // color for the message to the user
-    let mut color_string = args_iter.next()
-        .ok_or(ArgError::ArgMissing(String::from("task name")))?;
-    let color = match color_string.as_str() {
-        "red" => {
-            color_string = color_string.red_fg();
-            Some(Color::Red)
-        },
-        "yellow" => {
-            color_string = color_string.yellow_fg();
-            Some(Color::Yellow)
-        },
-        "green" => {
-            color_string = color_string.green_fg();
-            Some(Color::Green)
-        },
-        "blue" => {
-            color_string = color_string.blue_fg();
-            Some(Color::Blue)
-        },
-        "purple" => {
-            color_string = color_string.purple_fg();
-            Some(Color::Purple)
+    let mut filter = TaskFilter::default();
+    while let Some(arg) = args_iter.next() {
+        if !filter.parse_option(&arg, &mut args_iter)? {
+            return Err(ArgError::UnknownOption(arg));
         }
-        "clear" => {
-            color_string = String::new();
-            None
-        },
-        other => { return Err(ArgError::InvalidColor(other.to_string())); }
-    };
-
-    check_for_more_args(args_iter)?;
+    }
 
-    // Set the color
-    tasks[task_id].color = color;
+    Ok(tasks.iter().filter(|task| filter.matches(task, today)).count())
+}
 
-    // Print the result
-    if color_string.is_empty() {
-        println!("Color removed for task \'{}\'", tasks[task_id].name);
-    } else {
-        println!("Color for task \'{}\' was set to {}", tasks[task_id].name, color_string);
+// Parse the argument of '--older-than' and '--newer-than' into a date. This is either a date
+// (YYYY-MM-DD) or an age relative to today, in days ('30d') or weeks ('4w').
+fn parse_age(age_opt: Option<String>, today: NaiveDate) -> Result<NaiveDate> {
+    let age_string = age_opt.ok_or(ArgError::ArgMissing(String::from("date or age")))?;
+    if let Ok(date) = NaiveDate::parse_from_str(&age_string, "%Y-%m-%d") {
+        return Ok(date);
     }
 
-    Ok(())
+    let days = if let Some(days_string) = age_string.strip_suffix('d') {
+        days_string.parse::<u64>().ok()
+    } else if let Some(weeks_string) = age_string.strip_suffix('w') {
+        weeks_string.parse::<u64>().ok().and_then(|weeks| weeks.checked_mul(7))
+    } else {
+        None
+    };
+    let days = days.ok_or(ArgError::InvalidAge(age_string))?;
+    today.checked_sub_days(Days::new(days)).ok_or(ArgError::DateOutOfRange)
 }
 
-// Adds a note to the task
-pub fn add_note<T>(tasks: &mut [Task], mut args_iter: T) -> Result<()>
-where
-    T: Iterator<Item = String> {
-    let task_id = parse_task_id(tasks, &args_iter.next())?;
-    let note = args_iter.collect::<Vec<String>>().join(" ");
+// Summarize the listed tasks, e.g. '11 tasks (3 red, 2 green, 1 overdue)'
+fn list_summary(tasks: &[Task], positions: &[usize], today: NaiveDate) -> String {
+    let listed: Vec<&Task> = positions.iter().map(|&i| &tasks[i]).collect();
 
-    if note == *"clear" {
-        tasks[task_id].note = String::new();
-        return Ok(());
+    let mut details = vec![];
+    for color in Color::ALL {
+        let count = listed.iter().filter(|task| filter::has_color(color.clone())(task)).count();
+        if count == 0 {
+            continue;
+        }
+        let detail = format!("{} {}", count, color.name());
+        details.push(match color {
+            Color::Red => detail.red_fg(),
+            Color::Yellow => detail.yellow_fg(),
+            Color::Green => detail.green_fg(),
+            Color::Blue => detail.blue_fg(),
+            Color::Purple => detail.purple_fg(),
+        });
     }
 
-    if !tasks[task_id].note.is_empty() {
-        tasks[task_id].note.push('\n');
+    let is_overdue = filter::overdue(today);
+    let overdue = listed.iter().filter(|task| is_overdue(task)).count();
+    if overdue > 0 {
+        details.push(format!("{} overdue", overdue).red_fg());
     }
-    tasks[task_id].note.push_str(&note);
-
-    Ok(())
-}
-
-// Sort tasks by color, then due date
-pub fn sort_tasks<T>(tasks: &mut [Task], args_iter: T) -> Result<()>
-where
-    T: Iterator<Item = String> {
-    check_for_more_args(args_iter)?;
 
-    tasks.sort_by_key(|task| (task.due_date));
-    tasks.sort_by_key(|task| (task.due_date.is_none())); // Order 'None' values to the bottom
-    tasks.sort_by(|task1, task2| task1.color.cmp(&task2.color)); // A bit contrived because this cannot be written as:
-                                                                 //     tasks.sort_by_key(|task| (task.color));
-    tasks.sort_by_key(|task| (task.color.is_none()));  // Order 'None' values to the bottom
-
-    Ok(())
-}
+    let estimated: u64 = listed.iter().filter_map(|task| task.estimate).map(u64::from).sum();
+    if estimated > 0 {
+        details.push(format!("{} estimated", minutes_string_from(estimated)));
+    }
 
-// Add a due date to the task
-pub fn add_duedate<T>(tasks: &mut [Task], mut args_iter: T) -> Result<()>
-where
-    T: Iterator<Item = String> {
-    let task_id = parse_task_id(tasks, &args_iter.next())?;
-    let date_string = args_iter.next().ok_or(ArgError::ArgMissing(String::from("date")))?;
-    check_for_more_args(args_iter)?;
-    
-    if date_string == "clear" {
-        tasks[task_id].due_date = None;
-        println!("Due date for task \'{}\' has been cleared", tasks[task_id].name)
+    let total = if listed.len() == 1 { String::from("1 task") } else { format!("{} tasks", listed.len()) };
+    if details.is_empty() {
+        total
     } else {
-    let due_date = NaiveDate::parse_from_str(date_string.as_str(), "%Y-%m-%d")
-        .map_err(|_| ArgError::IncorrectDateFormat)?;
-    tasks[task_id].due_date = Some(due_date);
-    println!("Due date for task \'{}\' was set to {}",
-        tasks[task_id].name,
-        tasks[task_id]
-            .due_date
-            .unwrap()
-            .format("%Y-%m-%d")
-        )
+        format!("{} ({})", total, details.join(", "))
     }
+}
 
-    Ok(())
+// One-line health check shown by 'info', e.g. 'You have 12 tasks, 3 overdue'
+pub fn info_summary(tasks: &[Task], today: NaiveDate) -> String {
+    let total = if tasks.len() == 1 { String::from("1 task") } else { format!("{} tasks", tasks.len()) };
+    let is_overdue = filter::overdue(today);
+    let overdue = tasks.iter().filter(|task| is_overdue(task)).count();
+    let overdue = if overdue > 0 { format!("{} overdue", overdue).red_fg() } else { String::from("0 overdue") };
+    format!("You have {}, {}", total, overdue)
 }
 
-// Show program help
-pub fn show_help(args_iter: env::Args) -> Result<()> {
-    check_for_more_args(args_iter)?;
+// Explain the symbols and colors used in the task list
+fn print_legend() {
+    let columns = [
+        ("ID", String::from("Position of the task in the list. A * means the task is pinned")),
+        ("Task name", format!("Tasks that are done are marked with '{}'", DONE_MARKER.trim_end())),
+        ("Stable", String::from("Stable ID of the task, which can be given as 'id:N'")),
+        ("Due date", format!("Due dates in the past are shown in {}, and due dates within a few days in {} (unless the task is done)", "red".red_fg(), "yellow".yellow_fg())),
+        ("Subtasks", String::from("Number of subtasks done / total number of subtasks")),
+        ("Note", String::from("A ✓ means the task has a note (see 'todo show')")),
+        ("Blocked by", String::from("ID of the task that blocks this task")),
+    ];
 
-    let help_str = include_str!("help.txt");
-    println!("{help_str}\n");
+    println!("Legend:");
+    for (column, description) in columns {
+        println!("  {:<12} {}", column, description);
+    }
+    println!("  {:<12} {} {}  {} {}  {} {}  {} {}  {} {}", "Colors",
+        " ".red_bg(), Color::Red,
+        " ".yellow_bg(), Color::Yellow,
+        " ".green_bg(), Color::Green,
+        " ".blue_bg(), Color::Blue,
+        " ".purple_bg(), Color::Purple);
+    println!();
+}
 
-    Ok(())
+// Print a single line of the task list
+fn print_task_row(tasks: &[Task], i: usize, columns: &[Column], width: usize, relative: bool, today: NaiveDate, config: &Config) {
+    let cells: Vec<String> = columns.iter().map(|column| column.cell(tasks, i, width, relative, today, config)).collect();
+    println!("{}", cells.join(" ").trim_end());
 }
 
-// Rename a task
-pub fn rename_task<T>(tasks: &mut [Task], mut args_iter: T) -> Result<()>
-where
-    T: Iterator<Item = String> {
-    let task_id = parse_task_id(tasks, &args_iter.next())?;
-    let name_old = tasks[task_id].name.to_owned();
-    let name_new = args_iter.collect::<Vec<String>>().join(" ");
+// Columns of the task list. Which columns are shown, and in which order, can be set with the
+// TODO_LIST_COLUMNS environment variable.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Column {
+    Color,
+    Id,
+    Stable,
+    Name,
+    Created,
+    Due,
+    Subtasks,
+    Note,
+    NotePreview,
+    Blocked,
+}
 
-    tasks[task_id].name = name_new;
+// Shown before the name of a task that is done, in the task list
+const DONE_MARKER: &str = "(done) ";
 
-    println!("Renamed task \'{}\' to \'{}\'", name_old, tasks[task_id].name);
+// Width of the note column for 'list --note-preview'
+const NOTE_PREVIEW_WIDTH: usize = 20;
 
-    Ok(())
-}
+impl Column {
+    const DEFAULT: [Column; 9] = [
+        Column::Color,
+        Column::Id,
+        Column::Stable,
+        Column::Name,
+        Column::Created,
+        Column::Due,
+        Column::Subtasks,
+        Column::Note,
+        Column::Blocked,
+    ];
 
-#[cfg(test)]
-mod tests {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "color" => Some(Column::Color),
+            "id" => Some(Column::Id),
+            "stable" => Some(Column::Stable),
+            "name" => Some(Column::Name),
+            "created" => Some(Column::Created),
+            "due" => Some(Column::Due),
+            "subtasks" => Some(Column::Subtasks),
+            "note" => Some(Column::Note),
+            "blocked" => Some(Column::Blocked),
+            _ => None
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Column::Color => "color",
+            Column::Id => "id",
+            Column::Stable => "stable",
+            Column::Name => "name",
+            Column::Created => "created",
+            Column::Due => "due",
+            Column::Subtasks => "subtasks",
+            Column::Note | Column::NotePreview => "note",
+            Column::Blocked => "blocked",
+        }
+    }
+
+    // Width of the column on screen, or None for the name column, which fills the remaining space
+    fn width(&self, config: &Config) -> Option<usize> {
+        match self {
+            Column::Color => Some(1),
+            Column::Id => Some(4),
+            Column::Stable => Some(7),
+            Column::Name => None,
+            Column::Created => Some(config.date_width.max(13) + 1),
+            Column::Due => Some(config.date_width.max(10) + 1),
+            Column::Subtasks => Some(8),
+            Column::Note => Some(4),
+            Column::NotePreview => Some(NOTE_PREVIEW_WIDTH),
+            Column::Blocked => Some(10),
+        }
+    }
+
+    fn header(&self, name_width: usize, config: &Config) -> String {
+        match self {
+            Column::Color => String::from(" "),
+            Column::Id => String::from(" ID "),
+            Column::Stable => String::from(" Stable"),
+            Column::Name => format!("{:<name_width$}", "Task name"),
+            Column::Created => format!("{:<width$}", "Creation date", width = self.width(config).unwrap()),
+            Column::Due => format!("{:<width$}", "Due date", width = self.width(config).unwrap()),
+            Column::Subtasks => String::from("Subtasks"),
+            Column::Note => String::from("Note"),
+            Column::NotePreview => format!("{:<NOTE_PREVIEW_WIDTH$}", "Note"),
+            Column::Blocked => String::from("Blocked by"),
+        }
+    }
+
+    // Format the column for the task at position 'i', padded to the width of the column
+    fn cell(&self, tasks: &[Task], i: usize, name_width: usize, relative: bool, today: NaiveDate, config: &Config) -> String {
+        let task = &tasks[i];
+        match self {
+            Column::Color => match task.color {
+                Some(Color::Red) => " ".red_bg(),
+                Some(Color::Yellow) => " ".yellow_bg(),
+                Some(Color::Green) => " ".green_bg(),
+                Some(Color::Blue) => " ".blue_bg(),
+                Some(Color::Purple) => " ".purple_bg(),
+                None => String::from(" "),
+            },
+            Column::Id => {
+                let pinned = if task.pinned { "*" } else { " " };
+                format!("{:>3}{}", i + 1, pinned)
+            },
+            Column::Stable => format!("{:>7}", task.id),
+            Column::Name if task.completed_date.is_some() => fit_to_width(&format!("{}{}", DONE_MARKER, task.name), name_width),
+            Column::Name => fit_to_width(&task.name, name_width),
+            Column::Created => {
+                let creation_date = task.string_from_creation_date(config);
+                let padding = self.width(config).unwrap().saturating_sub(creation_date.width());
+                creation_date + &" ".repeat(padding)
+            },
+            Column::Due => {
+                // Pad based on the text without color codes
+                let padding = self.width(config).unwrap().saturating_sub(task.plain_string_from_due_date(relative, today, config).width());
+                task.string_from_due_date(relative, today, config) + &" ".repeat(padding)
+            },
+            Column::Subtasks => {
+                let subtasks = if !task.subtasks.is_empty() {
+                    let done = task.subtasks.iter().filter(|subtask| subtask.done).count();
+                    format!("{}/{}", done, task.subtasks.len())
+                } else {
+                    String::new()
+                };
+                format!("{:<8}", subtasks)
+            },
+            Column::Note => {
+                let note = if !task.note.is_empty() || task.note_file { "✓" } else { "" };
+                format!("{:<4}", note)
+            },
+            Column::NotePreview => {
+                // Notes in a separate file are not read for the list
+                let mut lines = task.note.lines();
+                let preview = match lines.next() {
+                    _ if task.note_file => String::from("(in file)"),
+                    Some(first) if lines.next().is_some() => format!("{}...", first.trim_end()),
+                    Some(first) => first.to_string(),
+                    None => String::new(),
+                };
+                fit_to_width(&preview, NOTE_PREVIEW_WIDTH)
+            },
+            Column::Blocked => {
+                let blocked_by = blocker_position(tasks, task).map_or(String::new(), |position| (position + 1).to_string());
+                format!("{:<10}", blocked_by)
+            },
+        }
+    }
+}
+
+// Value of the column for the task at position 'i', without padding, colors or symbols, for
+// 'list --plain' and 'list --porcelain'. Porcelain values always have YYYY-MM-DD dates and no tabs.
+fn field_value(column: Column, tasks: &[Task], i: usize, relative: bool, today: NaiveDate, config: &Config, porcelain: bool) -> String {
+    let task = &tasks[i];
+    let date_string = |date: NaiveDate| if porcelain { date.format("%Y-%m-%d").to_string() } else { config.format_date(date) };
+    let value = match column {
+        Column::Color => task.color.as_ref().map(|color| color.name()).unwrap_or_default().to_string(),
+        Column::Id => (i + 1).to_string(),
+        Column::Stable => task.id.to_string(),
+        Column::Name => task.name.clone(),
+        Column::Created => date_string(task.creation_date),
+        Column::Due if porcelain => task.due_date.map(date_string).unwrap_or_default(),
+        Column::Due => task.plain_string_from_due_date(relative, today, config),
+        Column::Subtasks if task.subtasks.is_empty() => String::new(),
+        Column::Subtasks => {
+            let done = task.subtasks.iter().filter(|subtask| subtask.done).count();
+            format!("{}/{}", done, task.subtasks.len())
+        },
+        Column::Note | Column::NotePreview if task.note_file => String::from("(in file)"),
+        Column::Note | Column::NotePreview => task.note.lines().next().unwrap_or_default().to_string(),
+        Column::Blocked => blocker_position(tasks, task).map_or(String::new(), |position| (position + 1).to_string()),
+    };
+    if porcelain { value.replace('\t', " ") } else { value }
+}
+
+fn field_values(tasks: &[Task], i: usize, fields: &[Column], relative: bool, today: NaiveDate, config: &Config, porcelain: bool) -> Vec<String> {
+    fields.iter().map(|&field| field_value(field, tasks, i, relative, today, config, porcelain)).collect()
+}
+
+// Format a task for 'list --plain --fields'. The ID is right-aligned and the name padded to
+// 'width', like in the regular plain list. Dates and colors are padded, so the fields line up.
+fn plain_fields_row(tasks: &[Task], i: usize, fields: &[Column], width: usize, relative: bool, today: NaiveDate, config: &Config) -> String {
+    let values: Vec<String> = fields
+        .iter()
+        .zip(field_values(tasks, i, fields, relative, today, config, false))
+        .map(|(field, value)| match field {
+            Column::Id => format!("{:>3}", value),
+            Column::Name => format!("{}{}", value, " ".repeat(width.saturating_sub(value.width()))),
+            Column::Created | Column::Due => format!("{}{}", value, " ".repeat(config.date_width.saturating_sub(value.width()))),
+            Column::Color => format!("{:<6}", value),
+            _ => value,
+        })
+        .collect();
+    values.join("  ").trim_end().to_string()
+}
+
+// Parse the fields for 'list --fields' from a comma-separated list (e.g. 'id,name,due'). Unlike
+// TODO_LIST_COLUMNS, an unknown field is an error.
+fn parse_fields(names: &str) -> Result<Vec<Column>> {
+    let mut fields = vec![];
+    for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        let field = Column::from_name(&name.to_lowercase()).ok_or(ArgError::InvalidField(name.to_string()))?;
+        if !fields.contains(&field) {
+            fields.push(field);
+        }
+    }
+    if fields.is_empty() {
+        return Err(ArgError::ArgMissing(String::from("fields")));
+    }
+    Ok(fields)
+}
+
+// Parse the columns to show from a comma-separated list of column names (e.g. 'id,name,due').
+// Unknown names are ignored, and added to 'warnings'. Without any valid column, all columns are
+// shown.
+pub fn list_columns(setting: Option<String>, warnings: &mut Vec<String>) -> Vec<Column> {
+    let Some(setting) = setting else {
+        return Column::DEFAULT.to_vec();
+    };
+
+    let mut columns = vec![];
+    for name in setting.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        match Column::from_name(&name.to_lowercase()) {
+            Some(column) if !columns.contains(&column) => columns.push(column),
+            Some(..) => (),
+            None => warnings.push(format!("Ignoring unknown column in TODO_LIST_COLUMNS: {}", name)),
+        }
+    }
+
+    if columns.is_empty() {
+        Column::DEFAULT.to_vec()
+    } else {
+        columns
+    }
+}
+
+// Truncate (with '...') or pad a name to exactly 'width' columns on screen. Wide characters
+// (e.g. CJK) take two columns, and grapheme clusters (e.g. emoji sequences) are never split.
+fn fit_to_width(name: &str, width: usize) -> String {
+    let mut fitted = if name.width() >= width {
+        let mut truncated = String::new();
+        let mut truncated_width = 0;
+        for grapheme in name.graphemes(true) {
+            truncated_width += grapheme.width();
+            if truncated_width > width.saturating_sub(4) {
+                break;
+            }
+            truncated.push_str(grapheme);
+        }
+        truncated + "..."
+    } else {
+        name.to_string()
+    };
+
+    let padding = width.saturating_sub(fitted.width());
+    fitted.push_str(&" ".repeat(padding));
+    fitted
+}
+
+// Format a task for 'list --plain' as 'ID  name  due date', without any colors or symbols
+fn plain_task_row(task: &Task, i: usize, width: usize, relative: bool, today: NaiveDate, config: &Config) -> String {
+    let padding = " ".repeat(width.saturating_sub(task.name.width()));
+    let row = format!("{:>3}  {}{}  {}", i + 1, task.name, padding, task.plain_string_from_due_date(relative, today, config));
+    row.trim_end().to_string()
+}
+
+// Format a task for 'list --porcelain'. The fields are separated by tabs and are always in the
+// same order: ID, stable ID, creation date, due date, color, name. A missing value is an empty
+// field. Tabs in the name are replaced by spaces. Do not change this format, scripts rely on it.
+fn porcelain_task_row(task: &Task, i: usize) -> String {
+    let due_date = task.due_date.map(|date| date.format("%Y-%m-%d").to_string()).unwrap_or_default();
+    let color = task.color.as_ref().map(|color| color.name()).unwrap_or_default();
+    format!("{}\t{}\t{}\t{}\t{}\t{}",
+        i + 1,
+        task.id,
+        task.creation_date.format("%Y-%m-%d"),
+        due_date,
+        color,
+        task.name.replace('\t', " "))
+}
+
+// Group the listed tasks by color for 'list --group-by color', in the order of the colors and
+// followed by tasks without a color. Within a group, tasks are sorted by due date (tasks without
+// a due date last). Empty groups are omitted.
+fn color_groups(tasks: &[Task], positions: &[usize], completed_last: bool) -> Vec<(Option<Color>, Vec<usize>)> {
+    let colors = Color::ALL.into_iter().map(Some).chain([None]);
+    colors
+        .map(|color| {
+            let mut group_positions: Vec<usize> = positions
+                .iter()
+                .copied()
+                .filter(|&i| tasks[i].color == color)
+                .collect();
+            group_positions.sort_by(|&a, &b| {
+                let done_order = if completed_last { sort::completed_last(&tasks[a], &tasks[b]) } else { Ordering::Equal };
+                done_order
+                    .then_with(|| sort::by_due_date(&tasks[a], &tasks[b]))
+                    .then_with(|| sort::by_id(&tasks[a], &tasks[b]))
+            });
+            (color, group_positions)
+        })
+        .filter(|(_, group_positions)| !group_positions.is_empty())
+        .collect()
+}
+
+// Groups for 'list --by-week', based on the ISO week of the due date
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum WeekGroup {
+    Overdue,
+    ThisWeek,
+    NextWeek,
+    Later,
+    NoDueDate,
+}
+
+impl WeekGroup {
+    const ALL: [WeekGroup; 5] = [
+        WeekGroup::Overdue,
+        WeekGroup::ThisWeek,
+        WeekGroup::NextWeek,
+        WeekGroup::Later,
+        WeekGroup::NoDueDate,
+    ];
+
+    fn from_due_date(due_date: Option<NaiveDate>, today: NaiveDate) -> Self {
+        let Some(date) = due_date else {
+            return WeekGroup::NoDueDate;
+        };
+
+        if date < today {
+            WeekGroup::Overdue
+        } else if date.iso_week() == today.iso_week() {
+            WeekGroup::ThisWeek
+        } else if date.iso_week() == (today + Days::new(7)).iso_week() {
+            WeekGroup::NextWeek
+        } else {
+            WeekGroup::Later
+        }
+    }
+}
+
+impl fmt::Display for WeekGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeekGroup::Overdue => write!(f, "Overdue"),
+            WeekGroup::ThisWeek => write!(f, "This week"),
+            WeekGroup::NextWeek => write!(f, "Next week"),
+            WeekGroup::Later => write!(f, "Later"),
+            WeekGroup::NoDueDate => write!(f, "No due date"),
+        }
+    }
+}
+
+// Create task and add to vector. With '--created', the creation date can be set explicitly.
+// With '--after', the task is inserted after the given task instead of at the end. A task with
+// the same name as an existing task is only added with '--force'.
+pub fn create_task<T>(tasks: &mut Vec<Task>, next_id: &mut u64, today: NaiveDate, config: &Config, args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let mut args_iter = args_iter.peekable();
+
+    // Parse options
+    let mut creation_date = None;
+    let mut position = tasks.len();
+    let mut force = false;
+    let mut json = None;
+    loop {
+        if args_iter.next_if_eq("--json").is_some() {
+            json = Some(args_iter.next().ok_or(ArgError::ArgMissing(String::from("JSON object")))?);
+        } else if args_iter.next_if_eq("--created").is_some() {
+            let date_string = args_iter.next().ok_or(ArgError::ArgMissing(String::from("creation date")))?;
+            let date = NaiveDate::parse_from_str(date_string.as_str(), "%Y-%m-%d")
+                .map_err(|_| ArgError::IncorrectDateFormat)?;
+            if date > today {
+                return Err(ArgError::FutureCreationDate);
+            }
+            creation_date = Some(date);
+        } else if args_iter.next_if_eq("--after").is_some() {
+            position = parse_task_id(tasks, &args_iter.next())? + 1;
+        } else if args_iter.next_if_eq("--force").is_some() {
+            force = true;
+        } else {
+            break;
+        }
+    }
+
+    // The task is either described by a JSON object (read from stdin for '-'), or only by its name
+    let mut task = match json {
+        Some(json) => {
+            check_for_more_args(args_iter)?;
+            let json = if json == "-" {
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text).map_err(|e| ArgError::InvalidJson(e.to_string()))?;
+                text
+            } else {
+                json
+            };
+            task_from_json(&json, today, config)?
+        },
+        None => {
+            let task_name = validate_task_name(args_iter.collect::<Vec<String>>().join(" "))?;
+            if task_name.is_empty() {
+                return Err(ArgError::ArgMissing(String::from("task name")));
+            };
+            Task::new(task_name, today)
+        },
+    };
+
+    // Check for duplicates
+    let name_normalized = task.name.trim().to_lowercase();
+    if let Some(duplicate) = tasks.iter().position(|task| task.name.trim().to_lowercase() == name_normalized) {
+        if !force {
+            return Err(ArgError::DuplicateTask(duplicate + 1));
+        }
+    }
+
+    task.id = *next_id;
+    *next_id += 1;
+    task.creation_date = creation_date.unwrap_or(today);
+    if task.color.is_none() {
+        task.color = config.default_color.clone();
+    }
+    tasks.insert(position, task);
+    info(format!("Task created with ID {}", position + 1).green_fg());
+
+    Ok(())
+}
+
+// A task as given to 'add --json'. Only the fields that describe the task are accepted: the ids
+// and dates that record its history are assigned as for any new task. Values are given as on
+// the command line, e.g. '+3d' as a due date, so they are checked in the same way.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct TaskInput {
+    name: String,
+    #[serde(default)]
+    due_date: Option<String>,
+    #[serde(default)]
+    color: Option<String>,
+    #[serde(default)]
+    note: String,
+    #[serde(default)]
+    subtasks: Vec<String>,
+    #[serde(default)]
+    pinned: bool,
+    #[serde(default)]
+    estimate: Option<u32>,
+    #[serde(default)]
+    url: Option<String>
+}
+
+// Create a task from a JSON object (see 'TaskInput'). Nothing is created if any field is invalid.
+fn task_from_json(json: &str, today: NaiveDate, config: &Config) -> Result<Task> {
+    let input: TaskInput = serde_json::from_str(json).map_err(|e| ArgError::InvalidJson(e.to_string()))?;
+
+    let name = validate_task_name(input.name)?;
+    if name.is_empty() {
+        return Err(ArgError::ArgMissing(String::from("task name")));
+    }
+    let note = trim_note(&strip_escape_sequences(&input.note));
+    let max_length = config.max_note_length;
+    let length = note.chars().count();
+    if length > max_length {
+        return Err(ArgError::NoteTooLong(length, max_length));
+    }
+
+    let mut task = Task::new(name, today);
+    if let Some(date_string) = input.due_date {
+        task.due_date = parse_due_value(&date_string, None, today, config.week_end)?;
+    }
+    if let Some(color_name) = input.color {
+        task.color = Color::parse(&color_name)?;
+    }
+    task.note = note;
+    for text in input.subtasks {
+        task.subtasks.push(Subtask { text: validate_subtask_text(text)?, done: false });
+    }
+    task.pinned = input.pinned;
+    if let Some(minutes) = input.estimate {
+        task.estimate = parse_estimate_value(&minutes.to_string())?;
+    }
+    if let Some(url) = input.url {
+        task.url = parse_url_value(&url)?;
+    }
+
+    Ok(task)
+}
+
+// Task names are shown on a single line, so control characters (e.g. newlines and ANSI escape
+// codes) are not allowed. Tabs are replaced by spaces, and surrounding whitespace is removed, as
+// it would misalign the list.
+fn validate_task_name(name: String) -> Result<String> {
+    let name = name.replace('\t', " ");
+    if name.chars().any(char::is_control) {
+        return Err(ArgError::InvalidTaskName);
+    }
+    Ok(name.trim().to_string())
+}
+
+// Subtasks are shown on a single line below their task, so the same rules apply as for task
+// names. An empty subtask is not allowed.
+fn validate_subtask_text(text: String) -> Result<String> {
+    let text = validate_task_name(text).map_err(|_| ArgError::InvalidSubtaskText)?;
+    if text.is_empty() {
+        return Err(ArgError::ArgMissing(String::from("subtask text")));
+    }
+    Ok(text)
+}
+
+// Remove surrounding whitespace from each line of a note, and leading and trailing blank lines.
+// Spacing within lines and blank lines between paragraphs are kept.
+fn trim_note(note: &str) -> String {
+    note.trim().lines().map(str::trim).collect::<Vec<&str>>().join("\n")
+}
+
+// Remove ANSI escape sequences and control characters other than newlines and tabs from a text
+fn strip_escape_sequences(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // CSI sequences ('ESC [') end with a character in the range '@'..='~', other
+            // escape sequences consist of a single character after ESC
+            if chars.next_if_eq(&'[').is_some() {
+                while chars.next().is_some_and(|c| !('@'..='~').contains(&c)) {}
+            } else {
+                chars.next();
+            }
+        } else if !c.is_control() || c == '\n' || c == '\t' {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+// Provide a summary of the task
+pub fn show_task<T>(tasks: &[Task], notes_dir: &Path, today: NaiveDate, config: &Config, args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let mut args_iter = args_iter.peekable();
+    let mut detail = Detail::Normal;
+    while let Some(option) = args_iter.next_if(|arg| arg.starts_with("--")) {
+        detail = match option.as_str() {
+            "--expand" => Detail::Expanded,
+            "--summary" => Detail::Summary,
+            _ => return Err(ArgError::UnknownOption(option)),
+        };
+    }
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+    check_for_more_args(args_iter)?;
+    let task = &tasks[task_id];
+
+    // Format dates
+    let creation_date = tasks[task_id].string_from_creation_date(config);
+    let due_date = match task.due_date {
+        Some(..) => format!("{} ({})", task.string_from_due_date(false, today, config), task.string_from_due_date(true, today, config)),
+        None => String::new()
+    };
+
+    // Format color
+    let mut color = task.color
+        .as_ref()
+        .map_or(String::from("None"), |c| c.to_string());
+    color = match task.color {
+        Some(Color::Red) => color.red_fg(),
+        Some(Color::Yellow) => color.yellow_fg(),
+        Some(Color::Green) => color.green_fg(),
+        Some(Color::Blue) => color.blue_fg(),
+        Some(Color::Purple) => color.purple_fg(),
+        None => color
+    };
+
+    // Print all to screen
+    let width = 75;
+    println!("{:>15} {:<width$}", "ID:", task_id + 1);
+    println!("{:>15} {:<width$}", "Stable ID:", task.id);
+    println!("{:>15} {:<width$}", "Name:", task.name);
+    println!("{:>15} {:<width$}", "Creation date:", creation_date);
+    println!("{:>15} {:<width$}", "Age:", days_string((today - task.creation_date).num_days()));
+    println!("{:>15} {:<width$}", "Last modified:", config.format_date(task.last_modified()));
+    if let Some(date) = task.completed_date {
+        println!("{:>15} {:<width$}", "Completed:", config.format_date(date));
+    }
+    println!("{:>15} {:<width$}", "Due date:", due_date);
+    if let Some(date) = task.due_date {
+        let days = (date - today).num_days();
+        if days >= 0 {
+            println!("{:>15} {:<width$}", "Due in:", days_string(days));
+        } else {
+            println!("{:>15} {:<width$}", "Overdue by:", days_string(-days).red_fg());
+        }
+    }
+    if let Some(date) = task.start_date.filter(|&date| date > today) {
+        println!("{:>15} {:<width$}", "Snoozed until:", config.format_date(date));
+    }
+    println!("{:>15} {:<width$}", "Color:", color);
+    if let Some(minutes) = task.estimate {
+        println!("{:>15} {:<width$}", "Estimate:", minutes_string_from(minutes as u64));
+    }
+    if let Some(url) = &task.url {
+        println!("{:>15} {:<width$}", "URL:", url);
+    }
+    if let Some(position) = blocker_position(tasks, task) {
+        let blocked_by = format!("{} ({})", position + 1, tasks[position].name);
+        println!("{:>15} {:<width$}", "Blocked by:", blocked_by);
+    }
+
+    // Print the checklist of subtasks, or only the number done in summary mode
+    if detail == Detail::Summary {
+        if !task.subtasks.is_empty() {
+            let done = task.subtasks.iter().filter(|subtask| subtask.done).count();
+            let line = format!("{}/{} done", done, task.subtasks.len());
+            println!("{:>15} {:<width$}", "Subtasks:", line);
+        }
+    } else {
+        for (i, subtask) in task.subtasks.iter().enumerate() {
+            let identifier = if i == 0 { "Subtasks:" } else { "" };
+            let marker = if subtask.done { "[x]" } else { "[ ]" };
+            let line = format!("{} {:>2}. {}", marker, i + 1, subtask.text);
+            println!("{:>15} {:<width$}", identifier, line);
+        }
+    }
+
+    // Print the note as well. Notes stored in a separate file are only read now.
+    let note_text = if task.note_file {
+        read_note(notes_dir, task.id)
+    } else {
+        task.note.to_owned()
+    };
+    if let Some(size) = note_size_string(&note_text) {
+        println!("{:>15} {:<width$}", "Note size:", size);
+    }
+    if task.note_file && detail == Detail::Expanded {
+        let filename = note_filename(notes_dir, task.id);
+        println!("{:>15} {:<width$}", "Note file:", filename.display());
+    }
+    for (i, line) in note_lines(&note_text, detail, width).iter().enumerate() {
+        let identifier = if i == 0 { "Note:" } else { "" }; // Don't show 'Note:' more than once
+        println!("{:>15} {:<width$}", identifier, line);
+    }
+
+    // Finally, an empty line
+    println!();
+
+    Ok(())
+}
+
+// How much of a task is shown by 'show'. The summary only gives the first line of the note and
+// the number of subtasks done; expanded shows the note exactly as written.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Detail {
+    Summary,
+    Normal,
+    Expanded,
+}
+
+// Lines of a note as shown by 'show'. Normally, lines are wrapped at 'width'. Expanded, the
+// lines are kept as they are (e.g. for Markdown in a note file). The summary is the first line
+// only, cut off at 'width'.
+fn note_lines(note: &str, detail: Detail, width: usize) -> Vec<String> {
+    let note = note.trim_end();
+    match detail {
+        Detail::Summary => {
+            let first = note.lines().next().unwrap_or_default();
+            if first.len() == note.len() && first.chars().count() <= width {
+                vec![first.to_string()]
+            } else {
+                let shortened: String = first.chars().take(width.saturating_sub(4)).collect();
+                vec![format!("{} ...", shortened.trim_end())]
+            }
+        }
+        Detail::Expanded => note.split('\n').map(String::from).collect(),
+        Detail::Normal => {
+            let mut lines = vec![];
+            for line in note.split('\n') {
+                let mut printline = String::new();
+                for word in line.split(' ') {
+                    if printline.is_empty() {
+                        printline.push_str(word);
+                    } else if printline.len() + word.len() < width {
+                        printline.push(' ');
+                        printline.push_str(word);
+                    } else {
+                        lines.push(printline);
+                        printline = String::from(word); // New line
+                    }
+                }
+                lines.push(printline);
+            }
+            lines
+        }
+    }
+}
+
+// Describe the size of a note, e.g. '350 words (2 min read)', at 200 words per minute. Returns
+// None for an empty note.
+fn note_size_string(note: &str) -> Option<String> {
+    let words = note.split_whitespace().count();
+    if words == 0 {
+        return None;
+    }
+    let minutes = words.div_ceil(200);
+    let words_string = if words == 1 { String::from("1 word") } else { format!("{} words", words) };
+    Some(format!("{} ({} min read)", words_string, minutes))
+}
+
+// Delete a task from the Vec
+pub fn delete_task<T>(tasks: &mut Vec<Task>, list: &ListScope, today: NaiveDate, args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let mut args_iter = args_iter.peekable();
+
+    // Either a single task, or all tasks that match the filter options. Removing several tasks
+    // is confirmed first, unless '--yes' is given. At least one filter option is required, as
+    // an empty filter would match every task.
+    if args_iter.peek().is_some_and(|arg| arg.starts_with("--")) {
+        let mut filter = TaskFilter::default();
+        let mut filtered = false;
+        let mut skip_confirmation = false;
+        while let Some(option) = args_iter.next() {
+            if option == "--yes" {
+                skip_confirmation = true;
+            } else if filter.parse_option(&option, &mut args_iter)? {
+                filtered = true;
+            } else {
+                return Err(ArgError::UnknownOption(option));
+            }
+        }
+        if !filtered {
+            return Err(ArgError::ArgMissing(String::from("filter option")));
+        }
+
+        list.check_explicit("remove")?;
+        let count = tasks.iter().filter(|task| filter.matches(task, today)).count();
+        if count == 0 {
+            info("No tasks match the given options");
+            return Ok(());
+        }
+        let tasks_string = if count == 1 { String::from("1 task") } else { format!("{} tasks", count) };
+        if !skip_confirmation && !confirm(&format!("Remove {} from list \'{}\'?", tasks_string, list.name()))? {
+            return Err(ArgError::NotConfirmed);
+        }
+
+        tasks.retain(|task| !filter.matches(task, today));
+        info(format!("Removed {} from list \'{}\'", tasks_string, list.name()).green_fg());
+        return Ok(());
+    }
+
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+
+    check_for_more_args(args_iter)?;
+
+    let task_name = tasks[task_id].name.to_owned();
+    tasks.remove(task_id);
+    info(format!("Removed task \'{}\'", task_name).green_fg());
+
+    Ok(())
+}
+
+// Remove tasks that duplicate another task, e.g. after importing tasks twice
+pub fn dedupe_tasks<T>(tasks: &mut Vec<Task>, today: NaiveDate, args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    check_for_more_args(args_iter)?;
+
+    let duplicates = duplicate_positions(tasks);
+    if duplicates.is_empty() {
+        info("No duplicate tasks found".green_fg());
+        return Ok(());
+    }
+
+    for &(i, _) in &duplicates {
+        info(format!("Removing duplicate task \'{}\' (ID {})", tasks[i].name, i + 1).green_fg());
+    }
+
+    // Tasks blocked by a removed duplicate are blocked by the kept task instead, unless that
+    // would make them block themselves
+    for &(i, kept) in &duplicates {
+        for blocked in 0..tasks.len() {
+            if tasks[blocked].blocked_by != Some(tasks[i].id) || duplicates.iter().any(|&(j, _)| j == blocked) {
+                continue;
+            }
+            if blocks_itself(tasks, blocked, kept) {
+                tasks[blocked].blocked_by = None;
+                info(format!("Task \'{}\' is no longer blocked", tasks[blocked].name).green_fg());
+            } else {
+                tasks[blocked].blocked_by = Some(tasks[kept].id);
+            }
+            tasks[blocked].touch(today);
+        }
+    }
+
+    for &(i, _) in duplicates.iter().rev() {
+        tasks.remove(i);
+    }
+    let tasks_string = if duplicates.len() == 1 { String::from("1 duplicate task") } else { format!("{} duplicate tasks", duplicates.len()) };
+    info(format!("Removed {}", tasks_string).green_fg());
+
+    Ok(())
+}
+
+// Positions of the tasks that duplicate another task (the same name, due date, color and note),
+// each with the position of the task that is kept. Of each set of duplicates, the earliest
+// created task is kept (or the first one in the list, if created on the same day). Tasks with a
+// note in a separate file are never duplicates.
+fn duplicate_positions(tasks: &[Task]) -> Vec<(usize, usize)> {
+    let is_duplicate = |a: &Task, b: &Task| {
+        a.name == b.name && a.due_date == b.due_date && a.color == b.color && a.note == b.note
+            && !a.note_file && !b.note_file
+    };
+
+    (0..tasks.len())
+        .filter_map(|i| {
+            let kept = (0..tasks.len())
+                .filter(|&j| j == i || is_duplicate(&tasks[i], &tasks[j]))
+                .min_by_key(|&j| (tasks[j].creation_date, j))
+                .unwrap();
+            (kept != i).then_some((i, kept))
+        })
+        .collect()
+}
+
+// Parse the arguments of 'move-to-list': the task and the name of the other list
+pub fn parse_move_args<T>(tasks: &[Task], mut args_iter: T) -> Result<(usize, String)>
+where
+    T: Iterator<Item = String> {
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+    let list_name = args_iter.next().ok_or(ArgError::ArgMissing(String::from("list name")))?;
+    check_for_more_args(args_iter)?;
+    validate_list_name(&list_name)?;
+    Ok((task_id, list_name))
+}
+
+// Remove a task from one list and append it to another. Returns its position in the other list.
+// A note in a separate file (in 'notes_dir') is copied into the task, as the stable id changes.
+pub fn move_task(tasks: &mut Vec<Task>, task_id: usize, notes_dir: &Path, target_tasks: &mut Vec<Task>, target_next_id: &mut u64, today: NaiveDate) -> usize {
+    if tasks[task_id].note_file {
+        tasks[task_id].note = read_note(notes_dir, tasks[task_id].id);
+        tasks[task_id].note_file = false;
+    }
+
+    let mut task = tasks.remove(task_id);
+
+    // Stable ids are only unique within a list, so tasks blocked by the moved task are unblocked
+    for blocked in tasks.iter_mut().filter(|t| t.blocked_by == Some(task.id)) {
+        blocked.blocked_by = None;
+        blocked.touch(today);
+        info(format!("Task \'{}\' is no longer blocked", blocked.name).green_fg());
+    }
+
+    task.id = *target_next_id;
+    *target_next_id += 1;
+    task.blocked_by = None; // Blocking tasks are not moved along
+    task.touch(today);
+    target_tasks.push(task);
+    target_tasks.len() - 1
+}
+
+// Confirm that the task at 'position' in the other list was moved there
+pub fn report_move(target_tasks: &[Task], position: usize, list_name: &str) {
+    info(format!("Moved task \'{}\' to list \'{}\' with ID {}", target_tasks[position].name, list_name, position + 1).green_fg());
+}
+
+// List names are used as filenames, so only allow letters, digits, '-' and '_'
+pub fn validate_list_name(name: &str) -> Result<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return Err(ArgError::InvalidListName(name.to_string()));
+    }
+    Ok(())
+}
+
+// Mark a task as done. The completion date is kept for the 'done-today' and 'done-this-week'
+// reports.
+pub fn complete_task<T>(tasks: &mut [Task], today: NaiveDate, config: &Config, mut args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+    check_for_more_args(args_iter)?;
+
+    let task = &mut tasks[task_id];
+    if let Some(date) = task.completed_date {
+        info(format!("Task \'{}\' was already done on {}", task.name, config.format_date(date)).green_fg());
+        return Ok(());
+    }
+
+    task.completed_date = Some(today);
+    task.touch(today);
+    info(format!("Task \'{}\' is done", task.name).green_fg());
+
+    Ok(())
+}
+
+// List the tasks that were completed today, or this (ISO) week
+pub fn report_done<T>(tasks: &[Task], this_week: bool, today: NaiveDate, config: &Config, args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    check_for_more_args(args_iter)?;
+
+    let period = if this_week { "this week" } else { "today" };
+    let positions = completed_positions(tasks, today, this_week);
+    if positions.is_empty() {
+        println!("No tasks were completed {}\n", period);
+        return Ok(());
+    }
+
+    println!("Completed {}:", period);
+    for i in positions {
+        println!("{:>3}  {}  {}", i + 1, config.format_date(tasks[i].completed_date.unwrap()), tasks[i].name);
+    }
+    println!();
+
+    Ok(())
+}
+
+// Positions of the tasks completed on 'today', or in the same week as 'today'
+fn completed_positions(tasks: &[Task], today: NaiveDate, this_week: bool) -> Vec<usize> {
+    let completed = |task: &Task| if this_week {
+        filter::completed_in_week_of(today)(task)
+    } else {
+        filter::completed_on(today)(task)
+    };
+    (0..tasks.len()).filter(|&i| completed(&tasks[i])).collect()
+}
+
+// Print statistics about the tasks. With '--chart', a sparkline of the number of tasks created
+// per day over the last 14 days (or the given number of days) is shown as well.
+pub fn show_stats<T>(tasks: &[Task], today: NaiveDate, config: &Config, args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let mut args_iter = args_iter.peekable();
+    let mut chart_days = None;
+    if args_iter.next_if_eq("--chart").is_some() {
+        chart_days = Some(match args_iter.next() {
+            Some(days_string) => parse_chart_days(days_string)?,
+            None => DEFAULT_CHART_DAYS,
+        });
+    }
+    check_for_more_args(args_iter)?;
+
+    let done = tasks.iter().filter(|task| filter::done()(task)).count();
+    let overdue = tasks.iter().filter(|task| filter::overdue(today)(task)).count();
+    println!("Tasks:       {:>5}", tasks.len());
+    println!("Done:        {:>5}", done);
+    println!("Overdue:     {:>5}", overdue);
+    if !tasks.is_empty() {
+        let total_age: i64 = tasks.iter().map(|task| (today - task.creation_date).num_days()).sum();
+        println!("Average age: {:>5} days", total_age / tasks.len() as i64);
+    }
+
+    if let Some(days) = chart_days {
+        let counts = created_per_day(tasks, today, days);
+        let first_day = today - Days::new(days as u64 - 1);
+        println!();
+        println!("Tasks created per day, {} to {} ({} in total, at most {} on one day):",
+                 config.format_date(first_day), config.format_date(today),
+                 counts.iter().sum::<usize>(), counts.iter().max().unwrap_or(&0));
+        println!("{}", sparkline(&counts));
+    }
+    println!();
+
+    Ok(())
+}
+
+const DEFAULT_CHART_DAYS: usize = 14;
+const MAX_CHART_DAYS: usize = 366;
+
+fn parse_chart_days(days_string: String) -> Result<usize> {
+    days_string
+        .parse::<usize>()
+        .ok()
+        .filter(|days| (1..=MAX_CHART_DAYS).contains(days))
+        .ok_or(ArgError::InvalidDayCount(days_string))
+}
+
+// The number of tasks created on each of the last 'days' days, ending with 'today'. Days on
+// which no tasks were created count as zero, so every day has its own place on the chart.
+fn created_per_day(tasks: &[Task], today: NaiveDate, days: usize) -> Vec<usize> {
+    let mut counts = vec![0; days];
+    for task in tasks {
+        let age = (today - task.creation_date).num_days();
+        if (0..days as i64).contains(&age) {
+            counts[days - 1 - age as usize] += 1;
+        }
+    }
+    counts
+}
+
+// One character per count, scaled to the largest count. A zero is shown as '·' rather than as
+// the lowest block, so a day without new tasks can be told apart from a day with a few.
+fn sparkline(counts: &[usize]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = counts.iter().copied().max().unwrap_or(0);
+    counts
+        .iter()
+        .map(|&count| match count {
+            0 => '·',
+            _ => BLOCKS[(count * BLOCKS.len()).div_ceil(max) - 1],
+        })
+        .collect()
+}
+
+// Show a desktop notification for each task that is due today or overdue. If notifications are
+// not supported, the tasks are printed instead.
+pub fn notify_tasks<T>(tasks: &[Task], today: NaiveDate, args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    check_for_more_args(args_iter)?;
+
+    let positions = due_positions(tasks, today);
+    if positions.is_empty() {
+        info("No tasks are due today or overdue");
+        return Ok(());
+    }
+
+    let mut supported = true;
+    for i in positions {
+        let message = due_message(&tasks[i], today);
+        if supported {
+            match notify_rust::Notification::new().appname("todo").summary(&tasks[i].name).body(&message).show() {
+                Ok(..) => continue,
+                Err(e) => {
+                    eprintln!("{}", format!("Desktop notifications are not supported ({}). Printing the tasks instead", e).yellow_err());
+                    supported = false;
+                }
+            }
+        }
+        println!("{:>3}  {}: {}", i + 1, tasks[i].name, message);
+    }
+
+    Ok(())
+}
+
+// Positions of the tasks that are due on or before 'today'. Tasks that are done or snoozed are
+// skipped.
+fn due_positions(tasks: &[Task], today: NaiveDate) -> Vec<usize> {
+    let (is_due, is_done, is_snoozed) = (filter::due_by(today), filter::done(), filter::snoozed(today));
+    (0..tasks.len())
+        .filter(|&i| is_due(&tasks[i]) && !is_done(&tasks[i]) && !is_snoozed(&tasks[i]))
+        .collect()
+}
+
+// Describe when a task that is due was due, e.g. 'Due today' or 'Overdue by 3 days'
+fn due_message(task: &Task, today: NaiveDate) -> String {
+    let days = (today - task.due_date.unwrap()).num_days();
+    if days == 0 {
+        String::from("Due today")
+    } else {
+        format!("Overdue by {}", days_string(days))
+    }
+}
+
+// Pin or unpin a task. Pinned tasks are kept at the top of the list by 'sort'.
+pub fn pin_task<T>(tasks: &mut [Task], pinned: bool, today: NaiveDate, mut args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+    check_for_more_args(args_iter)?;
+
+    tasks[task_id].pinned = pinned;
+    tasks[task_id].touch(today);
+    let action = if pinned { "pinned" } else { "unpinned" };
+    info(format!("Task \'{}\' was {}", tasks[task_id].name, action).green_fg());
+
+    Ok(())
+}
+
+// Set or clear a task color
+pub fn set_task_color<T>(tasks: &mut [Task], today: NaiveDate, args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let mut args_iter = args_iter.peekable();
+
+    // Either a single task, or all tasks that match the filter options. '--all' selects every
+    // task; it has to be given explicitly, so a mistyped ID can never recolor all tasks. It
+    // cannot be combined with filter options, as it would be unclear which of the two applies.
+    let mut filter = TaskFilter::default();
+    let task_id = if args_iter.peek().is_some_and(|arg| arg.starts_with("--")) {
+        let mut all = false;
+        let mut filter_option = None;
+        while let Some(option) = args_iter.next_if(|arg| arg.starts_with("--")) {
+            if option == "--all" {
+                all = true;
+            } else if filter.parse_option(&option, &mut args_iter)? {
+                filter_option = Some(option);
+            } else {
+                return Err(ArgError::UnknownOption(option));
+            }
+        }
+        if let (true, Some(option)) = (all, filter_option) {
+            return Err(ArgError::ConflictingOptions(String::from("--all"), option));
+        }
+        None
+    } else {
+        Some(parse_task_id(tasks, &args_iter.next())?)
+    };
+
+    // Get the color string from the argument and look up the color. Change the string
+    // color for the message to the user
+    let mut color_string = args_iter.next()
+        .ok_or(ArgError::ArgMissing(String::from("task name")))?;
+    let color = Color::parse(&color_string)?;
+    let hex_string = color_string.starts_with('#').then(|| color_string.clone());
+    if let Some(color) = &color {
+        color_string = color.name().to_string();
+    }
+    color_string = match color {
+        Some(Color::Red) => color_string.red_fg(),
+        Some(Color::Yellow) => color_string.yellow_fg(),
+        Some(Color::Green) => color_string.green_fg(),
+        Some(Color::Blue) => color_string.blue_fg(),
+        Some(Color::Purple) => color_string.purple_fg(),
+        None => String::new()
+    };
+    if let Some(hex_string) = hex_string {
+        color_string.push_str(&format!(" (closest to {})", hex_string).green_fg());
+    }
+
+    check_for_more_args(args_iter)?;
+
+    let Some(task_id) = task_id else {
+        let mut count = 0;
+        for task in tasks.iter_mut().filter(|task| filter.matches(task, today)) {
+            task.color = color.clone();
+            task.touch(today);
+            count += 1;
+        }
+        let tasks_string = if count == 1 { String::from("1 task") } else { format!("{} tasks", count) };
+        if color_string.is_empty() {
+            info(format!("Color removed for {}", tasks_string).green_fg());
+        } else {
+            info(format!("{}{}", format!("Color for {} was set to ", tasks_string).green_fg(), color_string));
+        }
+        return Ok(());
+    };
+
+    // Set the color
+    tasks[task_id].color = color;
+    tasks[task_id].touch(today);
+
+    // Print the result
+    if color_string.is_empty() {
+        info(format!("Color removed for task \'{}\'", tasks[task_id].name).green_fg());
+    } else {
+        info(format!("{}{}", format!("Color for task \'{}\' was set to ", tasks[task_id].name).green_fg(), color_string));
+    }
+
+    Ok(())
+}
+
+// Set several attributes of a task at once, e.g. 'set 3 --color red --due 2025-07-10'. Values
+// are validated as by the individual commands. If any value is invalid, all invalid values are
+// reported and the task is not changed. Attributes that are not given are left unchanged.
+pub fn set_attributes<T>(tasks: &mut [Task], today: NaiveDate, config: &Config, mut args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+
+    let mut color = None;
+    let mut due_date = None;
+    let mut estimate = None;
+    let mut errors = vec![];
+    while let Some(option) = args_iter.next() {
+        let attribute = match option.as_str() {
+            "--color" => "color",
+            "--due" => "due date",
+            "--estimate" => "estimate",
+            _ => return Err(ArgError::UnknownOption(option)),
+        };
+        let Some(value) = args_iter.next() else {
+            errors.push(ArgError::ArgMissing(String::from(attribute)));
+            break;
+        };
+        let result = match option.as_str() {
+            "--color" => Color::parse(&value).map(|value| color = Some(value)).map_err(ArgError::from),
+            "--due" => parse_due_value(&value, tasks[task_id].due_date, today, config.week_end).map(|value| due_date = Some(value)),
+            _ => parse_estimate_value(&value).map(|value| estimate = Some(value)),
+        };
+        if let Err(e) = result {
+            errors.push(e);
+        }
+    }
+
+    match errors.len() {
+        0 if color.is_none() && due_date.is_none() && estimate.is_none() => {
+            return Err(ArgError::ArgMissing(String::from("attribute to set (--color, --due or --estimate)")));
+        },
+        0 => (),
+        1 => return Err(errors.remove(0)),
+        _ => return Err(ArgError::MultipleErrors(errors)),
+    }
+
+    let task = &mut tasks[task_id];
+    let mut changes = vec![];
+    if let Some(color) = color {
+        changes.push(format!("color {}", color.as_ref().map_or("none", |c| c.name())));
+        task.color = color;
+    }
+    if let Some(due_date) = due_date {
+        changes.push(format!("due date {}", due_date.map_or(String::from("none"), |d| config.format_date(d))));
+        task.due_date = due_date;
+    }
+    if let Some(estimate) = estimate {
+        changes.push(format!("estimate {}", estimate.map_or(String::from("none"), |m| minutes_string_from(m as u64))));
+        task.estimate = estimate;
+    }
+    task.touch(today);
+    info(format!("Task \'{}\' was updated: {}", task.name, changes.join(", ")).green_fg());
+
+    Ok(())
+}
+
+// Adds a note to the task
+pub fn add_note<T>(tasks: &mut [Task], today: NaiveDate, config: &Config, args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let mut args_iter = args_iter.peekable();
+    let mut truncate = false;
+    let mut replace = false;
+    let mut note_filename = None;
+    while let Some(option) = args_iter.next_if(|arg| arg.starts_with("--")) {
+        match option.as_str() {
+            "--truncate" => truncate = true,
+            "--replace" => replace = true,
+            "--file" => note_filename = Some(args_iter.next().ok_or(ArgError::ArgMissing(String::from("note file")))?),
+            _ => return Err(ArgError::UnknownOption(option)),
+        }
+    }
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+
+    // The note is either read from a file, or given as the remaining arguments
+    let from_file = note_filename.is_some();
+    let mut note = match note_filename {
+        Some(filename) => {
+            check_for_more_args(args_iter)?;
+            let contents = read_to_string(&filename)
+                .map_err(|e| ArgError::UnreadableFile(format!("{} ({})", filename, e)))?;
+            trim_note(&strip_escape_sequences(&contents))
+        }
+        None => trim_note(&strip_escape_sequences(&args_iter.collect::<Vec<String>>().join(" "))),
+    };
+
+    if !from_file && note == *"clear" {
+        tasks[task_id].note = String::new();
+        tasks[task_id].note_file = false;
+        tasks[task_id].touch(today);
+        return Ok(());
+    }
+
+    if tasks[task_id].note_file {
+        return Err(ArgError::NoteInFile);
+    }
+
+    // Protect the tasks file against e.g. accidentally pasting a huge text. The limit applies
+    // to the whole note, including the separating newline.
+    let max_length = config.max_note_length;
+    let existing_length = match tasks[task_id].note.chars().count() {
+        _ if replace => 0,
+        0 => 0,
+        length => length + 1,
+    };
+    let length = existing_length + note.chars().count();
+    if length > max_length {
+        if !truncate {
+            return Err(ArgError::NoteTooLong(length, max_length));
+        }
+        note = note.chars().take(max_length.saturating_sub(existing_length)).collect();
+        info(format!("Note truncated to the maximum length of {} characters", max_length).yellow_fg());
+    }
+
+    if replace {
+        tasks[task_id].note.clear();
+    } else if !tasks[task_id].note.is_empty() {
+        tasks[task_id].note.push('\n');
+    }
+    tasks[task_id].note.push_str(&note);
+    tasks[task_id].touch(today);
+
+    Ok(())
+}
+
+// Edit the note of a task in an external editor. The note is moved to a separate file
+// (notes/<stable id>.md), which keeps the tasks file small for long notes.
+pub fn edit_note<T>(tasks: &mut [Task], notes_dir: &Path, editor: Option<String>, today: NaiveDate, mut args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+    check_for_more_args(args_iter)?;
+
+    let (program, editor_args) = editor_command(editor, cfg!(windows))?;
+    let task = &mut tasks[task_id];
+    let path = note_filename(notes_dir, task.id);
+
+    // Move the note to its own file. If the editor fails, the file is removed again, so the
+    // note stays where it was.
+    let move_note = !task.note_file;
+    if move_note {
+        create_dir_all(notes_dir).map_err(|e| ArgError::UnwritableFile(format!("{} ({})", notes_dir.display(), e)))?;
+        write(&path, &task.note).map_err(|e| ArgError::UnwritableFile(format!("{} ({})", path.display(), e)))?;
+    }
+
+    if let Err(e) = run_editor(&program, &editor_args, &path) {
+        if move_note {
+            let _ = remove_file(&path);
+        }
+        return Err(e);
+    }
+
+    if move_note {
+        task.note = String::new();
+        task.note_file = true;
+    }
+    task.touch(today);
+
+    info(format!("Note for task \'{}\' is saved in {}", task.name, path.display()).green_fg());
+
+    Ok(())
+}
+
+// Determine the editor program and its arguments from the EDITOR environment variable (e.g.
+// 'code --wait'). Without it, Notepad is used on Windows; elsewhere, there is no safe default.
+fn editor_command(editor: Option<String>, windows: bool) -> Result<(String, Vec<String>)> {
+    let editor = match editor.filter(|e| !e.trim().is_empty()) {
+        Some(editor) => editor,
+        None if windows => String::from("notepad"),
+        None => return Err(ArgError::EditorNotSet),
+    };
+
+    let mut words = editor.split_whitespace().map(String::from);
+    let program = words.next().unwrap();
+    Ok((program, words.collect()))
+}
+
+// Run the editor on a file and wait for it to close
+fn run_editor(program: &str, editor_args: &[String], path: &Path) -> Result<()> {
+    let status = Command::new(program)
+        .args(editor_args)
+        .arg(path)
+        .status()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => ArgError::EditorNotFound(program.to_string()),
+            _ => ArgError::EditorFailed(format!("{} ({})", program, e)),
+        })?;
+    if !status.success() {
+        return Err(ArgError::EditorFailed(format!("{} ({})", program, status)));
+    }
+    Ok(())
+}
+
+// Sort tasks by color, then due date, or by the given sort keys. With 'completed_last', tasks
+// that are done come after the other tasks.
+pub fn sort_tasks<T>(tasks: &mut [Task], completed_last: bool, args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let keys = args_iter.map(|key| sort::SortKey::parse(&key)).collect::<Result<Vec<_>>>()?;
+    if keys.is_empty() {
+        sort_by_rank(tasks, completed_last);
+    } else {
+        sort_by_keys(tasks, &keys, completed_last);
+    }
+
+    Ok(())
+}
+
+// Sort tasks by color and due date. With 'completed_last', tasks that are done come after the
+// other tasks, and are sorted by color and due date among themselves.
+fn sort_by_rank(tasks: &mut [Task], completed_last: bool) {
+    sort_by_keys(tasks, &sort::SortKey::DEFAULT, completed_last);
+}
+
+// Sort tasks by the given keys, in order: a key only decides between tasks that are equal for
+// all keys before it.
+fn sort_by_keys(tasks: &mut [Task], keys: &[sort::SortKey], completed_last: bool) {
+    // Pinned tasks stay at the top, in their current order
+    tasks.sort_by(sort::pinned_first);
+    let number_pinned = tasks.iter().filter(|task| task.pinned).count();
+    let tasks = &mut tasks[number_pinned..];
+
+    // Tasks that are equal for all keys are ordered by stable id, so the result does not depend
+    // on the current order (which avoids needless changes to the tasks file)
+    tasks.sort_by(|a, b| {
+        let done_order = if completed_last { sort::completed_last(a, b) } else { Ordering::Equal };
+        keys.iter()
+            .fold(done_order, |order, key| order.then_with(|| key.compare(a, b)))
+            .then_with(|| sort::by_id(a, b))
+    });
+}
+
+// Add a due date to the task. A relative delta such as '+7d' or '-2d' shifts the current due
+// date (or today, if no due date is set) by that number of days.
+pub fn add_duedate<T>(tasks: &mut [Task], today: NaiveDate, config: &Config, mut args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+    let date_string = args_iter.next().ok_or(ArgError::ArgMissing(String::from("date")))?;
+    check_for_more_args(args_iter)?;
+    
+    tasks[task_id].due_date = parse_due_value(&date_string, tasks[task_id].due_date, today, config.week_end)?;
+    tasks[task_id].touch(today);
+    match tasks[task_id].due_date {
+        Some(date) => info(format!("Due date for task \'{}\' was set to {}",
+            tasks[task_id].name,
+            config.format_date(date)
+            ).green_fg()),
+        None => info(format!("Due date removed for task \'{}\'", tasks[task_id].name).green_fg()),
+    }
+
+    Ok(())
+}
+
+// Parse the value of a due date: 'clear' (no due date), a relative delta such as '+7d' from the
+// current due date (or today), 'eow' or 'eom' (with the week ending on 'week_end'), or a date
+fn parse_due_value(date_string: &str, due_date: Option<NaiveDate>, today: NaiveDate, week_end: Weekday) -> Result<Option<NaiveDate>> {
+    if date_string == "clear" {
+        return Ok(None);
+    }
+    if let Some(date) = end_of_period(date_string, today, week_end) {
+        return Ok(Some(date));
+    }
+    let date = match parse_day_delta(date_string) {
+        Some(days) => Duration::try_days(days)
+            .and_then(|delta| due_date.unwrap_or(today).checked_add_signed(delta))
+            .ok_or(ArgError::DateOutOfRange)?,
+        None => parse_due_date(date_string, today)?
+    };
+    Ok(Some(date))
+}
+
+// Parse the keywords 'eow' (the end of the week, i.e. the next 'week_end' day, which may be today)
+// and 'eom' (the last day of the current month)
+fn end_of_period(keyword: &str, today: NaiveDate, week_end: Weekday) -> Option<NaiveDate> {
+    match keyword {
+        "eow" => {
+            let days = (7 + week_end.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+            today.checked_add_days(Days::new(days as u64))
+        },
+        "eom" => {
+            let first_of_month = today.with_day(1)?;
+            first_of_month.checked_add_months(Months::new(1))?.pred_opt()
+        },
+        _ => None,
+    }
+}
+
+// Hide a task from the list for a number of days, without changing its due date
+pub fn snooze_task<T>(tasks: &mut [Task], today: NaiveDate, config: &Config, mut args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+    let days_string = args_iter.next().ok_or(ArgError::ArgMissing(String::from("number of days")))?;
+    check_for_more_args(args_iter)?;
+
+    let days = days_string
+        .parse::<i64>()
+        .ok()
+        .filter(|&days| days > 0)
+        .ok_or(ArgError::InvalidDayCount(days_string))?;
+    let start_date = Duration::try_days(days)
+        .and_then(|duration| today.checked_add_signed(duration))
+        .ok_or(ArgError::DateOutOfRange)?;
+
+    tasks[task_id].start_date = Some(start_date);
+    tasks[task_id].touch(today);
+    info(format!("Task \'{}\' is snoozed and will reappear on {}",
+        tasks[task_id].name,
+        config.format_date(start_date)
+        ).green_fg());
+
+    Ok(())
+}
+
+// Parse a due date of the form YYYY-MM-DD. The shorthands MM-DD and DD are in the current year,
+// and in the current year and month, respectively.
+fn parse_due_date(date_string: &str, today: NaiveDate) -> Result<NaiveDate> {
+    if let Ok(date) = NaiveDate::parse_from_str(date_string, "%Y-%m-%d") {
+        return Ok(date);
+    }
+
+    let is_number = |part: &str| (1..=2).contains(&part.len()) && part.chars().all(|c| c.is_ascii_digit());
+    let parts: Vec<&str> = date_string.split('-').collect();
+    if !parts.iter().all(|part| is_number(part)) {
+        return Err(ArgError::IncorrectDateFormat);
+    }
+    let numbers: Vec<u32> = parts.iter().map(|part| part.parse().unwrap()).collect();
+    let date = match numbers[..] {
+        [month, day] => NaiveDate::from_ymd_opt(today.year(), month, day),
+        [day] => NaiveDate::from_ymd_opt(today.year(), today.month(), day),
+        _ => None,
+    };
+    date.ok_or(ArgError::IncorrectDateFormat)
+}
+
+// Set the estimated effort of a task in minutes, or remove it with 'clear'
+pub fn estimate_task<T>(tasks: &mut [Task], today: NaiveDate, mut args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+    let minutes_string = args_iter.next().ok_or(ArgError::ArgMissing(String::from("number of minutes")))?;
+    check_for_more_args(args_iter)?;
+
+    tasks[task_id].estimate = parse_estimate_value(&minutes_string)?;
+    tasks[task_id].touch(today);
+    match tasks[task_id].estimate {
+        Some(minutes) => info(format!("Estimate for task \'{}\' was set to {}", tasks[task_id].name, minutes_string_from(minutes as u64)).green_fg()),
+        None => info(format!("Estimate removed for task \'{}\'", tasks[task_id].name).green_fg()),
+    }
+
+    Ok(())
+}
+
+// Set the URL of a task (e.g. a ticket or document), or remove it with 'clear'
+pub fn set_url<T>(tasks: &mut [Task], today: NaiveDate, mut args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+    let url_string = args_iter.next().ok_or(ArgError::ArgMissing(String::from("URL")))?;
+    check_for_more_args(args_iter)?;
+
+    tasks[task_id].url = parse_url_value(&url_string)?;
+    tasks[task_id].touch(today);
+    match &tasks[task_id].url {
+        Some(url) => info(format!("URL for task \'{}\' was set to {}", tasks[task_id].name, url).green_fg()),
+        None => info(format!("URL removed for task \'{}\'", tasks[task_id].name).green_fg()),
+    }
+
+    Ok(())
+}
+
+// Parse the value of a URL: 'clear' (no URL) or an http(s) URL with a host, without whitespace
+fn parse_url_value(url_string: &str) -> Result<Option<String>> {
+    if url_string == "clear" {
+        return Ok(None);
+    }
+    let rest = url_string
+        .strip_prefix("https://")
+        .or_else(|| url_string.strip_prefix("http://"))
+        .ok_or(ArgError::InvalidUrl(url_string.to_string()))?;
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    if host.is_empty() || host.starts_with(':') || !url_string.chars().all(is_url_char) || !valid_percent_escapes(url_string) {
+        return Err(ArgError::InvalidUrl(url_string.to_string()));
+    }
+    Ok(Some(url_string.to_string()))
+}
+
+// Characters allowed in a URL (RFC 3986). Others, such as quotes, '<', '>', '|' and '^', have to
+// be percent-encoded, so they can never reach a program that would treat them specially.
+fn is_url_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-._~:/?#[]@!$&'()*+,;=%".contains(c)
+}
+
+// Every '%' starts a percent-encoded byte, e.g. '%20'
+fn valid_percent_escapes(url_string: &str) -> bool {
+    url_string
+        .split('%')
+        .skip(1)
+        .all(|rest| rest.len() >= 2 && rest.as_bytes()[..2].iter().all(u8::is_ascii_hexdigit))
+}
+
+// Open the URL of a task in the default browser
+pub fn open_url<T>(tasks: &[Task], mut args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+    check_for_more_args(args_iter)?;
+    let url = tasks[task_id].url.as_ref().ok_or(ArgError::UrlNotSet)?;
+
+    let (program, opener_args) = opener_command(env::consts::OS);
+    let status = Command::new(&program)
+        .args(&opener_args)
+        .arg(url)
+        .status()
+        .map_err(|e| ArgError::OpenerFailed(format!("{} ({})", program, e)))?;
+    if !status.success() {
+        return Err(ArgError::OpenerFailed(format!("{} ({})", program, status)));
+    }
+    info(format!("Opened {}", url).green_fg());
+
+    Ok(())
+}
+
+// The program (and its arguments) that opens a URL in the default browser on the given OS. The
+// URL is never passed through a shell: on Windows, 'cmd /C start' would run anything after a
+// '&' in the URL as a separate command.
+fn opener_command(os: &str) -> (String, Vec<String>) {
+    match os {
+        "windows" => (String::from("rundll32"), vec![String::from("url.dll,FileProtocolHandler")]),
+        "macos" => (String::from("open"), vec![]),
+        _ => (String::from("xdg-open"), vec![]),
+    }
+}
+
+// Parse the value of an estimate: 'clear' (no estimate) or a positive number of minutes
+fn parse_estimate_value(minutes_string: &str) -> Result<Option<u32>> {
+    if minutes_string == "clear" {
+        return Ok(None);
+    }
+    minutes_string
+        .parse::<u32>()
+        .ok()
+        .filter(|&minutes| minutes > 0)
+        .map(Some)
+        .ok_or(ArgError::InvalidEstimate(minutes_string.to_string()))
+}
+
+// Format a number of minutes as hours and minutes, e.g. '45m', '2h' or '1h 30m'
+fn minutes_string_from(minutes: u64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
+
+// Parse a relative number of days of the form '+Nd' or '-Nd'
+fn parse_day_delta(delta_string: &str) -> Option<i64> {
+    let days_string = delta_string.strip_suffix('d')?;
+    if !days_string.starts_with(['+', '-']) {
+        return None;
+    }
+    days_string.parse::<i64>().ok()
+}
+
+// Parse the number of a subtask and check it exists. Return number - 1.
+fn parse_subtask_number(task: &Task, number_opt: Option<String>) -> Result<usize> {
+    let number_string = number_opt.ok_or(ArgError::ArgMissing(String::from("subtask number")))?;
+    let number = number_string
+        .parse::<usize>()
+        .map_err(|_| ArgError::InvalidSubtaskNumber(number_string))?;
+
+    if number > task.subtasks.len() || number == 0 {
+        Err(ArgError::SubtaskNotFound)
+    } else {
+        Ok(number - 1)
+    }
+}
+
+// Add, complete or remove subtasks of a task
+pub fn edit_subtasks<T>(tasks: &mut [Task], today: NaiveDate, mut args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let action = args_iter.next()
+        .ok_or(ArgError::ArgMissing(String::from("subtask action (add, done or rm)")))?;
+    if !matches!(action.as_str(), "add" | "done" | "rm") {
+        return Err(ArgError::InvalidSubtaskAction(action));
+    }
+
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+    let task = &mut tasks[task_id];
+
+    match action.as_str() {
+        "add" => {
+            let text = validate_subtask_text(args_iter.collect::<Vec<String>>().join(" "))?;
+            info(format!("Added subtask \'{}\' to task \'{}\'", text, task.name).green_fg());
+            task.subtasks.push(Subtask { text, done: false });
+        },
+        "done" => {
+            let number = parse_subtask_number(task, args_iter.next())?;
+            check_for_more_args(args_iter)?;
+            task.subtasks[number].done = true;
+            info(format!("Subtask \'{}\' of task \'{}\' is done", task.subtasks[number].text, task.name).green_fg());
+        },
+        _ => {
+            let number = parse_subtask_number(task, args_iter.next())?;
+            check_for_more_args(args_iter)?;
+            let subtask = task.subtasks.remove(number);
+            info(format!("Removed subtask \'{}\' from task \'{}\'", subtask.text, task.name).green_fg());
+        }
+    }
+    task.touch(today);
+
+    Ok(())
+}
+
+// Whether the task at 'task_id' would be blocked by itself, directly or indirectly, if it were
+// blocked by the task at 'blocker_id'. Follows the chain of blocking tasks.
+fn blocks_itself(tasks: &[Task], task_id: usize, blocker_id: usize) -> bool {
+    let mut current = Some(blocker_id);
+    while let Some(position) = current {
+        if position == task_id {
+            return true;
+        }
+        current = blocker_position(tasks, &tasks[position]);
+    }
+    false
+}
+
+// Mark a task as blocked by another task, or clear the blocking task
+pub fn block_task<T>(tasks: &mut [Task], today: NaiveDate, mut args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+    let blocker_string = args_iter.next()
+        .ok_or(ArgError::ArgMissing(String::from("id of the blocking task")))?;
+    check_for_more_args(args_iter)?;
+
+    if blocker_string == "clear" {
+        tasks[task_id].blocked_by = None;
+        tasks[task_id].touch(today);
+        info(format!("Task \'{}\' is no longer blocked", tasks[task_id].name).green_fg());
+        return Ok(());
+    }
+
+    let blocker_id = parse_task_id(tasks, &Some(blocker_string))?;
+
+    if blocks_itself(tasks, task_id, blocker_id) {
+        return Err(ArgError::CircularBlock);
+    }
+
+    tasks[task_id].blocked_by = Some(tasks[blocker_id].id);
+    tasks[task_id].touch(today);
+    info(format!("Task \'{}\' is now blocked by task \'{}\'", tasks[task_id].name, tasks[blocker_id].name).green_fg());
+
+    Ok(())
+}
+
+// Remove all tasks, after confirmation by the user
+pub fn purge_tasks<T>(tasks: &mut Vec<Task>, list: &ListScope, args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    // Parse options
+    let mut skip_confirmation = false;
+    for arg in args_iter {
+        match arg.as_str() {
+            "--yes" => skip_confirmation = true,
+            other => return Err(ArgError::UnknownOption(other.to_string())),
+        }
+    }
+
+    list.check_explicit("purge")?;
+    if tasks.is_empty() {
+        info(format!("List \'{}\' has no tasks to remove", list.name()));
+        return Ok(());
+    }
+    if !skip_confirmation && !confirm(&format!("Remove all {} tasks from list \'{}\'?", tasks.len(), list.name()))? {
+        return Err(ArgError::NotConfirmed);
+    }
+
+    let number_removed = tasks.len();
+    tasks.clear();
+    info(format!("Removed {} tasks from list \'{}\'", number_removed, list.name()).green_fg());
+
+    Ok(())
+}
+
+// Check the arguments of 'undo'. If the undo would add or remove more than one task (e.g. when
+// undoing 'purge'), ask for confirmation first, unless '--yes' is given. 'task_count' is the
+// number of tasks the undo would add or remove. A dry run only previews, so it never asks.
+pub fn confirm_undo<T>(task_count: usize, dry_run: bool, args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let mut skip_confirmation = dry_run;
+    for arg in args_iter {
+        match arg.as_str() {
+            "--yes" => skip_confirmation = true,
+            other => return Err(ArgError::UnknownOption(other.to_string())),
+        }
+    }
+
+    if !skip_confirmation && task_count > 1 && !confirm(&format!("Undo would add or remove {} tasks. Continue?", task_count))? {
+        return Err(ArgError::NotConfirmed);
+    }
+
+    Ok(())
+}
+
+// Show program help. If a command is given, only the help for that command is shown.
+pub fn show_help<T>(mut args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let topic = args_iter.next();
+    check_for_more_args(args_iter)?;
+
+    let help_str = include_str!("help.txt");
+    let sections = help_sections(help_str);
+    let Some(topic) = topic else {
+        println!("{}\n", full_help(help_str, &sections));
+        return Ok(());
+    };
+
+    let matching_sections: Vec<&String> = sections
+        .iter()
+        .filter(|(command, _)| *command == topic)
+        .map(|(_, section)| section)
+        .collect();
+
+    if matching_sections.is_empty() {
+        let topics: Vec<&str> = COMMANDS.iter().map(|command| command.name).collect();
+        let closest = commands::closest(&topic).map(String::from);
+        return Err(ArgError::UnknownHelpTopic(topic, closest, topics.join(", ")));
+    }
+
+    println!("Usage: todo [options] {} [arguments]\n", topic);
+    for section in matching_sections {
+        print!("{}", section);
+    }
+    println!();
+
+    Ok(())
+}
+
+// The help text, with the list of commands generated from the command table: the sections of
+// each command, in the order of the table
+fn full_help(help_str: &str, sections: &[(&str, String)]) -> String {
+    let mut help = help_str
+        .lines()
+        .take_while(|line| !line.starts_with("Available commands:"))
+        .map(|line| format!("{}\n", line))
+        .collect::<String>();
+    help.push_str("Available commands:\n");
+    for command in COMMANDS {
+        for (_, section) in sections.iter().filter(|(name, _)| *name == command.name) {
+            help.push_str(section);
+        }
+    }
+    help.trim_end().to_string()
+}
+
+// Split the list of commands in the help text into one section per usage line. Each section
+// starts with the usage line (indented by two spaces), followed by its indented description.
+fn help_sections(help_str: &str) -> Vec<(&str, String)> {
+    let mut sections: Vec<(&str, String)> = vec![];
+
+    let commands = help_str
+        .lines()
+        .skip_while(|line| !line.starts_with("Available commands:"))
+        .skip(1);
+    for line in commands {
+        let is_usage_line = line.starts_with("  ") && !line[2..].starts_with(' ');
+        if is_usage_line {
+            let command = line.split_whitespace().next().unwrap_or_default();
+            sections.push((command, String::new()));
+        }
+        if let Some((_, section)) = sections.last_mut() {
+            section.push_str(line);
+            section.push('\n');
+        }
+    }
+
+    sections
+}
+
+// Export tasks in another format to stdout. Only iCalendar ('ical') is supported.
+pub fn export_tasks<T>(tasks: &[Task], notes_dir: &Path, args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let mut args_iter = args_iter.peekable();
+    args_iter.next_if_eq("--format");
+    let format = args_iter.next().ok_or(ArgError::ArgMissing(String::from("export format")))?;
+    check_for_more_args(args_iter)?;
+
+    match format.as_str() {
+        "ical" => print!("{}", ical_calendar(tasks, notes_dir, Utc::now())),
+        other => return Err(ArgError::UnknownExportFormat(other.to_string())),
+    }
+
+    Ok(())
+}
+
+// Build an iCalendar file (RFC 5545) with an all-day event on the due date of each task.
+// Tasks without a due date are skipped.
+fn ical_calendar(tasks: &[Task], notes_dir: &Path, now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        format!("PRODID:-//todo-rs//ToDo {}//EN", env!("CARGO_PKG_VERSION")),
+        String::from("CALSCALE:GREGORIAN"),
+    ];
+
+    let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    for task in tasks {
+        let Some(due_date) = task.due_date else {
+            continue;
+        };
+        let note = if task.note_file { read_note(notes_dir, task.id) } else { task.note.to_owned() };
+
+        lines.push(String::from("BEGIN:VEVENT"));
+        lines.push(format!("UID:task-{}@todo-rs", task.id));
+        lines.push(format!("DTSTAMP:{}", timestamp));
+        lines.push(format!("DTSTART;VALUE=DATE:{}", due_date.format("%Y%m%d")));
+        if let Some(end_date) = due_date.succ_opt() {
+            lines.push(format!("DTEND;VALUE=DATE:{}", end_date.format("%Y%m%d")));
+        }
+        lines.push(format!("SUMMARY:{}", ical_escape(&task.name)));
+        if !note.trim().is_empty() {
+            lines.push(format!("DESCRIPTION:{}", ical_escape(note.trim_end())));
+        }
+        lines.push(String::from("END:VEVENT"));
+    }
+    lines.push(String::from("END:VCALENDAR"));
+
+    lines.iter().map(|line| ical_fold(line) + "\r\n").collect()
+}
+
+// Escape text values for iCalendar
+fn ical_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace(['\r', '\n'], "\\n")
+}
+
+// Fold lines longer than 75 octets, as required by iCalendar. Continuation lines start with a
+// space. Multi-byte characters are never split.
+fn ical_fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut line_length = 0;
+    for c in line.chars() {
+        if line_length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            line_length = 1;
+        }
+        folded.push(c);
+        line_length += c.len_utf8();
+    }
+    folded
+}
+
+// Parse the number of changes to go back for 'recover'
+pub fn parse_undo_steps<T>(mut args_iter: T) -> Result<usize>
+where
+    T: Iterator<Item = String> {
+    let steps_string = args_iter.next().ok_or(ArgError::ArgMissing(String::from("number of undos")))?;
+    check_for_more_args(args_iter)?;
+    steps_string
+        .parse::<usize>()
+        .ok()
+        .filter(|&steps| steps > 0)
+        .ok_or(ArgError::InvalidUndoSteps(steps_string))
+}
+
+// Print the JSON Schema of the tasks file, which can be used to validate hand-edited files
+pub fn show_schema<T>(args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    check_for_more_args(args_iter)?;
+    print!("{}", include_str!("schema.json"));
+    Ok(())
+}
+
+// Rename a task. With '--append', the text is added to the existing name instead.
+pub fn rename_task<T>(tasks: &mut [Task], today: NaiveDate, config: &Config, args_iter: T) -> Result<()>
+where
+    T: Iterator<Item = String> {
+    let mut args_iter = args_iter.peekable();
+    let task_id = parse_task_id(tasks, &args_iter.next())?;
+    let append = args_iter.next_if_eq("--append").is_some();
+    let name_old = tasks[task_id].name.to_owned();
+    let text = validate_task_name(args_iter.collect::<Vec<String>>().join(" "))?;
+
+    let name_new = if append {
+        if text.is_empty() {
+            return Err(ArgError::ArgMissing(String::from("text to append")));
+        }
+        format!("{} {}", name_old, text)
+    } else {
+        if text.is_empty() {
+            return Err(ArgError::ArgMissing(String::from("new name")));
+        }
+        text
+    };
+
+    tasks[task_id].name = name_new;
+    tasks[task_id].touch(today);
+
+    // For long names, a small typo is easily missed, so the changes are highlighted. Without
+    // colors, a highlight is not possible.
+    let long = name_old.width().max(tasks[task_id].name.width()) > 30;
+    if long && config.colors {
+        let highlighted: String = text_changes(&name_old, &tasks[task_id].name)
+            .into_iter()
+            .map(|change| match change {
+                Change::Same(text) => text,
+                Change::Removed(text) => text.red_fg(),
+                Change::Added(text) => text.green_fg(),
+            })
+            .collect();
+        info(format!("{}{}{}", "Renamed task \'".green_fg(), highlighted, "\'".green_fg()));
+    } else {
+        info(format!("Renamed task \'{}\' to \'{}\'", name_old, tasks[task_id].name).green_fg());
+    }
+
+    Ok(())
+}
+
+// A part of a text that is unchanged, removed or added
+#[derive(Debug, PartialEq)]
+enum Change {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+// Character-level differences between two texts, based on the longest common subsequence of
+// their graphemes. Consecutive graphemes with the same kind of change are merged.
+fn text_changes(old: &str, new: &str) -> Vec<Change> {
+    let old: Vec<&str> = old.graphemes(true).collect();
+    let new: Vec<&str> = new.graphemes(true).collect();
+
+    // common[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes: Vec<Change> = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        let change = if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+            Change::Same(old[i - 1].to_string())
+        } else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+            i += 1;
+            Change::Removed(old[i - 1].to_string())
+        } else {
+            j += 1;
+            Change::Added(new[j - 1].to_string())
+        };
+        match (changes.last_mut(), change) {
+            (Some(Change::Same(text)), Change::Same(next))
+            | (Some(Change::Removed(text)), Change::Removed(next))
+            | (Some(Change::Added(text)), Change::Added(next)) => text.push_str(&next),
+            (_, change) => changes.push(change),
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
     use super::*;
+    use crate::file_io::{Meta, load_tasks, save_file};
+    use chrono::TimeZone;
     use std::vec::IntoIter;
 
+    // A fixed date, so the tests do not depend on the day they run
+    fn fixed_today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 7, 17).unwrap()
+    }
+
+    // The default list, without other lists next to it
+    fn default_list() -> ListScope {
+        ListScope { filename: PathBuf::from("tasks.json"), explicit: false, list_names: |_| vec![], load_tasks: |_| Ok(vec![]) }
+    }
+
+    #[test]
+    fn test_create_task() {
+
+        let args_iter_correct: IntoIter<String> = vec![String::from("test")].into_iter();
+        let args_iter_correct2: IntoIter<String> = vec![String::from("test"), String::from("2")].into_iter();
+        let args_iter_missing: IntoIter<String> = vec![].into_iter();
+
+        let mut tasks: Vec<Task> = vec![];
+        let mut next_id = 1;
+        assert!(matches!(
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_correct),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].name, String::from("test"));
+
+        tasks = vec![];
+        assert!(matches!(
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_correct2),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].name, String::from("test 2"));
+
+        assert!(matches!(
+            create_task(&mut vec![], &mut next_id, fixed_today(), &Config::default(), args_iter_missing),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+
+        // New tasks get the default color from the config
+        let config = Config { default_color: Some(Color::Green), ..Config::default() };
+        create_task(&mut tasks, &mut next_id, fixed_today(), &config, vec![String::from("green")].into_iter()).unwrap();
+        assert_eq!(tasks[1].color, Some(Color::Green));
+    }
+
+    #[test]
+    fn test_create_task_created() {
+        let args_iter_correct: IntoIter<String> = vec![String::from("--created"), String::from("2024-03-01"), String::from("test")].into_iter();
+        let args_iter_incorrect: IntoIter<String> = vec![String::from("--created"), String::from("20240301"), String::from("test")].into_iter();
+        let args_iter_future: IntoIter<String> = vec![String::from("--created"), String::from("9999-01-01"), String::from("test")].into_iter();
+        let args_iter_missing: IntoIter<String> = vec![String::from("--created")].into_iter();
+
+        let mut tasks: Vec<Task> = vec![];
+        let mut next_id = 1;
+        assert!(matches!(
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_correct),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].name, String::from("test"));
+        assert_eq!(tasks[0].creation_date, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+
+        assert!(matches!(
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_incorrect),
+            Result::Err(ArgError::IncorrectDateFormat)
+        ));
+
+        assert!(matches!(
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_future),
+            Result::Err(ArgError::FutureCreationDate)
+        ));
+
+        assert!(matches!(
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_missing),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+        assert_eq!(tasks.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_task_id_by_name() {
+        let tasks = vec![
+            Task::new(String::from("Buy groceries"), fixed_today()),
+            Task::new(String::from("Call the bank"), fixed_today()),
+            Task::new(String::from("Book flights"), fixed_today()),
+        ];
+
+        assert_eq!(parse_task_id(&tasks, &Some(String::from("groceries"))), Ok(0));
+        assert_eq!(parse_task_id(&tasks, &Some(String::from("BANK"))), Ok(1));
+        assert_eq!(parse_task_id(&tasks, &Some(String::from("dentist"))), Err(ArgError::TaskNotFound));
+        assert!(matches!(
+            parse_task_id(&tasks, &Some(String::from("b"))),
+            Result::Err(ArgError::AmbiguousTask(..))
+        ));
+        assert!(matches!(
+            parse_task_id(&tasks, &Some(String::new())),
+            Result::Err(ArgError::InvalidTaskId(..))
+        ));
+
+        // Numbers are always positions, even if a name contains the number
+        let tasks = vec![Task::new(String::from("Task 2"), fixed_today()), Task::new(String::from("Task 1"), fixed_today())];
+        assert_eq!(parse_task_id(&tasks, &Some(String::from("1"))), Ok(0));
+    }
+
+    #[test]
+    fn test_create_task_duplicate() {
+        let mut tasks: Vec<Task> = vec![];
+        let mut next_id = 1;
+        create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), vec![String::from("Test")].into_iter()).unwrap();
+
+        let args_iter_duplicate: IntoIter<String> = vec![String::from(" test ")].into_iter();
+        let args_iter_force: IntoIter<String> = vec![String::from("--force"), String::from("test")].into_iter();
+
+        assert_eq!(
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_duplicate),
+            Err(ArgError::DuplicateTask(1))
+        );
+        assert_eq!(tasks.len(), 1);
+
+        assert!(matches!(
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_force),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks.len(), 2);
+    }
+
+    #[test]
+    fn test_create_task_json() {
+        let today = fixed_today();
+        let mut tasks: Vec<Task> = vec![];
+        let mut next_id = 1;
+
+        let json = r#"{"name": "test", "due_date": "+3d", "color": "red", "note": "note", "subtasks": ["step 1"], "estimate": 30, "url": "https://example.com"}"#;
+        let args_iter_correct: IntoIter<String> = vec![String::from("--json"), String::from(json)].into_iter();
+        let args_iter_malformed: IntoIter<String> = vec![String::from("--json"), String::from(r#"{"name": "test""#)].into_iter();
+        let args_iter_unknown_field: IntoIter<String> = vec![String::from("--json"), String::from(r#"{"name": "test", "id": 5}"#)].into_iter();
+        let args_iter_invalid_color: IntoIter<String> = vec![String::from("--json"), String::from(r#"{"name": "test", "color": "orange"}"#)].into_iter();
+        let args_iter_too_many: IntoIter<String> = vec![String::from("--json"), String::from(json), String::from("more")].into_iter();
+
+        assert!(matches!(create_task(&mut tasks, &mut next_id, today, &Config::default(), args_iter_malformed), Err(ArgError::InvalidJson(..))));
+        assert!(matches!(create_task(&mut tasks, &mut next_id, today, &Config::default(), args_iter_unknown_field), Err(ArgError::InvalidJson(..))));
+        assert_eq!(
+            create_task(&mut tasks, &mut next_id, today, &Config::default(), args_iter_invalid_color),
+            Err(ArgError::InvalidColor(String::from("orange")))
+        );
+        assert!(matches!(create_task(&mut tasks, &mut next_id, today, &Config::default(), args_iter_too_many), Err(ArgError::TooManyArgs(..))));
+
+        // Subtasks are checked like task names
+        for (subtasks, invalid_text) in [(r#"[""]"#, false), (r#"["  "]"#, false), (r#"["step\n2"]"#, true), (r#"["\u001b[31mstep"]"#, true)] {
+            let json = format!(r#"{{"name": "test", "subtasks": {}}}"#, subtasks);
+            match create_task(&mut tasks, &mut next_id, today, &Config::default(), vec![String::from("--json"), json].into_iter()) {
+                Err(ArgError::InvalidSubtaskText) => assert!(invalid_text),
+                Err(ArgError::ArgMissing(..)) => assert!(!invalid_text),
+                other => panic!("unexpected result for {}: {:?}", subtasks, other),
+            }
+        }
+        assert_eq!(validate_subtask_text(String::from("  step\t1 ")), Ok(String::from("step 1")));
+        assert_eq!(validate_subtask_text(String::from("step\n2")), Err(ArgError::InvalidSubtaskText));
+        assert!(matches!(validate_subtask_text(String::from(" ")), Err(ArgError::ArgMissing(..))));
+        assert!(tasks.is_empty());
+        assert_eq!(next_id, 1);
+
+        create_task(&mut tasks, &mut next_id, today, &Config::default(), args_iter_correct).unwrap();
+        let task = &tasks[0];
+        assert_eq!(task.id, 1);
+        assert_eq!(task.name, "test");
+        assert_eq!(task.creation_date, today);
+        assert_eq!(task.due_date, NaiveDate::from_ymd_opt(2025, 7, 20));
+        assert_eq!(task.color, Some(Color::Red));
+        assert_eq!(task.note, "note");
+        assert_eq!(task.subtasks, vec![Subtask { text: String::from("step 1"), done: false }]);
+        assert_eq!(task.estimate, Some(30));
+        assert_eq!(task.url, Some(String::from("https://example.com")));
+
+        // The same duplicate check applies as for a name
+        let args_iter_duplicate: IntoIter<String> = vec![String::from("--json"), String::from(r#"{"name": "Test"}"#)].into_iter();
+        assert_eq!(create_task(&mut tasks, &mut next_id, today, &Config::default(), args_iter_duplicate), Err(ArgError::DuplicateTask(1)));
+    }
+
+    #[test]
+    fn test_stats_chart() {
+        let today = fixed_today();
+        let mut tasks: Vec<Task> = vec![];
+        for days_ago in [0, 0, 0, 0, 2, 3, 20] {
+            let mut task = Task::new(format!("Task {}", days_ago), fixed_today());
+            task.creation_date = today - Days::new(days_ago);
+            tasks.push(task);
+        }
+
+        // The oldest day comes first; days without tasks and tasks outside the period are handled
+        assert_eq!(created_per_day(&tasks, today, 5), vec![0, 1, 1, 0, 4]);
+        assert_eq!(created_per_day(&[], today, 3), vec![0, 0, 0]);
+        assert_eq!(sparkline(&[0, 1, 1, 0, 4]), "·▂▂·█");
+        assert_eq!(sparkline(&[0, 0]), "··");
+        assert_eq!(sparkline(&[1, 2, 3, 4, 5, 6, 7, 8]), "▁▂▃▄▅▆▇█");
+
+        assert_eq!(parse_chart_days(String::from("30")), Ok(30));
+        assert!(matches!(parse_chart_days(String::from("0")), Result::Err(ArgError::InvalidDayCount(..))));
+        assert!(matches!(parse_chart_days(String::from("abc")), Result::Err(ArgError::InvalidDayCount(..))));
+        assert!(show_stats(&tasks, today, &Config::default(), vec![String::from("--chart"), String::from("7")].into_iter()).is_ok());
+        assert!(show_stats(&tasks, today, &Config::default(), vec![String::from("--chart"), String::from("1000")].into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_trim_whitespace() {
+        let mut tasks: Vec<Task> = vec![];
+        let mut next_id = 1;
+
+        let args_iter_padded: IntoIter<String> = vec![String::from("  padded"), String::from("name  ")].into_iter();
+        let args_iter_blank: IntoIter<String> = vec![String::from("   "), String::from("\t")].into_iter();
+        let args_iter_rename_blank: IntoIter<String> = vec![String::from("1"), String::from("  ")].into_iter();
+        let args_iter_rename_padded: IntoIter<String> = vec![String::from("1"), String::from(" new  name ")].into_iter();
+        let args_iter_note: IntoIter<String> = vec![String::from("1"), String::from("\n  first  line \n\n second\n ")].into_iter();
+
+        create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_padded).unwrap();
+        assert_eq!(tasks[0].name, "padded name");
+        assert_eq!(create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_blank), Err(ArgError::ArgMissing(String::from("task name"))));
+
+        assert_eq!(rename_task(&mut tasks, fixed_today(), &Config::default(), args_iter_rename_blank), Err(ArgError::ArgMissing(String::from("new name"))));
+        rename_task(&mut tasks, fixed_today(), &Config::default(), args_iter_rename_padded).unwrap();
+        assert_eq!(tasks[0].name, "new  name");
+
+        // Spacing within lines and blank lines between them are kept
+        add_note(&mut tasks, fixed_today(), &Config::default(), args_iter_note).unwrap();
+        assert_eq!(tasks[0].note, "first  line\n\nsecond");
+        assert_eq!(trim_note(" \n \t "), "");
+    }
+
+    #[test]
+    fn test_control_characters() {
+        let mut tasks: Vec<Task> = vec![];
+        let mut next_id = 1;
+
+        let args_iter_newline: IntoIter<String> = vec![String::from("test\nname")].into_iter();
+        let args_iter_escape: IntoIter<String> = vec![String::from("\u{1b}[31mtest")].into_iter();
+        let args_iter_tab: IntoIter<String> = vec![String::from("test\tname")].into_iter();
+        let args_iter_rename: IntoIter<String> = vec![String::from("1"), String::from("\u{7}test")].into_iter();
+        let args_iter_note: IntoIter<String> = vec![String::from("1"), String::from("\u{1b}[1;31mred\u{1b}[0m\nline\u{1b}c")].into_iter();
+
+        assert!(matches!(
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_newline),
+            Result::Err(ArgError::InvalidTaskName)
+        ));
+        assert!(matches!(
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_escape),
+            Result::Err(ArgError::InvalidTaskName)
+        ));
+
+        // Tabs are replaced by spaces
+        create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_tab).unwrap();
+        assert_eq!(tasks[0].name, String::from("test name"));
+
+        assert!(matches!(
+            rename_task(&mut tasks, fixed_today(), &Config::default(), args_iter_rename),
+            Result::Err(ArgError::InvalidTaskName)
+        ));
+
+        // Notes keep their newlines, but lose escape sequences
+        add_note(&mut tasks, fixed_today(), &Config::default(), args_iter_note).unwrap();
+        assert_eq!(tasks[0].note, String::from("red\nline"));
+    }
+
+    #[test]
+    fn test_create_task_after() {
+        let mut tasks: Vec<Task> = vec![];
+        let mut next_id = 1;
+        for name in ["test 1", "test 2"] {
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), vec![String::from(name)].into_iter()).unwrap();
+        }
+
+        let args_iter_incorrect: IntoIter<String> = vec![String::from("--after"), String::from("3"), String::from("test")].into_iter();
+        let args_iter_missing: IntoIter<String> = vec![String::from("--after")].into_iter();
+        let args_iter_correct: IntoIter<String> = vec![String::from("--after"), String::from("1"), String::from("test 3")].into_iter();
+        let args_iter_correct_2: IntoIter<String> = vec![String::from("--after"), String::from("3"), String::from("--created"), String::from("2024-03-01"), String::from("test 4")].into_iter();
+
+        assert!(matches!(
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_incorrect),
+            Result::Err(ArgError::TaskNotFound)
+        ));
+
+        assert!(matches!(
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_missing),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+
+        assert!(matches!(
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_correct),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[1].name, String::from("test 3"));
+
+        assert!(matches!(
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), args_iter_correct_2),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[3].name, String::from("test 4"));
+        assert_eq!(tasks[3].creation_date, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(tasks.len(), 4);
+    }
+
+    #[test]
+    fn test_delete_task() {
+        let mut tasks = vec![Task::new(String::from("test"), fixed_today())];
+
+        let args_iter_incorrect: IntoIter<String> = vec![String::from("2")].into_iter();
+        let args_iter_invalid: IntoIter<String> = vec![String::from("foobar")].into_iter();
+        let args_iter_too_many: IntoIter<String> = vec![String::from("1"), String::from("more")].into_iter();
+        let args_iter_missing: IntoIter<String> = vec![].into_iter();
+        let args_iter_correct: IntoIter<String> = vec![String::from("1")].into_iter();
+        let list = ListScope { filename: PathBuf::from("tasks.json"), explicit: false, list_names: |_| vec![], load_tasks: |_| Ok(vec![]) };
+
+        assert!(matches!(
+            delete_task(&mut tasks, &list, fixed_today(), args_iter_incorrect),
+            Result::Err(ArgError::TaskNotFound)
+        ));
+
+        assert!(matches!(
+            delete_task(&mut tasks, &list, fixed_today(), args_iter_invalid),
+            Result::Err(ArgError::TaskNotFound)
+        ));
+
+        assert!(matches!(
+            delete_task(&mut tasks, &list, fixed_today(), args_iter_too_many),
+            Result::Err(ArgError::TooManyArgs(..))
+        ));
+
+        assert!(matches!(
+            delete_task(&mut tasks, &list, fixed_today(), args_iter_missing),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+
+        assert!(matches!(
+            delete_task(&mut tasks, &list, fixed_today(), args_iter_correct),
+            Result::Ok(..)
+        ));
+        assert!(tasks.is_empty());
+    }
+
+    #[test]
+    fn test_due_positions() {
+        let today = fixed_today();
+
+        let mut tasks = vec![
+            Task::new(String::from("test 1"), fixed_today()),
+            Task::new(String::from("test 2"), fixed_today()),
+            Task::new(String::from("test 3"), fixed_today()),
+            Task::new(String::from("test 4"), fixed_today()),
+            Task::new(String::from("test 5"), fixed_today()),
+        ];
+        tasks[0].due_date = NaiveDate::from_ymd_opt(2025, 7, 17);
+        tasks[1].due_date = NaiveDate::from_ymd_opt(2025, 7, 18);
+        tasks[2].due_date = NaiveDate::from_ymd_opt(2025, 7, 14);
+        tasks[3].due_date = NaiveDate::from_ymd_opt(2025, 7, 14);
+        tasks[3].completed_date = NaiveDate::from_ymd_opt(2025, 7, 15);
+        tasks[4].due_date = NaiveDate::from_ymd_opt(2025, 7, 16);
+        tasks[4].start_date = NaiveDate::from_ymd_opt(2025, 7, 20);
+
+        assert_eq!(due_positions(&tasks, today), vec![0, 2]);
+        assert_eq!(due_message(&tasks[0], today), String::from("Due today"));
+        assert_eq!(due_message(&tasks[2], today), String::from("Overdue by 3 days"));
+    }
+
+    #[test]
+    fn test_dedupe_tasks() {
+        let mut tasks = vec![
+            Task::new(String::from("test"), fixed_today()),
+            Task::new(String::from("test"), fixed_today()),
+            Task::new(String::from("test"), fixed_today()),
+            Task::new(String::from("test"), fixed_today()),
+            Task::new(String::from("test"), fixed_today()),
+            Task::new(String::from("other"), fixed_today()),
+        ];
+        tasks[1].creation_date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        tasks[1].id = 2;
+        tasks[3].note = String::from("different note");
+        tasks[4].color = Some(Color::Red);
+        assert_eq!(duplicate_positions(&tasks), vec![(0, 1), (2, 1)]);
+
+        let args_iter_too_many: IntoIter<String> = vec![String::from("more")].into_iter();
+        let args_iter_correct: IntoIter<String> = vec![].into_iter();
+
+        assert!(matches!(
+            dedupe_tasks(&mut tasks, fixed_today(), args_iter_too_many),
+            Result::Err(ArgError::TooManyArgs(..))
+        ));
+        assert_eq!(tasks.len(), 6);
+
+        assert!(matches!(
+            dedupe_tasks(&mut tasks, fixed_today(), args_iter_correct),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks.len(), 4);
+        assert_eq!(tasks[0].id, 2);
+        assert!(duplicate_positions(&tasks).is_empty());
+
+        // A task blocked by a removed duplicate is blocked by the kept task instead
+        let mut tasks = vec![Task::new(String::from("test"), fixed_today()), Task::new(String::from("test"), fixed_today()), Task::new(String::from("other"), fixed_today())];
+        for (i, task) in tasks.iter_mut().enumerate() {
+            task.id = i as u64 + 1;
+        }
+        tasks[2].blocked_by = Some(2);
+        dedupe_tasks(&mut tasks, fixed_today(), std::iter::empty()).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[1].blocked_by, Some(1));
+
+        // Unless the kept task is blocked by it, which would be a cycle
+        let mut tasks = vec![Task::new(String::from("test"), fixed_today()), Task::new(String::from("other"), fixed_today()), Task::new(String::from("test"), fixed_today())];
+        for (i, task) in tasks.iter_mut().enumerate() {
+            task.id = i as u64 + 1;
+        }
+        tasks[0].blocked_by = Some(2);
+        tasks[1].blocked_by = Some(3);
+        dedupe_tasks(&mut tasks, fixed_today(), std::iter::empty()).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].blocked_by, Some(2));
+        assert_eq!(tasks[1].blocked_by, None);
+    }
+
+    #[test]
+    fn test_delete_task_filter() {
+        let mut tasks = vec![
+            Task::new(String::from("test 1"), fixed_today()),
+            Task::new(String::from("test 2"), fixed_today()),
+            Task::new(String::from("test 3"), fixed_today()),
+        ];
+        tasks[0].color = Some(Color::Green);
+        tasks[0].completed_date = Some(fixed_today());
+        tasks[1].color = Some(Color::Green);
+        tasks[2].completed_date = Some(fixed_today());
+
+        let args_iter_unknown: IntoIter<String> = vec![String::from("--done"), String::from("--foo")].into_iter();
+        let args_iter_missing: IntoIter<String> = vec![String::from("--color")].into_iter();
+        let args_iter_done_green: IntoIter<String> = vec![String::from("--done"), String::from("--color"), String::from("green"), String::from("--yes")].into_iter();
+        let args_iter_done: IntoIter<String> = vec![String::from("--yes"), String::from("--done")].into_iter();
+        let args_iter_no_filter: IntoIter<String> = vec![String::from("--yes")].into_iter();
+        let list = ListScope { filename: PathBuf::from("tasks.json"), explicit: true, list_names: |_| vec![], load_tasks: |_| Ok(vec![]) };
+
+        assert!(matches!(
+            delete_task(&mut tasks, &list, fixed_today(), args_iter_unknown),
+            Result::Err(ArgError::UnknownOption(..))
+        ));
+
+        // '--yes' alone does not remove all tasks
+        assert_eq!(
+            delete_task(&mut tasks, &list, fixed_today(), args_iter_no_filter),
+            Err(ArgError::ArgMissing(String::from("filter option")))
+        );
+        assert!(matches!(
+            delete_task(&mut tasks, &list, fixed_today(), args_iter_missing),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+        assert_eq!(tasks.len(), 3);
+
+        assert!(matches!(
+            delete_task(&mut tasks, &list, fixed_today(), args_iter_done_green),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks.iter().map(|task| task.name.as_str()).collect::<Vec<&str>>(), vec!["test 2", "test 3"]);
+
+        assert!(matches!(
+            delete_task(&mut tasks, &list, fixed_today(), args_iter_done),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].name, "test 2");
+
+        // Without matching tasks, nothing is removed and no confirmation is asked
+        assert_eq!(delete_task(&mut tasks, &list, fixed_today(), vec![String::from("--overdue")].into_iter()), Ok(()));
+        assert_eq!(tasks.len(), 1);
+    }
+
+    #[test]
+    fn test_rename_task() {
+        let mut tasks = vec![Task::new(String::from("test"), fixed_today())];
+        let taskname_new = String::from("test renamed");
+
+        let args_iter_incorrect: IntoIter<String> = vec![String::from("2")].into_iter();
+        let args_iter_invalid: IntoIter<String> = vec![String::from("foobar")].into_iter();
+        let args_iter_missing: IntoIter<String> = vec![].into_iter();
+        let args_iter_missing_name: IntoIter<String> = vec![String::from("1")].into_iter();
+        let args_iter_blank_name: IntoIter<String> = vec![String::from("1"), String::from(" ")].into_iter();
+        let args_iter_correct: IntoIter<String> = vec![String::from("1"), taskname_new.clone()].into_iter();
+
+        assert!(matches!(
+            rename_task(&mut tasks, fixed_today(), &Config::default(), args_iter_incorrect),
+            Result::Err(ArgError::TaskNotFound)
+        ));
+
+        assert!(matches!(
+            rename_task(&mut tasks, fixed_today(), &Config::default(), args_iter_invalid),
+            Result::Err(ArgError::TaskNotFound)
+        ));
+
+        assert!(matches!(
+            rename_task(&mut tasks, fixed_today(), &Config::default(), args_iter_missing),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+
+        assert!(matches!(
+            rename_task(&mut tasks, fixed_today(), &Config::default(), args_iter_missing_name),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+
+        assert!(matches!(
+            rename_task(&mut tasks, fixed_today(), &Config::default(), args_iter_blank_name),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+        assert_eq!(tasks[0].name, String::from("test"));
+
+        assert!(matches!(
+            rename_task(&mut tasks, fixed_today(), &Config::default(), args_iter_correct),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].name, taskname_new);
+    }
+
+    #[test]
+    fn test_complete_task() {
+        let mut tasks = vec![Task::new(String::from("1"), fixed_today()), Task::new(String::from("2"), fixed_today()), Task::new(String::from("3"), fixed_today())];
+
+        let args_iter_correct: IntoIter<String> = vec![String::from("2")].into_iter();
+        let args_iter_again: IntoIter<String> = vec![String::from("2")].into_iter();
+        let args_iter_incorrect: IntoIter<String> = vec![String::from("4")].into_iter();
+
+        assert!(matches!(
+            complete_task(&mut tasks, fixed_today(), &Config::default(), args_iter_incorrect),
+            Result::Err(ArgError::TaskNotFound)
+        ));
+        assert!(matches!(
+            complete_task(&mut tasks, fixed_today(), &Config::default(), args_iter_correct),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[1].completed_date, Some(fixed_today()));
+
+        // Completing a task again keeps the original date
+        tasks[1].completed_date = NaiveDate::from_ymd_opt(2025, 6, 9);
+        complete_task(&mut tasks, fixed_today(), &Config::default(), args_iter_again).unwrap();
+        assert_eq!(tasks[1].completed_date, NaiveDate::from_ymd_opt(2025, 6, 9));
+    }
+
+    #[test]
+    fn test_completed_positions() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 12).unwrap(); // Thursday
+        let mut tasks = vec![Task::new(String::from("1"), fixed_today()), Task::new(String::from("2"), fixed_today()), Task::new(String::from("3"), fixed_today()), Task::new(String::from("4"), fixed_today())];
+        tasks[0].completed_date = Some(today);
+        tasks[1].completed_date = NaiveDate::from_ymd_opt(2025, 6, 9); // Monday
+        tasks[2].completed_date = NaiveDate::from_ymd_opt(2025, 6, 8); // Sunday of the previous week
+
+        assert_eq!(completed_positions(&tasks, today, false), vec![0]);
+        assert_eq!(completed_positions(&tasks, today, true), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_last_modified() {
+        // New and changed tasks get the date the command runs on
+        let mut tasks = vec![Task::new(String::from("test"), fixed_today())];
+        assert_eq!(tasks[0].last_modified(), fixed_today());
+
+        // Tasks saved by older versions have no modification date
+        tasks[0].creation_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        tasks[0].modified_date = None;
+        assert_eq!(tasks[0].last_modified(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+
+        let args_iter_rename: IntoIter<String> = vec![String::from("1"), String::from("renamed")].into_iter();
+        rename_task(&mut tasks, fixed_today(), &Config::default(), args_iter_rename).unwrap();
+        assert_eq!(tasks[0].last_modified(), fixed_today());
+
+        tasks[0].modified_date = None;
+        let args_iter_due: IntoIter<String> = vec![String::from("1"), String::from("clear")].into_iter();
+        add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_due).unwrap();
+        assert_eq!(tasks[0].modified_date, Some(fixed_today()));
+    }
+
+    #[test]
+    fn test_list_columns() {
+        let mut warnings = vec![];
+        assert_eq!(list_columns(None, &mut warnings), Column::DEFAULT.to_vec());
+        assert_eq!(list_columns(Some(String::new()), &mut warnings), Column::DEFAULT.to_vec());
+        assert_eq!(
+            list_columns(Some(String::from("name, ID,due")), &mut warnings),
+            vec![Column::Name, Column::Id, Column::Due]
+        );
+        assert!(warnings.is_empty());
+
+        // Unknown and repeated columns are ignored
+        assert_eq!(
+            list_columns(Some(String::from("id,priority,name,id")), &mut warnings),
+            vec![Column::Id, Column::Name]
+        );
+        assert_eq!(warnings, vec![String::from("Ignoring unknown column in TODO_LIST_COLUMNS: priority")]);
+        assert_eq!(list_columns(Some(String::from("priority")), &mut warnings), Column::DEFAULT.to_vec());
+    }
+
+    #[test]
+    fn test_column_cells() {
+        let mut tasks = vec![Task::new(String::from("test"), fixed_today()), Task::new(String::from("pinned"), fixed_today())];
+        tasks[0].id = 12;
+        tasks[0].due_date = NaiveDate::from_ymd_opt(2099, 1, 1);
+        tasks[1].pinned = true;
+        tasks[1].blocked_by = Some(12);
+
+        // Cells have the same width as the headers
+        for column in Column::DEFAULT {
+            let header_width = strip_colors(&column.header(10, &Config::default())).width();
+            for i in 0..tasks.len() {
+                assert_eq!(strip_colors(&column.cell(&tasks, i, 10, false, fixed_today(), &Config::default())).width(), header_width, "{:?}", column);
+            }
+        }
+
+        assert_eq!(Column::Id.cell(&tasks, 1, 10, false, fixed_today(), &Config::default()), "  2*");
+        assert_eq!(Column::Stable.cell(&tasks, 0, 10, false, fixed_today(), &Config::default()), "     12");
+        assert_eq!(Column::Blocked.cell(&tasks, 1, 10, false, fixed_today(), &Config::default()), "1         ");
+    }
+
+    #[test]
+    fn test_note_preview_cells() {
+        let mut tasks = vec![Task::new(String::from("test"), fixed_today()); 4];
+        tasks[1].note = String::from("Short note");
+        tasks[2].note = String::from("First line\nSecond line");
+        tasks[3].note = String::from("A note that does not fit in the column");
+
+        let header_width = Column::NotePreview.header(10, &Config::default()).width();
+        for i in 0..tasks.len() {
+            assert_eq!(Column::NotePreview.cell(&tasks, i, 10, false, fixed_today(), &Config::default()).width(), header_width);
+        }
+        assert_eq!(Column::NotePreview.cell(&tasks, 0, 10, false, fixed_today(), &Config::default()).trim_end(), "");
+        assert_eq!(Column::NotePreview.cell(&tasks, 1, 10, false, fixed_today(), &Config::default()).trim_end(), "Short note");
+        assert_eq!(Column::NotePreview.cell(&tasks, 2, 10, false, fixed_today(), &Config::default()).trim_end(), "First line...");
+        assert_eq!(Column::NotePreview.cell(&tasks, 3, 10, false, fixed_today(), &Config::default()).trim_end(), "A note that does...");
+
+        tasks[0].note_file = true;
+        assert_eq!(Column::NotePreview.cell(&tasks, 0, 10, false, fixed_today(), &Config::default()).trim_end(), "(in file)");
+    }
+
+    #[test]
+    fn test_days_string() {
+        assert_eq!(days_string(0), "0 days");
+        assert_eq!(days_string(1), "1 day");
+        assert_eq!(days_string(12), "12 days");
+        assert_eq!(days_string(-1), "-1 day");
+    }
+
+    #[test]
+    fn test_fit_to_width() {
+        assert_eq!(fit_to_width("short", 10), "short     ");
+        assert_eq!(fit_to_width("exactly 10", 10), "exactl... ");
+        assert_eq!(fit_to_width("a longer task name", 10), "a long... ");
+
+        // CJK characters take two columns each
+        assert_eq!(fit_to_width("日本語", 10), "日本語    ");
+        assert_eq!(fit_to_width("日本語のタスク", 10), "日本語... ");
+        assert_eq!(fit_to_width("日本語のタスク", 9), "日本...  ");
+        assert_eq!(fit_to_width("日本語のタスク", 10).width(), 10);
+
+        // Emoji sequences are kept whole
+        let family = "👨‍👩‍👧";
+        assert_eq!(fit_to_width(&family.repeat(5), 10), format!("{family}{family}{family}... "));
+        assert_eq!(fit_to_width("café", 6), "café  ");
+    }
+
+    #[test]
+    fn test_plain_task_row() {
+        let mut task = Task::new(String::from("A task with a name longer than the width"), fixed_today());
+        task.color = Some(Color::Red);
+        task.note = String::from("note");
+        task.due_date = NaiveDate::from_ymd_opt(2000, 1, 1);
+
+        assert_eq!(plain_task_row(&task, 0, 10, false, fixed_today(), &Config::default()), "  1  A task with a name longer than the width  2000-01-01");
+
+        task.name = String::from("Short");
+        task.due_date = None;
+        assert_eq!(plain_task_row(&task, 11, 8, false, fixed_today(), &Config::default()), " 12  Short");
+    }
+
+    #[test]
+    fn test_porcelain_task_row() {
+        let mut task = Task::new(String::from("A task\twith a tab"), fixed_today());
+        task.id = 7;
+        task.creation_date = NaiveDate::from_ymd_opt(1999, 12, 31).unwrap();
+        task.color = Some(Color::Red);
+        task.due_date = NaiveDate::from_ymd_opt(2000, 1, 1);
+
+        assert_eq!(porcelain_task_row(&task, 0), "1\t7\t1999-12-31\t2000-01-01\tred\tA task with a tab");
+
+        task.color = None;
+        task.due_date = None;
+        assert_eq!(porcelain_task_row(&task, 11), "12\t7\t1999-12-31\t\t\tA task with a tab");
+    }
+
+    #[test]
+    fn test_text_changes() {
+        assert_eq!(text_changes("Buy milk", "Buy milk"), vec![Change::Same(String::from("Buy milk"))]);
+        assert_eq!(text_changes("Call Bob", "Call Rob"), vec![
+            Change::Same(String::from("Call ")),
+            Change::Removed(String::from("B")),
+            Change::Added(String::from("R")),
+            Change::Same(String::from("ob")),
+        ]);
+        assert_eq!(text_changes("Write report", "Write the report"), vec![
+            Change::Same(String::from("Write ")),
+            Change::Added(String::from("the ")),
+            Change::Same(String::from("report")),
+        ]);
+        assert_eq!(text_changes("", "new"), vec![Change::Added(String::from("new"))]);
+        assert_eq!(text_changes("old", ""), vec![Change::Removed(String::from("old"))]);
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        assert_eq!(parse_fields("id, Name,due,id"), Ok(vec![Column::Id, Column::Name, Column::Due]));
+        assert_eq!(parse_fields("id,priority"), Err(ArgError::InvalidField(String::from("priority"))));
+        assert!(matches!(parse_fields(","), Result::Err(ArgError::ArgMissing(..))));
+        assert!(ArgError::InvalidField(String::from("priority")).to_string().contains("color, id, stable, name"));
+    }
+
+    #[test]
+    fn test_field_values() {
+        let mut tasks = vec![Task::new(String::from("Blocker"), fixed_today()), Task::new(String::from("A task\twith a tab"), fixed_today())];
+        tasks[0].id = 3;
+        tasks[1].id = 7;
+        tasks[1].creation_date = NaiveDate::from_ymd_opt(1999, 12, 31).unwrap();
+        tasks[1].color = Some(Color::Red);
+        tasks[1].note = String::from("First line\nSecond line");
+        tasks[1].blocked_by = Some(3);
+        tasks[1].subtasks = vec![Subtask { text: String::from("subtask"), done: true }];
+
+        let fields = Column::DEFAULT.to_vec();
+        assert_eq!(
+            field_values(&tasks, 1, &fields, false, fixed_today(), &Config::default(), true).join("\t"),
+            "red\t2\t7\tA task with a tab\t1999-12-31\t\t1/1\tFirst line\t1"
+        );
+        assert_eq!(
+            plain_fields_row(&tasks, 0, &[Column::Id, Column::Name, Column::Stable], 10, false, fixed_today(), &Config::default()),
+            "  1  Blocker     3"
+        );
+    }
+
+    #[test]
+    fn test_parse_age() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 15).unwrap();
+
+        assert_eq!(parse_age(Some(String::from("2025-01-01")), today), Ok(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()));
+        assert_eq!(parse_age(Some(String::from("30d")), today), Ok(NaiveDate::from_ymd_opt(2025, 5, 16).unwrap()));
+        assert_eq!(parse_age(Some(String::from("2w")), today), Ok(NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()));
+        assert_eq!(parse_age(Some(String::from("0d")), today), Ok(today));
+        assert!(matches!(parse_age(None, today), Result::Err(ArgError::ArgMissing(..))));
+        assert!(matches!(parse_age(Some(String::from("-3d")), today), Result::Err(ArgError::InvalidAge(..))));
+        assert!(matches!(parse_age(Some(String::from("3m")), today), Result::Err(ArgError::InvalidAge(..))));
+        assert!(matches!(parse_age(Some(String::from("99999999999w")), today), Result::Err(ArgError::DateOutOfRange)));
+    }
+
+    #[test]
+    fn test_number_of_tasks() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 15).unwrap();
+        let mut tasks = vec![Task::new(String::from("1"), fixed_today()), Task::new(String::from("2"), fixed_today()), Task::new(String::from("3"), fixed_today())];
+        tasks[0].due_date = NaiveDate::from_ymd_opt(2025, 6, 14);
+        tasks[0].color = Some(Color::Red);
+        tasks[1].due_date = NaiveDate::from_ymd_opt(2025, 6, 15);
+        tasks[2].color = Some(Color::Red);
+
+        let args_iter_all: IntoIter<String> = vec![].into_iter();
+        let args_iter_overdue: IntoIter<String> = vec![String::from("--overdue")].into_iter();
+        let args_iter_color: IntoIter<String> = vec![String::from("--color"), String::from("red")].into_iter();
+        let args_iter_both: IntoIter<String> = vec![String::from("--color"), String::from("red"), String::from("--overdue")].into_iter();
+        let args_iter_invalid: IntoIter<String> = vec![String::from("--color"), String::from("pink")].into_iter();
+        let args_iter_missing: IntoIter<String> = vec![String::from("--color")].into_iter();
+        let args_iter_unknown: IntoIter<String> = vec![String::from("--tag")].into_iter();
+
+        assert_eq!(number_of_tasks(&tasks, today, args_iter_all), Ok(3));
+        assert_eq!(number_of_tasks(&tasks, today, args_iter_overdue), Ok(1));
+        assert_eq!(number_of_tasks(&tasks, today, args_iter_color), Ok(2));
+        assert_eq!(number_of_tasks(&tasks, today, args_iter_both), Ok(1));
+        assert!(matches!(
+            number_of_tasks(&tasks, today, args_iter_invalid),
+            Result::Err(ArgError::InvalidColor(..))
+        ));
+        assert!(matches!(
+            number_of_tasks(&tasks, today, args_iter_missing),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+        assert!(matches!(
+            number_of_tasks(&tasks, today, args_iter_unknown),
+            Result::Err(ArgError::UnknownOption(..))
+        ));
+    }
+
+    #[test]
+    fn test_snooze_task() {
+        let mut tasks = vec![Task::new(String::from("test"), fixed_today())];
+        tasks[0].due_date = NaiveDate::from_ymd_opt(2025, 1, 1);
+
+        let args_iter_missing: IntoIter<String> = vec![String::from("1")].into_iter();
+        let args_iter_zero: IntoIter<String> = vec![String::from("1"), String::from("0")].into_iter();
+        let args_iter_text: IntoIter<String> = vec![String::from("1"), String::from("3d")].into_iter();
+        let args_iter_large: IntoIter<String> = vec![String::from("1"), String::from("999999999")].into_iter();
+        let args_iter_correct: IntoIter<String> = vec![String::from("1"), String::from("3")].into_iter();
+
+        assert!(matches!(
+            snooze_task(&mut tasks, fixed_today(), &Config::default(), args_iter_missing),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+        assert!(matches!(
+            snooze_task(&mut tasks, fixed_today(), &Config::default(), args_iter_zero),
+            Result::Err(ArgError::InvalidDayCount(..))
+        ));
+        assert!(matches!(
+            snooze_task(&mut tasks, fixed_today(), &Config::default(), args_iter_text),
+            Result::Err(ArgError::InvalidDayCount(..))
+        ));
+        assert!(matches!(
+            snooze_task(&mut tasks, fixed_today(), &Config::default(), args_iter_large),
+            Result::Err(ArgError::DateOutOfRange)
+        ));
+        assert!(tasks[0].start_date.is_none());
+
+        assert!(matches!(
+            snooze_task(&mut tasks, fixed_today(), &Config::default(), args_iter_correct),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].start_date, fixed_today().checked_add_days(Days::new(3)));
+        assert_eq!(tasks[0].due_date, NaiveDate::from_ymd_opt(2025, 1, 1));
+    }
+
+    #[test]
+    fn test_estimate_task() {
+        let mut tasks = vec![Task::new(String::from("test"), fixed_today())];
+
+        let args_iter_missing: IntoIter<String> = vec![String::from("1")].into_iter();
+        let args_iter_zero: IntoIter<String> = vec![String::from("1"), String::from("0")].into_iter();
+        let args_iter_negative: IntoIter<String> = vec![String::from("1"), String::from("-5")].into_iter();
+        let args_iter_text: IntoIter<String> = vec![String::from("1"), String::from("1h")].into_iter();
+        let args_iter_correct: IntoIter<String> = vec![String::from("1"), String::from("90")].into_iter();
+        let args_iter_clear: IntoIter<String> = vec![String::from("1"), String::from("clear")].into_iter();
+
+        assert!(matches!(
+            estimate_task(&mut tasks, fixed_today(), args_iter_missing),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+        assert!(matches!(
+            estimate_task(&mut tasks, fixed_today(), args_iter_zero),
+            Result::Err(ArgError::InvalidEstimate(..))
+        ));
+        assert!(matches!(
+            estimate_task(&mut tasks, fixed_today(), args_iter_negative),
+            Result::Err(ArgError::InvalidEstimate(..))
+        ));
+        assert!(matches!(
+            estimate_task(&mut tasks, fixed_today(), args_iter_text),
+            Result::Err(ArgError::InvalidEstimate(..))
+        ));
+        assert!(tasks[0].estimate.is_none());
+
+        assert!(matches!(
+            estimate_task(&mut tasks, fixed_today(), args_iter_correct),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].estimate, Some(90));
+
+        assert!(matches!(
+            estimate_task(&mut tasks, fixed_today(), args_iter_clear),
+            Result::Ok(..)
+        ));
+        assert!(tasks[0].estimate.is_none());
+    }
+
+    #[test]
+    fn test_set_url() {
+        let mut tasks = vec![Task::new(String::from("test"), fixed_today())];
+
+        let args_iter_missing: IntoIter<String> = vec![String::from("1")].into_iter();
+        let args_iter_invalid: IntoIter<String> = vec![String::from("1"), String::from("example.com")].into_iter();
+        let args_iter_correct: IntoIter<String> = vec![String::from("1"), String::from("https://example.com/a?b=c")].into_iter();
+        let args_iter_clear: IntoIter<String> = vec![String::from("1"), String::from("clear")].into_iter();
+
+        assert!(matches!(
+            set_url(&mut tasks, fixed_today(), args_iter_missing),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+        assert!(matches!(
+            set_url(&mut tasks, fixed_today(), args_iter_invalid),
+            Result::Err(ArgError::InvalidUrl(..))
+        ));
+        assert!(tasks[0].url.is_none());
+
+        assert!(matches!(
+            set_url(&mut tasks, fixed_today(), args_iter_correct),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].url, Some(String::from("https://example.com/a?b=c")));
+
+        assert!(matches!(
+            set_url(&mut tasks, fixed_today(), args_iter_clear),
+            Result::Ok(..)
+        ));
+        assert!(tasks[0].url.is_none());
+
+        let args_iter_open: IntoIter<String> = vec![String::from("1")].into_iter();
+        assert_eq!(open_url(&tasks, args_iter_open), Err(ArgError::UrlNotSet));
+    }
+
+    #[test]
+    fn test_parse_url_value() {
+        assert_eq!(parse_url_value("http://localhost:8080/path"), Ok(Some(String::from("http://localhost:8080/path"))));
+        assert_eq!(parse_url_value("https://example.com"), Ok(Some(String::from("https://example.com"))));
+        assert!(parse_url_value("https://").is_err());
+        assert!(parse_url_value("https:///path").is_err());
+        assert!(parse_url_value("https://:80").is_err());
+        assert!(parse_url_value("https://exa mple.com").is_err());
+        assert!(parse_url_value("ftp://example.com").is_err());
+        assert!(parse_url_value("javascript:alert(1)").is_err());
+
+        // Characters that are special to a shell are only accepted where URLs allow them
+        assert!(parse_url_value("https://example.com/a?b=1&c=%20").is_ok());
+        assert!(parse_url_value("https://x.com/|calc").is_err());
+        assert!(parse_url_value("https://x.com/\"&calc").is_err());
+        assert!(parse_url_value("https://x.com/^&calc").is_err());
+        assert!(parse_url_value("https://x.com/<a>").is_err());
+        assert!(parse_url_value("https://x.com/%PATH%").is_err());
+        assert!(parse_url_value("https://x.com/100%").is_err());
+    }
+
+    #[test]
+    fn test_opener_command() {
+        assert_eq!(opener_command("linux"), (String::from("xdg-open"), vec![]));
+        assert_eq!(opener_command("macos"), (String::from("open"), vec![]));
+        assert_eq!(opener_command("windows"), (String::from("rundll32"), vec![String::from("url.dll,FileProtocolHandler")]));
+    }
+
+    #[test]
+    fn test_minutes_string_from() {
+        assert_eq!(minutes_string_from(45), String::from("45m"));
+        assert_eq!(minutes_string_from(120), String::from("2h"));
+        assert_eq!(minutes_string_from(90), String::from("1h 30m"));
+    }
+
+    #[test]
+    fn test_set_attributes() {
+        let mut tasks = vec![Task::new(String::from("test"), fixed_today())];
+        tasks[0].color = Some(Color::Blue);
+
+        let args_iter_none: IntoIter<String> = vec![String::from("1")].into_iter();
+        let args_iter_unknown: IntoIter<String> = vec![String::from("1"), String::from("--priority"), String::from("high")].into_iter();
+        let args_iter_missing: IntoIter<String> = vec![String::from("1"), String::from("--color")].into_iter();
+        let args_iter_one_invalid: IntoIter<String> = vec![
+            String::from("1"), String::from("--color"), String::from("red"), String::from("--due"), String::from("02-30"),
+        ].into_iter();
+        let args_iter_two_invalid: IntoIter<String> = vec![
+            String::from("1"), String::from("--color"), String::from("pink"), String::from("--estimate"), String::from("0"),
+        ].into_iter();
+        let args_iter_correct: IntoIter<String> = vec![
+            String::from("1"), String::from("--due"), String::from("2025-07-10"), String::from("--estimate"), String::from("30"),
+        ].into_iter();
+        let args_iter_clear: IntoIter<String> = vec![
+            String::from("1"), String::from("--color"), String::from("clear"), String::from("--due"), String::from("+2d"),
+        ].into_iter();
+
+        assert!(matches!(
+            set_attributes(&mut tasks, fixed_today(), &Config::default(), args_iter_none),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+        assert!(matches!(
+            set_attributes(&mut tasks, fixed_today(), &Config::default(), args_iter_unknown),
+            Result::Err(ArgError::UnknownOption(..))
+        ));
+        assert!(matches!(
+            set_attributes(&mut tasks, fixed_today(), &Config::default(), args_iter_missing),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+        assert_eq!(set_attributes(&mut tasks, fixed_today(), &Config::default(), args_iter_one_invalid), Err(ArgError::IncorrectDateFormat));
+        assert_eq!(
+            set_attributes(&mut tasks, fixed_today(), &Config::default(), args_iter_two_invalid),
+            Err(ArgError::MultipleErrors(vec![ArgError::InvalidColor(String::from("pink")), ArgError::InvalidEstimate(String::from("0"))]))
+        );
+        assert_eq!(tasks[0].color, Some(Color::Blue));
+        assert!(tasks[0].due_date.is_none());
+
+        assert!(matches!(
+            set_attributes(&mut tasks, fixed_today(), &Config::default(), args_iter_correct),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].color, Some(Color::Blue));
+        assert_eq!(tasks[0].due_date, NaiveDate::from_ymd_opt(2025, 7, 10));
+        assert_eq!(tasks[0].estimate, Some(30));
+
+        assert!(matches!(
+            set_attributes(&mut tasks, fixed_today(), &Config::default(), args_iter_clear),
+            Result::Ok(..)
+        ));
+        assert!(tasks[0].color.is_none());
+        assert_eq!(tasks[0].due_date, NaiveDate::from_ymd_opt(2025, 7, 12));
+        assert_eq!(tasks[0].estimate, Some(30));
+    }
+
+    #[test]
+    fn test_block_task() {
+        let mut tasks: Vec<Task> = vec![];
+        let mut next_id = 1;
+        for name in ["test 1", "test 2", "test 3"] {
+            create_task(&mut tasks, &mut next_id, fixed_today(), &Config::default(), vec![String::from(name)].into_iter()).unwrap();
+        }
+
+        let args_iter_incorrect: IntoIter<String> = vec![String::from("1"), String::from("4")].into_iter();
+        let args_iter_missing: IntoIter<String> = vec![String::from("1")].into_iter();
+        let args_iter_self: IntoIter<String> = vec![String::from("1"), String::from("1")].into_iter();
+        let args_iter_correct_1: IntoIter<String> = vec![String::from("1"), String::from("2")].into_iter();
+        let args_iter_correct_2: IntoIter<String> = vec![String::from("2"), String::from("3")].into_iter();
+        let args_iter_circular: IntoIter<String> = vec![String::from("3"), String::from("1")].into_iter();
+        let args_iter_clear: IntoIter<String> = vec![String::from("1"), String::from("clear")].into_iter();
+
+        assert!(matches!(
+            block_task(&mut tasks, fixed_today(), args_iter_incorrect),
+            Result::Err(ArgError::TaskNotFound)
+        ));
+
+        assert!(matches!(
+            block_task(&mut tasks, fixed_today(), args_iter_missing),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+
+        assert!(matches!(
+            block_task(&mut tasks, fixed_today(), args_iter_self),
+            Result::Err(ArgError::CircularBlock)
+        ));
+
+        assert!(matches!(
+            block_task(&mut tasks, fixed_today(), args_iter_correct_1),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].blocked_by, Some(tasks[1].id));
+        assert_eq!(blocker_position(&tasks, &tasks[0]), Some(1));
+
+        assert!(matches!(
+            block_task(&mut tasks, fixed_today(), args_iter_correct_2),
+            Result::Ok(..)
+        ));
+
+        assert!(matches!(
+            block_task(&mut tasks, fixed_today(), args_iter_circular),
+            Result::Err(ArgError::CircularBlock)
+        ));
+
+        // Blocking task survives reordering
+        tasks.swap(1, 2);
+        assert_eq!(blocker_position(&tasks, &tasks[0]), Some(2));
+
+        // Task is no longer blocked once the blocking task is removed
+        tasks.remove(2);
+        assert_eq!(blocker_position(&tasks, &tasks[0]), None);
+
+        assert!(matches!(
+            block_task(&mut tasks, fixed_today(), args_iter_clear),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].blocked_by, None);
+    }
+
+    #[test]
+    fn test_assign_missing_ids() {
+        let mut tasks = vec![Task::new(String::from("test 1"), fixed_today()), Task::new(String::from("test 2"), fixed_today())];
+        tasks[1].id = 5;
+
+        let mut next_id = 0;
+        assign_missing_ids(&mut tasks, &mut next_id);
+        assert_eq!(tasks[0].id, 6);
+        assert_eq!(tasks[1].id, 5);
+        assert_eq!(next_id, 7);
+
+        // A higher counter is kept, so ids of removed tasks are not reused
+        tasks.push(Task::new(String::from("test 3"), fixed_today()));
+        next_id = 10;
+        assign_missing_ids(&mut tasks, &mut next_id);
+        assert_eq!(tasks[2].id, 10);
+        assert_eq!(next_id, 11);
+    }
+
+    #[test]
+    fn test_parse_task_id() {
+        let mut tasks = vec![Task::new(String::from("test 1"), fixed_today()), Task::new(String::from("test 2"), fixed_today())];
+        tasks[0].id = 3;
+        tasks[1].id = 8;
+
+        assert_eq!(parse_task_id(&tasks, &Some(String::from("2"))), Ok(1));
+        assert_eq!(parse_task_id(&tasks, &Some(String::from("id:3"))), Ok(0));
+        assert_eq!(parse_task_id(&tasks, &Some(String::from("id:8"))), Ok(1));
+        assert_eq!(parse_task_id(&tasks, &Some(String::from("id:2"))), Err(ArgError::TaskNotFound));
+        assert_eq!(parse_task_id(&tasks, &Some(String::from("last"))), Ok(1));
+        assert_eq!(parse_task_id(&tasks, &Some(String::from("$"))), Ok(1));
+        assert_eq!(parse_task_id(&tasks, &Some(String::from("first"))), Ok(0));
+        assert_eq!(parse_task_id(&tasks, &Some(String::from("^"))), Ok(0));
+        assert_eq!(parse_task_id(&[], &Some(String::from("last"))), Err(ArgError::TaskNotFound));
+        assert_eq!(parse_task_id(&[], &Some(String::from("first"))), Err(ArgError::TaskNotFound));
+        assert_eq!(parse_task_id(&[], &Some(String::from("^"))), Err(ArgError::TaskNotFound));
+
+        // 'last' and 'first' match a task name that has them as a word, '$' and '^' never do
+        let mut named = vec![Task::new(String::from("Last call"), fixed_today()), Task::new(String::from("test"), fixed_today()), Task::new(String::from("other"), fixed_today())];
+        named[0].id = 1;
+        assert_eq!(parse_task_id(&named, &Some(String::from("last"))), Ok(0));
+        assert_eq!(parse_task_id(&named, &Some(String::from("$"))), Ok(2));
+        named.push(Task::new(String::from("First things first"), fixed_today()));
+        assert_eq!(parse_task_id(&named, &Some(String::from("first"))), Ok(3));
+        assert_eq!(parse_task_id(&named, &Some(String::from("^"))), Ok(0));
+
+        // Part of a word is not enough to hide the keywords
+        let named = vec![Task::new(String::from("Blast radius"), fixed_today()), Task::new(String::from("First-aid kit"), fixed_today()), Task::new(String::from("other"), fixed_today())];
+        assert_eq!(parse_task_id(&named, &Some(String::from("last"))), Ok(2));
+        assert_eq!(parse_task_id(&named, &Some(String::from("first"))), Ok(0));
+        let named = vec![Task::new(String::from("test"), fixed_today()), Task::new(String::from("Call (last)"), fixed_today()), Task::new(String::from("other"), fixed_today())];
+        assert_eq!(parse_task_id(&named, &Some(String::from("last"))), Ok(1));
+
+        // IDs are plain decimal digits, without sign, spaces or leading zeros
+        for task_id in ["007", "01", "+3", "-1", " 2", "2 ", "id:+3", "id:03", "99999999999999999999999"] {
+            assert_eq!(parse_task_id(&tasks, &Some(String::from(task_id))), Err(ArgError::InvalidTaskId(String::from(task_id))));
+        }
+        assert_eq!(parse_task_id(&tasks, &Some(String::from("0"))), Err(ArgError::TaskNotFound));
+        assert_eq!(parse_task_id(&tasks, &Some(String::from("3"))), Err(ArgError::TaskNotFound));
+        assert!(matches!(
+            parse_task_id(&tasks, &Some(String::from("id:foo"))),
+            Result::Err(ArgError::InvalidTaskId(..))
+        ));
+        assert!(matches!(
+            parse_task_id(&tasks, &None),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+    }
+
+    #[test]
+    fn test_edit_subtasks() {
+        let mut tasks = vec![Task::new(String::from("test"), fixed_today())];
+
+        let args_iter_missing_1: IntoIter<String> = vec![].into_iter();
+        let args_iter_missing_2: IntoIter<String> = vec![String::from("add"), String::from("1")].into_iter();
+        let args_iter_missing_3: IntoIter<String> = vec![String::from("done"), String::from("1")].into_iter();
+        let args_iter_invalid_1: IntoIter<String> = vec![String::from("foo"), String::from("1")].into_iter();
+        let args_iter_invalid_2: IntoIter<String> = vec![String::from("done"), String::from("1"), String::from("foo")].into_iter();
+        let args_iter_incorrect_1: IntoIter<String> = vec![String::from("add"), String::from("2"), String::from("step")].into_iter();
+        let args_iter_incorrect_2: IntoIter<String> = vec![String::from("done"), String::from("1"), String::from("3")].into_iter();
+        let args_iter_add_1: IntoIter<String> = vec![String::from("add"), String::from("1"), String::from("step"), String::from("1")].into_iter();
+        let args_iter_add_2: IntoIter<String> = vec![String::from("add"), String::from("1"), String::from("step 2")].into_iter();
+        let args_iter_done: IntoIter<String> = vec![String::from("done"), String::from("1"), String::from("2")].into_iter();
+        let args_iter_rm: IntoIter<String> = vec![String::from("rm"), String::from("1"), String::from("1")].into_iter();
+
+        assert!(matches!(
+            edit_subtasks(&mut tasks, fixed_today(), args_iter_missing_1),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+
+        assert!(matches!(
+            edit_subtasks(&mut tasks, fixed_today(), args_iter_missing_2),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+
+        assert!(matches!(
+            edit_subtasks(&mut tasks, fixed_today(), args_iter_missing_3),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+
+        assert!(matches!(
+            edit_subtasks(&mut tasks, fixed_today(), args_iter_invalid_1),
+            Result::Err(ArgError::InvalidSubtaskAction(..))
+        ));
+
+        assert!(matches!(
+            edit_subtasks(&mut tasks, fixed_today(), args_iter_invalid_2),
+            Result::Err(ArgError::InvalidSubtaskNumber(..))
+        ));
+
+        assert!(matches!(
+            edit_subtasks(&mut tasks, fixed_today(), args_iter_incorrect_1),
+            Result::Err(ArgError::TaskNotFound)
+        ));
+
+        assert!(matches!(
+            edit_subtasks(&mut tasks, fixed_today(), args_iter_add_1),
+            Result::Ok(..)
+        ));
+        assert!(matches!(
+            edit_subtasks(&mut tasks, fixed_today(), args_iter_add_2),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].subtasks.len(), 2);
+        assert_eq!(tasks[0].subtasks[0].text, String::from("step 1"));
+
+        assert!(matches!(
+            edit_subtasks(&mut tasks, fixed_today(), args_iter_incorrect_2),
+            Result::Err(ArgError::SubtaskNotFound)
+        ));
+
+        assert!(matches!(
+            edit_subtasks(&mut tasks, fixed_today(), args_iter_done),
+            Result::Ok(..)
+        ));
+        assert!(tasks[0].subtasks[1].done);
+
+        assert!(matches!(
+            edit_subtasks(&mut tasks, fixed_today(), args_iter_rm),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].subtasks, vec![Subtask { text: String::from("step 2"), done: true }]);
+    }
+
+    #[test]
+    fn test_show_help() {
+        let args_iter_full: IntoIter<String> = vec![].into_iter();
+        let args_iter_topic: IntoIter<String> = vec![String::from("add")].into_iter();
+        let args_iter_unknown: IntoIter<String> = vec![String::from("foo")].into_iter();
+        let args_iter_too_many: IntoIter<String> = vec![String::from("add"), String::from("more")].into_iter();
+
+        assert!(matches!(show_help(args_iter_full), Result::Ok(..)));
+        assert!(matches!(show_help(args_iter_topic), Result::Ok(..)));
+        assert!(matches!(show_help(args_iter_unknown), Result::Err(ArgError::UnknownHelpTopic(_, None, _))));
+        assert!(matches!(
+            show_help(vec![String::from("lst")].into_iter()),
+            Result::Err(ArgError::UnknownHelpTopic(_, Some(closest), _)) if closest == "list"
+        ));
+        assert!(matches!(show_help(args_iter_too_many), Result::Err(ArgError::TooManyArgs(..))));
+    }
+
+    // Minimal JSON Schema validator, supporting only the keywords used in schema.json
+    fn matches_schema(root: &serde_json::Value, schema: &serde_json::Value, value: &serde_json::Value) -> bool {
+        use serde_json::Value;
+
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.strip_prefix("#/$defs/").unwrap();
+            return matches_schema(root, &root["$defs"][name], value);
+        }
+        if let Some(options) = schema["anyOf"].as_array() {
+            return options.iter().any(|option| matches_schema(root, option, value));
+        }
+        if let Some(options) = schema["enum"].as_array() {
+            return options.contains(value);
+        }
+
+        let types: Vec<&str> = match &schema["type"] {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().map(|t| t.as_str().unwrap()).collect(),
+            _ => vec![],
+        };
+        let type_matches = types.iter().any(|&t| match t {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "integer" => value.is_u64() || value.is_i64(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            _ => false,
+        });
+        if !type_matches {
+            return false;
+        }
+
+        if schema["format"] == "date" && NaiveDate::parse_from_str(value.as_str().unwrap(), "%Y-%m-%d").is_err() {
+            return false;
+        }
+        if let Some(minimum) = schema["minimum"].as_u64() {
+            if value.is_number() && value.as_u64().is_none_or(|v| v < minimum) {
+                return false;
+            }
+        }
+        if let Some(items) = value.as_array() {
+            return items.iter().all(|item| matches_schema(root, &schema["items"], item));
+        }
+        if let Some(object) = value.as_object() {
+            let properties = schema["properties"].as_object().unwrap();
+            let required = schema["required"].as_array().cloned().unwrap_or_default();
+            let additional = schema["additionalProperties"] != false;
+            return required.iter().all(|key| object.contains_key(key.as_str().unwrap()))
+                && object.iter().all(|(key, field)| match properties.get(key) {
+                    Some(property) => matches_schema(root, property, field),
+                    None => additional,
+                });
+        }
+        true
+    }
+
+    #[test]
+    fn test_ical_calendar() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 30, 0).unwrap();
+        let mut tasks = vec![Task::new(String::from("No due date"), fixed_today()), Task::new(String::from("Call Bob, Alice; Eve"), fixed_today())];
+        tasks[1].id = 7;
+        tasks[1].due_date = NaiveDate::from_ymd_opt(2025, 6, 30);
+        tasks[1].note = String::from("Line 1\nLine 2");
+
+        let calendar = ical_calendar(&tasks, Path::new("."), now);
+        let lines: Vec<&str> = calendar.split("\r\n").collect();
+        assert_eq!(lines, vec![
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            &format!("PRODID:-//todo-rs//ToDo {}//EN", env!("CARGO_PKG_VERSION")),
+            "CALSCALE:GREGORIAN",
+            "BEGIN:VEVENT",
+            "UID:task-7@todo-rs",
+            "DTSTAMP:20250601T123000Z",
+            "DTSTART;VALUE=DATE:20250630",
+            "DTEND;VALUE=DATE:20250701",
+            "SUMMARY:Call Bob\\, Alice\\; Eve",
+            "DESCRIPTION:Line 1\\nLine 2",
+            "END:VEVENT",
+            "END:VCALENDAR",
+            "",
+        ]);
+    }
+
+    #[test]
+    fn test_ical_round_trip() {
+        // Undo the escaping of a value, as a calendar application reading it would
+        fn unescape(value: &str) -> String {
+            let mut text = String::new();
+            let mut chars = value.chars();
+            while let Some(c) = chars.next() {
+                if c != '\\' {
+                    text.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('n') => text.push('\n'),
+                    Some(other) => text.push(other),
+                    None => (),
+                }
+            }
+            text
+        }
+
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 30, 0).unwrap();
+        let mut tasks = vec![Task::new(String::from("Tricky \"note\", with; delimiters"), fixed_today())];
+        tasks[0].due_date = NaiveDate::from_ymd_opt(2025, 6, 30);
+        tasks[0].note = format!("He said \"hi, there\"; left\\\r\nWindows line\rOld Mac line\n{}", "ä, ".repeat(30));
+
+        let calendar = ical_calendar(&tasks, Path::new("."), now).replace("\r\n ", "");
+        let lines: Vec<&str> = calendar.split("\r\n").collect();
+        let summary = lines.iter().find_map(|line| line.strip_prefix("SUMMARY:")).unwrap();
+        let description = lines.iter().find_map(|line| line.strip_prefix("DESCRIPTION:")).unwrap();
+        assert_eq!(unescape(summary), tasks[0].name);
+        assert_eq!(unescape(description), tasks[0].note.trim_end().replace("\r\n", "\n").replace('\r', "\n"));
+    }
+
     #[test]
-    fn test_create_task() {
+    fn test_ical_fold() {
+        let short = "SUMMARY:short";
+        assert_eq!(ical_fold(short), short);
 
-        let args_iter_correct: IntoIter<String> = vec![String::from("test")].into_iter();
-        let args_iter_correct2: IntoIter<String> = vec![String::from("test"), String::from("2")].into_iter();
-        let args_iter_missing: IntoIter<String> = vec![].into_iter();
+        let long = format!("SUMMARY:{}", "ä".repeat(40));
+        let folded = ical_fold(&long);
+        let folded_lines: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(folded_lines.len(), 2);
+        assert!(folded_lines.iter().all(|line| line.len() <= 75));
+        assert!(folded_lines[1].starts_with(' '));
+        assert_eq!(folded.replace("\r\n ", ""), long);
+    }
+
+    #[test]
+    fn test_parse_undo_steps() {
+        assert_eq!(parse_undo_steps(vec![String::from("3")].into_iter()), Ok(3));
+        assert!(matches!(parse_undo_steps(std::iter::empty()), Result::Err(ArgError::ArgMissing(..))));
+        assert!(matches!(parse_undo_steps(vec![String::from("0")].into_iter()), Result::Err(ArgError::InvalidUndoSteps(..))));
+        assert!(matches!(parse_undo_steps(vec![String::from("-1")].into_iter()), Result::Err(ArgError::InvalidUndoSteps(..))));
+        assert!(matches!(
+            parse_undo_steps(vec![String::from("1"), String::from("2")].into_iter()),
+            Result::Err(ArgError::TooManyArgs(..))
+        ));
+    }
+
+    #[test]
+    fn test_schema() {
+        let schema: serde_json::Value = serde_json::from_str(include_str!("schema.json")).unwrap();
+
+        let mut task_full = Task::new(String::from("full"), fixed_today());
+        task_full.id = 2;
+        task_full.due_date = NaiveDate::from_ymd_opt(2025, 1, 31);
+        task_full.color = Some(Color::Purple);
+        task_full.note = String::from("note");
+        task_full.blocked_by = Some(1);
+        task_full.subtasks = vec![Subtask { text: String::from("subtask"), done: true }];
+        task_full.note_file = true;
+        task_full.start_date = NaiveDate::from_ymd_opt(2025, 2, 1);
+        task_full.pinned = true;
+        task_full.modified_date = None;
+        task_full.completed_date = NaiveDate::from_ymd_opt(2025, 2, 2);
+        task_full.estimate = Some(90);
+        task_full.url = Some(String::from("https://example.com/ticket/12"));
+        let mut task_empty = Task::new(String::from("empty"), fixed_today());
+        task_empty.id = 1;
+
+        let meta = Meta { schema_version: 1, title: Some(String::from("title")), notes: vec![String::from("note")], ..Default::default() };
+        let data = serde_json::json!({ "meta": meta, "next_id": 3, "tasks": [task_empty, task_full] });
+        assert!(matches_schema(&schema, &schema, &data));
+
+        // Check that the validator actually rejects invalid data
+        let mut data_invalid = data.clone();
+        data_invalid["tasks"][0]["color"] = serde_json::json!("Pink");
+        assert!(!matches_schema(&schema, &schema, &data_invalid));
+        let mut data_invalid = data.clone();
+        data_invalid["tasks"][0]["unknown"] = serde_json::json!(true);
+        assert!(!matches_schema(&schema, &schema, &data_invalid));
+        let mut data_invalid = data.clone();
+        data_invalid["meta"]["notes"] = serde_json::json!("note");
+        assert!(!matches_schema(&schema, &schema, &data_invalid));
+        let mut data_invalid = data;
+        data_invalid["tasks"][1]["due_date"] = serde_json::json!("31-01-2025");
+        assert!(!matches_schema(&schema, &schema, &data_invalid));
+    }
+
+    #[test]
+    fn test_help_sections() {
+        let sections = help_sections(include_str!("help.txt"));
+
+        let add_sections: Vec<&(&str, String)> = sections.iter().filter(|(command, _)| *command == "add").collect();
+        assert_eq!(add_sections.len(), 4);
+        assert!(add_sections[0].1.starts_with("  add [task name]"));
+        assert!(add_sections[0].1.contains("'--force' is required"));
+
+        let color_sections: Vec<&(&str, String)> = sections.iter().filter(|(command, _)| *command == "color").collect();
+        assert_eq!(color_sections.len(), 3);
+        assert!(color_sections[0].1.contains("purple"));
+        assert!(color_sections[1].1.contains("--overdue"));
+        assert!(!color_sections[1].1.contains("rename"));
+    }
+
+    #[test]
+    fn test_help_matches_commands() {
+        let help_str = include_str!("help.txt");
+        let sections = help_sections(help_str);
+
+        // Every command has help, and there is no help for commands that do not exist
+        for command in COMMANDS {
+            assert!(sections.iter().any(|(name, _)| *name == command.name), "No help for {}", command.name);
+        }
+        for (name, _) in &sections {
+            assert!(COMMANDS.iter().any(|command| command.name == *name), "Unknown command in help: {}", name);
+        }
+
+        // The generated help contains all of help.txt
+        assert_eq!(full_help(help_str, &sections), help_str.trim_end());
+    }
+
+    #[test]
+    fn test_purge_tasks() {
+        let mut tasks = vec![Task::new(String::from("test 1"), fixed_today()), Task::new(String::from("test 2"), fixed_today())];
+        let list = ListScope { filename: PathBuf::from("tasks.json"), explicit: true, list_names: |_| vec![], load_tasks: |_| Ok(vec![]) };
+
+        let args_iter_unknown: IntoIter<String> = vec![String::from("--foo")].into_iter();
+        let args_iter_correct: IntoIter<String> = vec![String::from("--yes")].into_iter();
+
+        assert!(matches!(
+            purge_tasks(&mut tasks, &list, args_iter_unknown),
+            Result::Err(ArgError::UnknownOption(..))
+        ));
+        assert_eq!(tasks.len(), 2);
 
-        let mut tasks: Vec<Task> = vec![];
         assert!(matches!(
-            create_task(&mut tasks, args_iter_correct),
+            purge_tasks(&mut tasks, &list, args_iter_correct),
             Result::Ok(..)
         ));
+        assert!(tasks.is_empty());
+
+        // An empty list is left alone, without asking for confirmation
+        assert_eq!(purge_tasks(&mut tasks, &list, std::iter::empty()), Ok(()));
+    }
+
+    #[test]
+    fn test_list_scope() {
+        let filename = PathBuf::from("tasks.json");
+        let one_list = ListScope { filename: filename.clone(), explicit: false, list_names: |_| vec![String::from("tasks")], load_tasks: |_| Ok(vec![]) };
+        assert_eq!(one_list.name(), "tasks");
+        assert_eq!(one_list.check_explicit("purge"), Ok(()));
+
+        // With a second list, removing many tasks requires the list to be named, also in a batch
+        let two_lists = |_: &Path| vec![String::from("tasks"), String::from("work")];
+        let list = ListScope { filename: filename.clone(), explicit: false, list_names: two_lists, load_tasks: |_| Ok(vec![]) };
+        let explicit_list = ListScope { filename, explicit: true, list_names: two_lists, load_tasks: |_| Ok(vec![]) };
+        let mut tasks = vec![Task::new(String::from("test"), fixed_today())];
+        assert_eq!(purge_tasks(&mut tasks, &list, vec![String::from("--yes")].into_iter()), Err(ArgError::ListNotExplicit(String::from("purge"), 2)));
+        assert_eq!(delete_task(&mut tasks, &list, fixed_today(), vec![String::from("--yes"), String::from("--done")].into_iter()), Err(ArgError::ListNotExplicit(String::from("remove"), 2)));
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(delete_task(&mut tasks, &list, fixed_today(), vec![String::from("1")].into_iter()), Ok(()));
+        assert_eq!(explicit_list.check_explicit("purge"), Ok(()));
+    }
+
+    #[test]
+    fn test_confirm_undo() {
+        let args_iter_unknown: IntoIter<String> = vec![String::from("--foo")].into_iter();
+        let args_iter_yes: IntoIter<String> = vec![String::from("--yes")].into_iter();
+
+        assert_eq!(confirm_undo(1, false, args_iter_unknown), Err(ArgError::UnknownOption(String::from("--foo"))));
+
+        // Single changes and dry runs do not ask for confirmation
+        assert_eq!(confirm_undo(1, false, std::iter::empty()), Ok(()));
+        assert_eq!(confirm_undo(5, true, std::iter::empty()), Ok(()));
+        assert_eq!(confirm_undo(5, false, args_iter_yes), Ok(()));
+    }
+
+    #[test]
+    fn test_rename_task_append() {
+        let mut tasks = vec![Task::new(String::from("test"), fixed_today())];
+
+        let args_iter_missing: IntoIter<String> = vec![String::from("1"), String::from("--append")].into_iter();
+        let args_iter_correct: IntoIter<String> = vec![String::from("1"), String::from("--append"), String::from("(blocked)")].into_iter();
+
+        assert!(matches!(
+            rename_task(&mut tasks, fixed_today(), &Config::default(), args_iter_missing),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
         assert_eq!(tasks[0].name, String::from("test"));
 
-        tasks = vec![];
         assert!(matches!(
-            create_task(&mut tasks,args_iter_correct2),
+            rename_task(&mut tasks, fixed_today(), &Config::default(), args_iter_correct),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].name, String::from("test (blocked)"));
+    }
+
+    #[test]
+    fn test_add_duedate() {
+        let mut tasks = vec![Task::new("test".to_owned(), fixed_today())];
+        let due_date = String::from("2025-12-12");
+
+        let args_iter_incorrect_1: IntoIter<String> = vec![String::from("2"), due_date.clone()].into_iter();
+        let args_iter_incorrect_2: IntoIter<String> = vec![String::from("1"), String::from("20251212")].into_iter();
+        let args_iter_invalid: IntoIter<String> = vec![String::from("foobar"), due_date.clone()].into_iter();
+        let args_iter_too_many: IntoIter<String> = vec![String::from("1"), due_date.clone(), String::from("more")].into_iter();
+        let args_iter_missing_1: IntoIter<String> = vec![].into_iter();
+        let args_iter_missing_2: IntoIter<String> = vec![String::from("1")].into_iter();
+        let args_iter_correct: IntoIter<String> = vec![String::from("1"), due_date.clone()].into_iter();
+        let args_iter_correct_clear: IntoIter<String> = vec![String::from("1"), String::from("clear")].into_iter();
+
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_incorrect_1),
+            Result::Err(ArgError::TaskNotFound)
+        ));
+
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_incorrect_2),
+            Result::Err(ArgError::IncorrectDateFormat)
+        ));
+
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_invalid),
+            Result::Err(ArgError::TaskNotFound)
+        ));
+
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_too_many),
+            Result::Err(ArgError::TooManyArgs(..))
+        ));
+
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_missing_1),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_missing_2),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_correct),
+            Result::Ok(..)
+        ));
+        assert_eq!(
+            tasks[0].due_date
+                .unwrap()
+                .format("%Y-%m-%d")
+                .to_string(),
+            due_date
+        );
+
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_correct_clear),
+            Result::Ok(..)
+        ));
+        assert_eq!(
+            tasks[0].due_date,
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_due_date() {
+        let today = fixed_today();
+
+        assert_eq!(parse_due_date("2026-01-31", today), Ok(NaiveDate::from_ymd_opt(2026, 1, 31).unwrap()));
+        assert_eq!(parse_due_date("07-14", today), Ok(NaiveDate::from_ymd_opt(2025, 7, 14).unwrap()));
+        assert_eq!(parse_due_date("1-2", today), Ok(NaiveDate::from_ymd_opt(2025, 1, 2).unwrap()));
+        assert_eq!(parse_due_date("14", today), Ok(NaiveDate::from_ymd_opt(2025, 7, 14).unwrap()));
+        assert_eq!(parse_due_date("3", today), Ok(NaiveDate::from_ymd_opt(2025, 7, 3).unwrap()));
+
+        assert_eq!(parse_due_date("02-30", today), Err(ArgError::IncorrectDateFormat));
+        assert_eq!(parse_due_date("13-01", today), Err(ArgError::IncorrectDateFormat));
+        assert_eq!(parse_due_date("32", today), Err(ArgError::IncorrectDateFormat));
+        assert_eq!(parse_due_date("0", today), Err(ArgError::IncorrectDateFormat));
+        assert_eq!(parse_due_date("07-", today), Err(ArgError::IncorrectDateFormat));
+        assert_eq!(parse_due_date("+14", today), Err(ArgError::IncorrectDateFormat));
+        assert_eq!(parse_due_date("20251212", today), Err(ArgError::IncorrectDateFormat));
+    }
+
+    #[test]
+    fn test_end_of_period() {
+        let date = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+        let today = date(7, 17); // Thursday
+
+        assert_eq!(end_of_period("eow", today, Weekday::Sun), Some(date(7, 20)));
+        assert_eq!(end_of_period("eow", today, Weekday::Fri), Some(date(7, 18)));
+        assert_eq!(end_of_period("eow", date(7, 18), Weekday::Fri), Some(date(7, 18)));
+        assert_eq!(end_of_period("eow", date(7, 19), Weekday::Fri), Some(date(7, 25)));
+        assert_eq!(end_of_period("eow", date(7, 20), Weekday::Sun), Some(date(7, 20)));
+
+        assert_eq!(end_of_period("eom", today, Weekday::Sun), Some(date(7, 31)));
+        assert_eq!(end_of_period("eom", date(2, 1), Weekday::Sun), Some(date(2, 28)));
+        assert_eq!(end_of_period("eom", date(12, 31), Weekday::Sun), Some(date(12, 31)));
+        assert_eq!(end_of_period("eod", today, Weekday::Sun), None);
+
+        assert_eq!(parse_due_value("eom", None, today, Weekday::Sun), Ok(Some(date(7, 31))));
+        assert_eq!(parse_due_value("eow", None, today, Weekday::Fri), Ok(Some(date(7, 18))));
+    }
+
+    #[test]
+    fn test_commands_with_fixed_today() {
+        let today = fixed_today();
+        let mut tasks = vec![];
+        let mut next_id = 1;
+
+        let args_iter_future: IntoIter<String> = vec![String::from("--created"), String::from("2025-07-18"), String::from("test")].into_iter();
+        assert_eq!(create_task(&mut tasks, &mut next_id, today, &Config::default(), args_iter_future), Err(ArgError::FutureCreationDate));
+        create_task(&mut tasks, &mut next_id, today, &Config::default(), vec![String::from("test")].into_iter()).unwrap();
+        assert_eq!(tasks[0].creation_date, today);
+
+        add_duedate(&mut tasks, today, &Config::default(), vec![String::from("1"), String::from("+3d")].into_iter()).unwrap();
+        assert_eq!(tasks[0].due_date, NaiveDate::from_ymd_opt(2025, 7, 20));
+        assert_eq!(tasks[0].plain_string_from_due_date(true, today, &Config::default()), "in 3d");
+        assert_eq!(tasks[0].plain_string_from_due_date(true, NaiveDate::from_ymd_opt(2025, 7, 22).unwrap(), &Config::default()), "2d ago");
+
+        snooze_task(&mut tasks, today, &Config::default(), vec![String::from("1"), String::from("2")].into_iter()).unwrap();
+        assert_eq!(tasks[0].start_date, NaiveDate::from_ymd_opt(2025, 7, 19));
+
+        complete_task(&mut tasks, today, &Config::default(), vec![String::from("1")].into_iter()).unwrap();
+        assert_eq!(tasks[0].completed_date, Some(today));
+        assert_eq!(completed_positions(&tasks, today, false), vec![0]);
+    }
+
+    #[test]
+    fn test_add_duedate_relative() {
+        let mut tasks = vec![Task::new("test".to_owned(), fixed_today())];
+
+        let args_iter_no_date: IntoIter<String> = vec![String::from("1"), String::from("+3d")].into_iter();
+        let args_iter_later: IntoIter<String> = vec![String::from("1"), String::from("+7d")].into_iter();
+        let args_iter_earlier: IntoIter<String> = vec![String::from("1"), String::from("-2d")].into_iter();
+        let args_iter_incorrect: IntoIter<String> = vec![String::from("1"), String::from("7d")].into_iter();
+        let args_iter_overflow: IntoIter<String> = vec![String::from("1"), String::from("+99999999999d")].into_iter();
+
+        // Relative to today if there is no due date yet
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_no_date),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].due_date, fixed_today().checked_add_days(Days::new(3)));
+
+        tasks[0].due_date = NaiveDate::from_ymd_opt(2025, 12, 30);
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_later),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].due_date, NaiveDate::from_ymd_opt(2026, 1, 6));
+
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_earlier),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].due_date, NaiveDate::from_ymd_opt(2026, 1, 4));
+
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_incorrect),
+            Result::Err(ArgError::IncorrectDateFormat)
+        ));
+
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_overflow),
+            Result::Err(ArgError::DateOutOfRange)
+        ));
+        assert_eq!(tasks[0].due_date, NaiveDate::from_ymd_opt(2026, 1, 4));
+    }
+
+    #[test]
+    fn test_add_duedate_clear() {
+        let mut tasks = vec![Task::new("test".to_owned(), fixed_today())];
+        tasks[0].due_date = NaiveDate::from_ymd_opt(2025, 12, 12);
+
+        let args_iter_invalid: IntoIter<String> = vec![String::from("foobar"), String::from("clear")].into_iter();
+        let args_iter_too_many: IntoIter<String> = vec![String::from("1"), String::from("clear"), String::from("more")].into_iter();
+        let args_iter_correct: IntoIter<String> = vec![String::from("1"), String::from("clear")].into_iter();
+
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_invalid),
+            Result::Err(ArgError::TaskNotFound)
+        ));
+
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_too_many),
+            Result::Err(ArgError::TooManyArgs(..))
+        ));
+        assert!(tasks[0].due_date.is_some());
+
+        assert!(matches!(
+            add_duedate(&mut tasks, fixed_today(), &Config::default(), args_iter_correct),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].due_date, None);
+    }
+
+    #[test]
+    fn test_move_task() {
+        let mut tasks = vec![Task::new(String::from("1"), fixed_today()), Task::new(String::from("2"), fixed_today())];
+        tasks[0].id = 1;
+        tasks[1].id = 2;
+        tasks[1].blocked_by = Some(1);
+        let mut target_tasks = vec![Task::new(String::from("other"), fixed_today())];
+        let mut target_next_id = 5;
+
+        assert_eq!(move_task(&mut tasks, 1, Path::new("notes"), &mut target_tasks, &mut target_next_id, fixed_today()), 1);
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(target_tasks[1].name, String::from("2"));
+        assert_eq!(target_tasks[1].id, 5);
+        assert_eq!(target_tasks[1].blocked_by, None);
+        assert_eq!(target_next_id, 6);
+
+        // Tasks blocked by the moved task are no longer blocked
+        tasks.push(Task::new(String::from("3"), fixed_today()));
+        tasks[1].id = 3;
+        tasks[1].blocked_by = Some(1);
+        assert_eq!(move_task(&mut tasks, 0, Path::new("notes"), &mut target_tasks, &mut target_next_id, fixed_today()), 2);
+        assert_eq!(tasks[0].name, String::from("3"));
+        assert_eq!(tasks[0].blocked_by, None);
+    }
+
+    #[test]
+    fn test_validate_list_name() {
+        assert!(validate_list_name("work").is_ok());
+        assert!(validate_list_name("side-projects_2").is_ok());
+        for name in ["", "../work", "a/b", "a\\b", "work.json", ".."] {
+            assert!(matches!(
+                validate_list_name(name),
+                Result::Err(ArgError::InvalidListName(..))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_set_task_color_filter() {
+        let mut tasks = vec![Task::new(String::from("1"), fixed_today()), Task::new(String::from("2"), fixed_today()), Task::new(String::from("3"), fixed_today())];
+        tasks[0].due_date = NaiveDate::from_ymd_opt(2000, 1, 1);
+        tasks[1].due_date = NaiveDate::from_ymd_opt(2000, 1, 1);
+        tasks[1].color = Some(Color::Blue);
+
+        let args_iter_overdue: IntoIter<String> = vec![String::from("--overdue"), String::from("red")].into_iter();
+        let args_iter_color: IntoIter<String> = vec![String::from("--color"), String::from("red"), String::from("clear")].into_iter();
+        let args_iter_unknown: IntoIter<String> = vec![String::from("--tag"), String::from("x"), String::from("red")].into_iter();
+        let args_iter_missing: IntoIter<String> = vec![String::from("--overdue")].into_iter();
+
+        assert!(matches!(
+            set_task_color(&mut tasks, fixed_today(), args_iter_unknown),
+            Result::Err(ArgError::UnknownOption(..))
+        ));
+        assert!(matches!(
+            set_task_color(&mut tasks, fixed_today(), args_iter_missing),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+
+        assert!(matches!(
+            set_task_color(&mut tasks, fixed_today(), args_iter_overdue),
+            Result::Ok(..)
+        ));
+        assert_eq!(tasks[0].color, Some(Color::Red));
+        assert_eq!(tasks[1].color, Some(Color::Red));
+        assert_eq!(tasks[2].color, None);
+
+        assert!(matches!(
+            set_task_color(&mut tasks, fixed_today(), args_iter_color),
+            Result::Ok(..)
+        ));
+        assert!(tasks.iter().all(|task| task.color.is_none()));
+
+        // '--all' recolors every task, whatever its color or due date
+        let args_iter_all: IntoIter<String> = vec![String::from("--all"), String::from("green")].into_iter();
+        let args_iter_all_clear: IntoIter<String> = vec![String::from("--all"), String::from("clear")].into_iter();
+        tasks[0].color = Some(Color::Blue);
+        set_task_color(&mut tasks, fixed_today(), args_iter_all).unwrap();
+        assert!(tasks.iter().all(|task| task.color == Some(Color::Green)));
+        set_task_color(&mut tasks, fixed_today(), args_iter_all_clear).unwrap();
+        assert!(tasks.iter().all(|task| task.color.is_none()));
+
+        // '--all' cannot be combined with filter options, in either order
+        let args_iter_all_overdue: IntoIter<String> = vec![String::from("--all"), String::from("--overdue"), String::from("red")].into_iter();
+        let args_iter_color_all: IntoIter<String> = vec![String::from("--color"), String::from("red"), String::from("--all"), String::from("blue")].into_iter();
+        assert_eq!(
+            set_task_color(&mut tasks, fixed_today(), args_iter_all_overdue),
+            Err(ArgError::ConflictingOptions(String::from("--all"), String::from("--overdue")))
+        );
+        assert_eq!(
+            set_task_color(&mut tasks, fixed_today(), args_iter_color_all),
+            Err(ArgError::ConflictingOptions(String::from("--all"), String::from("--color")))
+        );
+        assert!(tasks.iter().all(|task| task.color.is_none()));
+    }
+
+    #[test]
+    fn test_set_task_color() {
+        let mut tasks = vec![Task::new( String::from("test") , fixed_today())];
+
+        assert_eq!(tasks[0].color, None);
+
+        let args_iter_incorrect_1: IntoIter<String> = vec![String::from("2"), String::from("red")].into_iter();
+        let args_iter_incorrect_2: IntoIter<String> = vec![String::from("1"), String::from("orange")].into_iter();
+        let args_iter_invalid: IntoIter<String> = vec![String::from("foobar"), String::from("red")].into_iter();
+        let args_iter_too_many: IntoIter<String> = vec![String::from("1"), String::from("red"), String::from("more")].into_iter();
+        let args_iter_missing_1: IntoIter<String> = vec![].into_iter();
+        let args_iter_missing_2: IntoIter<String> = vec![String::from("1")].into_iter();
+
+        let args_iter_correct_r: IntoIter<String> = vec![String::from("1"), String::from("red")].into_iter();
+        let args_iter_correct_y: IntoIter<String> = vec![String::from("1"), String::from("yellow")].into_iter();
+        let args_iter_correct_g: IntoIter<String> = vec![String::from("1"), String::from("green")].into_iter();
+        let args_iter_correct_b: IntoIter<String> = vec![String::from("1"), String::from("blue")].into_iter();
+        let args_iter_correct_p: IntoIter<String> = vec![String::from("1"), String::from("purple")].into_iter();
+        let args_iter_correct_hex: IntoIter<String> = vec![String::from("1"), String::from("#e01010")].into_iter();
+        let args_iter_correct_n: IntoIter<String> = vec![String::from("1"), String::from("clear")].into_iter();
+
+        // Test all failures
+        assert!(matches!(
+            set_task_color(&mut tasks, fixed_today(), args_iter_incorrect_1),
+            Result::Err(ArgError::TaskNotFound)
+        ));
+
+        assert!(matches!(
+            set_task_color(&mut tasks, fixed_today(), args_iter_incorrect_2),
+            Result::Err(ArgError::InvalidColor(..))
+        ));
+
+        assert!(matches!(
+            set_task_color(&mut tasks, fixed_today(), args_iter_invalid),
+            Result::Err(ArgError::TaskNotFound)
+        ));
+
+        assert!(matches!(
+            set_task_color(&mut tasks, fixed_today(), args_iter_too_many),
+            Result::Err(ArgError::TooManyArgs(..))
+        ));
+
+        assert!(matches!(
+            set_task_color(&mut tasks, fixed_today(), args_iter_missing_1),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+
+        assert!(matches!(
+            set_task_color(&mut tasks, fixed_today(), args_iter_missing_2),
+            Result::Err(ArgError::ArgMissing(..))
+        ));
+
+        // Test correct behavior
+        assert!(matches!(
+            set_task_color(&mut tasks, fixed_today(), args_iter_correct_r),
             Result::Ok(..)
         ));
-        assert_eq!(tasks[0].name, String::from("test 2"));
+        assert_eq!(tasks[0].color, Some(Color::Red));
 
         assert!(matches!(
-            create_task(&mut vec![], args_iter_missing),
-            Result::Err(ArgError::ArgMissing(..))
+            set_task_color(&mut tasks, fixed_today(), args_iter_correct_y),
+            Result::Ok(..)
         ));
-    }
-
-    #[test]
-    fn test_delete_task() {
-        let mut tasks = vec![Task::new(String::from("test"))];
-
-        let args_iter_incorrect: IntoIter<String> = vec![String::from("2")].into_iter();
-        let args_iter_invalid: IntoIter<String> = vec![String::from("foobar")].into_iter();
-        let args_iter_too_many: IntoIter<String> = vec![String::from("1"), String::from("more")].into_iter();
-        let args_iter_missing: IntoIter<String> = vec![].into_iter();
-        let args_iter_correct: IntoIter<String> = vec![String::from("1")].into_iter();
+        assert_eq!(tasks[0].color, Some(Color::Yellow));
 
         assert!(matches!(
-            delete_task(&mut tasks, args_iter_incorrect),
-            Result::Err(ArgError::TaskNotFound)
+            set_task_color(&mut tasks, fixed_today(), args_iter_correct_g),
+            Result::Ok(..)
         ));
+        assert_eq!(tasks[0].color, Some(Color::Green));
 
         assert!(matches!(
-            delete_task(&mut tasks, args_iter_invalid),
-            Result::Err(ArgError::InvalidTaskId(..))
+            set_task_color(&mut tasks, fixed_today(), args_iter_correct_b),
+            Result::Ok(..)
         ));
+        assert_eq!(tasks[0].color, Some(Color::Blue));
 
         assert!(matches!(
-            delete_task(&mut tasks, args_iter_too_many),
-            Result::Err(ArgError::TooManyArgs(..))
+            set_task_color(&mut tasks, fixed_today(), args_iter_correct_p),
+            Result::Ok(..)
         ));
+        assert_eq!(tasks[0].color, Some(Color::Purple));
 
         assert!(matches!(
-            delete_task(&mut tasks, args_iter_missing),
-            Result::Err(ArgError::ArgMissing(..))
+            set_task_color(&mut tasks, fixed_today(), args_iter_correct_hex),
+            Result::Ok(..)
         ));
+        assert_eq!(tasks[0].color, Some(Color::Red));
 
         assert!(matches!(
-            delete_task(&mut tasks, args_iter_correct),
+            set_task_color(&mut tasks, fixed_today(), args_iter_correct_n),
             Result::Ok(..)
         ));
-        assert!(tasks.is_empty());
+        assert_eq!(tasks[0].color, None);
     }
 
     #[test]
-    fn test_rename_task() {
-        let mut tasks = vec![Task::new(String::from("test"))];
-        let taskname_new = String::from("test renamed");
+    fn test_add_note() {
+        let mut tasks = vec![Task::new( String::from("test") , fixed_today())];
 
-        let args_iter_incorrect: IntoIter<String> = vec![String::from("2")].into_iter();
-        let args_iter_invalid: IntoIter<String> = vec![String::from("foobar")].into_iter();
-        let args_iter_missing: IntoIter<String> = vec![].into_iter();
-        let args_iter_correct: IntoIter<String> = vec![String::from("1"), taskname_new.clone()].into_iter();
+        let args_iter_incorrect_1: IntoIter<String> = vec![String::from("2"), String::from("red")].into_iter();
+        let args_iter_new: IntoIter<String> = vec![String::from("1"), String::from("Line1")].into_iter();
+        let args_iter_add: IntoIter<String> = vec![String::from("1"), String::from("Line2")].into_iter();
+        let args_iter_clear: IntoIter<String> = vec![String::from("1"), String::from("clear")].into_iter();
 
         assert!(matches!(
-            rename_task(&mut tasks, args_iter_incorrect),
+            add_note(&mut tasks, fixed_today(), &Config::default(), args_iter_incorrect_1),
             Result::Err(ArgError::TaskNotFound)
         ));
 
         assert!(matches!(
-            rename_task(&mut tasks, args_iter_invalid),
-            Result::Err(ArgError::InvalidTaskId(..))
+            add_note(&mut tasks, fixed_today(), &Config::default(), args_iter_new),
+            Result::Ok(..)
         ));
+        assert_eq!(tasks[0].note, String::from("Line1"));
 
         assert!(matches!(
-            rename_task(&mut tasks, args_iter_missing),
-            Result::Err(ArgError::ArgMissing(..))
+            add_note(&mut tasks, fixed_today(), &Config::default(), args_iter_add),
+            Result::Ok(..)
         ));
+        assert_eq!(tasks[0].note, String::from("Line1\nLine2"));
 
         assert!(matches!(
-            rename_task(&mut tasks, args_iter_correct),
+            add_note(&mut tasks, fixed_today(), &Config::default(), args_iter_clear),
             Result::Ok(..)
         ));
-        assert_eq!(tasks[0].name, taskname_new);
+        assert_eq!(tasks[0].note, String::from(""));
     }
 
     #[test]
-    fn test_add_duedate() {
-        let mut tasks = vec![Task::new("test".to_owned())];
-        let due_date = String::from("2025-12-12");
+    fn test_add_note_too_long() {
+        let mut tasks = vec![Task::new( String::from("test") , fixed_today())];
+        tasks[0].note = "x".repeat(9_000);
 
-        let args_iter_incorrect_1: IntoIter<String> = vec![String::from("2"), due_date.clone()].into_iter();
-        let args_iter_incorrect_2: IntoIter<String> = vec![String::from("1"), String::from("20251212")].into_iter();
-        let args_iter_invalid: IntoIter<String> = vec![String::from("foobar"), due_date.clone()].into_iter();
-        let args_iter_too_many: IntoIter<String> = vec![String::from("1"), due_date.clone(), String::from("more")].into_iter();
-        let args_iter_missing_1: IntoIter<String> = vec![].into_iter();
-        let args_iter_missing_2: IntoIter<String> = vec![String::from("1")].into_iter();
-        let args_iter_correct: IntoIter<String> = vec![String::from("1"), due_date.clone()].into_iter();
-        let args_iter_correct_clear: IntoIter<String> = vec![String::from("1"), String::from("clear")].into_iter();
+        let args_iter_too_long: IntoIter<String> = vec![String::from("1"), "y".repeat(1_000)].into_iter();
+        let args_iter_truncate: IntoIter<String> = vec![String::from("--truncate"), String::from("1"), "y".repeat(1_000)].into_iter();
+
+        assert_eq!(add_note(&mut tasks, fixed_today(), &Config::default(), args_iter_too_long), Err(ArgError::NoteTooLong(10_001, 10_000)));
+        assert_eq!(tasks[0].note.len(), 9_000);
 
         assert!(matches!(
-            add_duedate(&mut tasks, args_iter_incorrect_1),
-            Result::Err(ArgError::TaskNotFound)
+            add_note(&mut tasks, fixed_today(), &Config::default(), args_iter_truncate),
+            Result::Ok(..)
         ));
+        assert_eq!(tasks[0].note.len(), 10_000);
+        assert!(tasks[0].note.ends_with(&"y".repeat(999)));
+
+        let config = Config { max_note_length: 5, ..Config::default() };
+        let args_iter_short_limit: IntoIter<String> = vec![String::from("--replace"), String::from("1"), String::from("abcdef")].into_iter();
+        assert_eq!(add_note(&mut tasks, fixed_today(), &config, args_iter_short_limit), Err(ArgError::NoteTooLong(6, 5)));
+    }
+
+    #[test]
+    fn test_note_lines() {
+        let note = "A first line that is rather long\n  - indented item";
+
+        assert_eq!(note_lines(note, Detail::Normal, 20), vec![
+            String::from("A first line that is"),
+            String::from("rather long"),
+            String::from("- indented item"),
+        ]);
+        assert_eq!(note_lines(note, Detail::Expanded, 20), vec![
+            String::from("A first line that is rather long"),
+            String::from("  - indented item"),
+        ]);
+        assert_eq!(note_lines(note, Detail::Summary, 20), vec![String::from("A first line tha ...")]);
+        assert_eq!(note_lines("Short", Detail::Summary, 20), vec![String::from("Short")]);
+        assert_eq!(note_lines("", Detail::Summary, 20), vec![String::new()]);
+    }
+
+    #[test]
+    fn test_add_note_file() {
+        let mut tasks = vec![Task::new( String::from("test") , fixed_today())];
+        tasks[0].note = String::from("Old note");
+
+        let mut note_filename = env::temp_dir();
+        note_filename.push(format!("todo-rs-test-note-{}.md", std::process::id()));
+        write(&note_filename, "Line 1\r\n\x1b[31mLine 2\x1b[0m\r\n\n").unwrap();
+        let note_filename_string = note_filename.to_str().unwrap().to_string();
+
+        let args_iter_append: IntoIter<String> = vec![String::from("--file"), note_filename_string.clone(), String::from("1")].into_iter();
+        let args_iter_replace: IntoIter<String> = vec![String::from("--replace"), String::from("--file"), note_filename_string.clone(), String::from("1")].into_iter();
+        let args_iter_too_many: IntoIter<String> = vec![String::from("--file"), note_filename_string.clone(), String::from("1"), String::from("text")].into_iter();
+        let args_iter_missing: IntoIter<String> = vec![String::from("--file")].into_iter();
+        let args_iter_unreadable: IntoIter<String> = vec![String::from("--file"), note_filename_string, String::from("1")].into_iter();
 
         assert!(matches!(
-            add_duedate(&mut tasks, args_iter_incorrect_2),
-            Result::Err(ArgError::IncorrectDateFormat)
+            add_note(&mut tasks, fixed_today(), &Config::default(), args_iter_append),
+            Result::Ok(..)
         ));
+        assert_eq!(tasks[0].note, String::from("Old note\nLine 1\nLine 2"));
 
         assert!(matches!(
-            add_duedate(&mut tasks, args_iter_invalid),
-            Result::Err(ArgError::InvalidTaskId(..))
+            add_note(&mut tasks, fixed_today(), &Config::default(), args_iter_replace),
+            Result::Ok(..)
         ));
+        assert_eq!(tasks[0].note, String::from("Line 1\nLine 2"));
 
         assert!(matches!(
-            add_duedate(&mut tasks, args_iter_too_many),
+            add_note(&mut tasks, fixed_today(), &Config::default(), args_iter_too_many),
             Result::Err(ArgError::TooManyArgs(..))
         ));
-
         assert!(matches!(
-            add_duedate(&mut tasks, args_iter_missing_1),
+            add_note(&mut tasks, fixed_today(), &Config::default(), args_iter_missing),
             Result::Err(ArgError::ArgMissing(..))
         ));
 
+        remove_file(&note_filename).unwrap();
         assert!(matches!(
-            add_duedate(&mut tasks, args_iter_missing_2),
-            Result::Err(ArgError::ArgMissing(..))
+            add_note(&mut tasks, fixed_today(), &Config::default(), args_iter_unreadable),
+            Result::Err(ArgError::UnreadableFile(..))
         ));
+    }
+
+    #[test]
+    fn test_relative_date_string() {
+        let today = fixed_today();
+
+        assert_eq!(relative_date_string(NaiveDate::from_ymd_opt(2025, 7, 17).unwrap(), today), String::from("today"));
+        assert_eq!(relative_date_string(NaiveDate::from_ymd_opt(2025, 7, 20).unwrap(), today), String::from("in 3d"));
+        assert_eq!(relative_date_string(NaiveDate::from_ymd_opt(2025, 7, 12).unwrap(), today), String::from("5d ago"));
+    }
+
+    #[test]
+    fn test_name_column_width() {
+        assert_eq!(name_column_width(None, None, 68), 75);
+        assert_eq!(name_column_width(Some(143), None, 68), 75);
+        assert_eq!(name_column_width(Some(168), None, 68), 100);
+        assert_eq!(name_column_width(Some(72), None, 68), 20);
+        assert_eq!(name_column_width(Some(1000), None, 68), 150);
+
+        assert_eq!(name_column_width(None, Some(50), 68), 75);
+        assert_eq!(name_column_width(Some(200), Some(50), 68), 100);
+        assert_eq!(name_column_width(Some(200), Some(30), 68), 60);
+        assert_eq!(name_column_width(Some(200), Some(5), 68), 20);
+        assert_eq!(name_column_width(Some(200), Some(90), 68), 132);
+        assert_eq!(name_column_width(Some(200), None, 68), 132);
+        assert_eq!(name_column_width(Some(120), Some(100), 68), 52);
+
+        // The name column gets the space that the other shown columns leave
+        assert_eq!(other_columns_width(&Column::DEFAULT, &Config::default()), 68);
+        assert_eq!(other_columns_width(&[Column::Id, Column::Name], &Config::default()), 6);
+        assert_eq!(name_column_width(Some(100), None, other_columns_width(&[Column::Id, Column::Name], &Config::default())), 94);
+        assert_eq!(other_columns_width(&[Column::Id, Column::Name, Column::NotePreview], &Config::default()), 27);
+
+        // Also with the columns from TODO_LIST_COLUMNS, if the terminal width is unknown
+        let columns = list_columns(Some(String::from("id,name,due")), &mut vec![]);
+        assert_eq!(other_columns_width(&columns, &Config::default()), 18);
+        assert_eq!(name_column_width(None, None, other_columns_width(&columns, &Config::default())), 125);
+    }
+
+    #[test]
+    fn test_week_group() {
+        let today = fixed_today(); // Thursday
+
+        assert_eq!(WeekGroup::from_due_date(None, today), WeekGroup::NoDueDate);
+        assert_eq!(WeekGroup::from_due_date(NaiveDate::from_ymd_opt(2025, 7, 16), today), WeekGroup::Overdue);
+        assert_eq!(WeekGroup::from_due_date(NaiveDate::from_ymd_opt(2025, 7, 17), today), WeekGroup::ThisWeek);
+        assert_eq!(WeekGroup::from_due_date(NaiveDate::from_ymd_opt(2025, 7, 20), today), WeekGroup::ThisWeek);
+        assert_eq!(WeekGroup::from_due_date(NaiveDate::from_ymd_opt(2025, 7, 21), today), WeekGroup::NextWeek);
+        assert_eq!(WeekGroup::from_due_date(NaiveDate::from_ymd_opt(2025, 7, 27), today), WeekGroup::NextWeek);
+        assert_eq!(WeekGroup::from_due_date(NaiveDate::from_ymd_opt(2025, 7, 28), today), WeekGroup::Later);
+    }
+
+    #[test]
+    fn test_color_groups() {
+        let mut tasks = vec![
+            Task::new(String::from("test 1"), fixed_today()),
+            Task::new(String::from("test 2"), fixed_today()),
+            Task::new(String::from("test 3"), fixed_today()),
+            Task::new(String::from("test 4"), fixed_today()),
+            Task::new(String::from("test 5"), fixed_today()),
+        ];
+        tasks[0].color = Some(Color::Blue);
+        tasks[1].color = Some(Color::Red);
+        tasks[2].color = Some(Color::Red);
+        tasks[2].due_date = NaiveDate::from_ymd_opt(2025, 7, 20);
+        tasks[3].color = Some(Color::Red);
+        tasks[3].due_date = NaiveDate::from_ymd_opt(2025, 7, 16);
+
+        assert_eq!(color_groups(&tasks, &[0, 1, 2, 3, 4], false), vec![
+            (Some(Color::Red), vec![3, 2, 1]),
+            (Some(Color::Blue), vec![0]),
+            (None, vec![4]),
+        ]);
+        assert_eq!(color_groups(&tasks, &[4, 0], false), vec![(Some(Color::Blue), vec![0]), (None, vec![4])]);
+
+        // Tasks that are done come last within their group
+        tasks[3].completed_date = NaiveDate::from_ymd_opt(2025, 7, 15);
+        assert_eq!(color_groups(&tasks, &[0, 1, 2, 3], true), vec![
+            (Some(Color::Red), vec![2, 1, 3]),
+            (Some(Color::Blue), vec![0]),
+        ]);
+    }
+
+    #[test]
+    fn test_list_tasks_empty() {
+        let args_iter_correct: IntoIter<String> = vec![].into_iter();
+        let args_iter_unknown: IntoIter<String> = vec![String::from("--foo")].into_iter();
 
         assert!(matches!(
-            add_duedate(&mut tasks, args_iter_correct),
+            list_tasks(&[], &default_list(), fixed_today(), &Config::default(), args_iter_correct),
             Result::Ok(..)
         ));
-        assert_eq!(
-            tasks[0].due_date
-                .unwrap()
-                .format("%Y-%m-%d")
-                .to_string(),
-            due_date
-        );
 
         assert!(matches!(
-            add_duedate(&mut tasks, args_iter_correct_clear),
-            Result::Ok(..)
+            list_tasks(&[], &default_list(), fixed_today(), &Config::default(), args_iter_unknown),
+            Result::Err(ArgError::UnknownOption(..))
         ));
-        assert_eq!(
-            tasks[0].due_date,
-            None
-        );
     }
 
     #[test]
-    fn test_set_task_color() {
-        let mut tasks = vec![Task::new( String::from("test") )];
+    fn test_diff_tasks() {
+        let mut older = vec![Task::new(String::from("test 1"), fixed_today()), Task::new(String::from("test 2"), fixed_today())];
+        older[0].id = 1;
+        older[1].id = 2;
 
-        assert_eq!(tasks[0].color, None);
+        let mut newer = older.clone();
+        assert!(diff_tasks(&older, &newer, &Config::default()).is_empty());
 
-        let args_iter_incorrect_1: IntoIter<String> = vec![String::from("2"), String::from("red")].into_iter();
-        let args_iter_incorrect_2: IntoIter<String> = vec![String::from("1"), String::from("orange")].into_iter();
-        let args_iter_invalid: IntoIter<String> = vec![String::from("foobar"), String::from("red")].into_iter();
-        let args_iter_too_many: IntoIter<String> = vec![String::from("1"), String::from("red"), String::from("more")].into_iter();
-        let args_iter_missing_1: IntoIter<String> = vec![].into_iter();
-        let args_iter_missing_2: IntoIter<String> = vec![String::from("1")].into_iter();
+        newer.remove(1);
+        newer.push(Task::new(String::from("test 3"), fixed_today()));
+        newer[1].id = 3;
+        newer[0].color = Some(Color::Red);
+        newer[0].due_date = NaiveDate::from_ymd_opt(2025, 7, 10);
+        newer[0].note = String::from("note");
+        let diff: Vec<String> = diff_tasks(&older, &newer, &Config::default()).iter().map(|line| strip_colors(line)).collect();
+        assert_eq!(diff, vec![
+            String::from("+ test 3"),
+            String::from("- test 2"),
+            String::from("~ test 1: due date none -> 2025-07-10, color none -> red, note changed"),
+        ]);
 
-        let args_iter_correct_r: IntoIter<String> = vec![String::from("1"), String::from("red")].into_iter();
-        let args_iter_correct_y: IntoIter<String> = vec![String::from("1"), String::from("yellow")].into_iter();
-        let args_iter_correct_g: IntoIter<String> = vec![String::from("1"), String::from("green")].into_iter();
-        let args_iter_correct_b: IntoIter<String> = vec![String::from("1"), String::from("blue")].into_iter();
-        let args_iter_correct_p: IntoIter<String> = vec![String::from("1"), String::from("purple")].into_iter();
-        let args_iter_correct_n: IntoIter<String> = vec![String::from("1"), String::from("clear")].into_iter();
+        // Dates are shown in the configured format
+        let config = Config { date_format: Some(String::from("%d/%m/%Y")), ..Config::default() };
+        let diff: Vec<String> = diff_tasks(&older, &newer, &config).iter().map(|line| strip_colors(line)).collect();
+        assert_eq!(diff[2], "~ test 1: due date none -> 10/07/2025, color none -> red, note changed");
 
-        // Test all failures
-        assert!(matches!(
-            set_task_color(&mut tasks, args_iter_incorrect_1),
-            Result::Err(ArgError::TaskNotFound)
-        ));
+        // Changes to fields that are not compared are not shown
+        let mut touched = older.clone();
+        touched[0].modified_date = NaiveDate::from_ymd_opt(2000, 1, 1);
+        assert!(diff_tasks(&older, &touched, &Config::default()).is_empty());
+
+        assert_eq!(tasks_added_or_removed(&older, &newer), 2);
+        assert_eq!(tasks_added_or_removed(&older, &touched), 0);
+        assert_eq!(tasks_added_or_removed(&older, &[]), 2);
+    }
+
+    #[test]
+    fn test_note_size_string() {
+        assert_eq!(note_size_string(""), None);
+        assert_eq!(note_size_string(" \n\t"), None);
+        assert_eq!(note_size_string("word"), Some(String::from("1 word (1 min read)")));
+        assert_eq!(note_size_string("two\nwords "), Some(String::from("2 words (1 min read)")));
+        assert_eq!(note_size_string(&"word ".repeat(201)), Some(String::from("201 words (2 min read)")));
+    }
+
+    #[test]
+    fn test_example_task() {
+        assert!(example_task_enabled(None));
+        assert!(example_task_enabled(Some(String::from("1"))));
+        assert!(!example_task_enabled(Some(String::from("0"))));
+        assert!(!example_task_enabled(Some(String::from("Off"))));
+
+        let mut tasks = vec![];
+        let mut next_id = 1;
+        add_example_task(&mut tasks, &mut next_id, fixed_today());
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].id, 1);
+        assert_eq!(next_id, 2);
+    }
+
+    #[test]
+    fn test_summarize_changes() {
+        let mut older = vec![Task::new(String::from("test 1"), fixed_today()), Task::new(String::from("test 2"), fixed_today())];
+        older[0].id = 1;
+        older[1].id = 2;
+
+        let mut newer = older.clone();
+        assert_eq!(summarize_changes(&older, &newer), String::from("No changes"));
+
+        newer.swap(0, 1);
+        assert_eq!(summarize_changes(&older, &newer), String::from("reordered"));
+
+        newer.remove(0);
+        newer[0].name = String::from("test 1 renamed");
+        newer.push(Task::new(String::from("test 3"), fixed_today()));
+        newer[1].id = 3;
+        assert_eq!(summarize_changes(&older, &newer), String::from("1 added, 1 removed, 1 changed"));
+    }
+
+    // Remove ANSI color codes, as colored output depends on the terminal
+    fn strip_colors(text: &str) -> String {
+        let mut stripped = String::new();
+        let mut in_code = false;
+        for c in text.chars() {
+            match c {
+                '\x1b' => in_code = true,
+                'm' if in_code => in_code = false,
+                _ if in_code => (),
+                _ => stripped.push(c),
+            }
+        }
+        stripped
+    }
+
+    #[test]
+    fn test_list_summary() {
+        let today = fixed_today();
+
+        let mut tasks = vec![
+            Task::new(String::from("test 1"), fixed_today()),
+            Task::new(String::from("test 2"), fixed_today()),
+            Task::new(String::from("test 3"), fixed_today()),
+            Task::new(String::from("test 4"), fixed_today()),
+        ];
+        assert_eq!(strip_colors(&list_summary(&tasks, &[0], today)), String::from("1 task"));
+
+        tasks[0].color = Some(Color::Red);
+        tasks[1].color = Some(Color::Green);
+        tasks[2].color = Some(Color::Red);
+        tasks[2].due_date = NaiveDate::from_ymd_opt(2025, 7, 16);
+        tasks[3].due_date = NaiveDate::from_ymd_opt(2025, 7, 17);
+        assert_eq!(strip_colors(&list_summary(&tasks, &[0, 1, 2, 3], today)), String::from("4 tasks (2 red, 1 green, 1 overdue)"));
+        assert_eq!(strip_colors(&info_summary(&tasks, today)), String::from("You have 4 tasks, 1 overdue"));
+        assert_eq!(info_summary(&tasks[..1], today), String::from("You have 1 task, 0 overdue"));
+        assert_eq!(strip_colors(&list_summary(&tasks, &[1, 3], today)), String::from("2 tasks (1 green)"));
+
+        tasks[1].estimate = Some(30);
+        tasks[3].estimate = Some(60);
+        assert_eq!(strip_colors(&list_summary(&tasks, &[1, 3], today)), String::from("2 tasks (1 green, 1h 30m estimated)"));
+
+        // A task that is done is not overdue, and is marked as done in the list
+        tasks[2].completed_date = Some(today);
+        assert_eq!(strip_colors(&list_summary(&tasks, &[0, 2], today)), String::from("2 tasks (2 red)"));
+        assert_eq!(strip_colors(&info_summary(&tasks, today)), String::from("You have 4 tasks, 0 overdue"));
+        assert_eq!(strip_colors(&Column::Name.cell(&tasks, 2, 20, false, today, &Config::default())), "(done) test 3       ");
+        assert_eq!(strip_colors(&Column::Due.cell(&tasks, 2, 20, false, today, &Config::default())).trim_end(), "2025-07-16");
+        assert!(!Column::Due.cell(&tasks, 2, 20, false, today, &Config::default()).contains('\x1b'));
+    }
+
+    #[test]
+    fn test_list_tasks() {
+        let tasks = vec![Task::new(String::from("test"), fixed_today())];
+
+        let args_iter_correct: IntoIter<String> = vec![].into_iter();
+        let args_iter_relative: IntoIter<String> = vec![String::from("--relative")].into_iter();
+        let args_iter_by_week: IntoIter<String> = vec![String::from("--by-week")].into_iter();
+        let args_iter_legend: IntoIter<String> = vec![String::from("--legend")].into_iter();
+        let args_iter_reverse: IntoIter<String> = vec![String::from("--reverse"), String::from("--by-week")].into_iter();
+        let args_iter_group_by: IntoIter<String> = vec![String::from("--group-by"), String::from("color")].into_iter();
+        let args_iter_group_by_invalid: IntoIter<String> = vec![String::from("--group-by"), String::from("size")].into_iter();
+        let args_iter_unknown: IntoIter<String> = vec![String::from("--foo")].into_iter();
+        let args_iter_ids_only: IntoIter<String> = vec![String::from("--color"), String::from("red"), String::from("--ids-only")].into_iter();
+        let args_iter_color_invalid: IntoIter<String> = vec![String::from("--color"), String::from("pink")].into_iter();
 
         assert!(matches!(
-            set_task_color(&mut tasks, args_iter_incorrect_2),
-            Result::Err(ArgError::InvalidColor(..))
+            list_tasks(&tasks, &default_list(), fixed_today(), &Config::default(), args_iter_correct),
+            Result::Ok(..)
         ));
 
         assert!(matches!(
-            set_task_color(&mut tasks, args_iter_invalid),
-            Result::Err(ArgError::InvalidTaskId(..))
+            list_tasks(&tasks, &default_list(), fixed_today(), &Config::default(), args_iter_relative),
+            Result::Ok(..)
         ));
 
         assert!(matches!(
-            set_task_color(&mut tasks, args_iter_too_many),
-            Result::Err(ArgError::TooManyArgs(..))
+            list_tasks(&tasks, &default_list(), fixed_today(), &Config::default(), args_iter_by_week),
+            Result::Ok(..)
         ));
 
         assert!(matches!(
-            set_task_color(&mut tasks, args_iter_missing_1),
-            Result::Err(ArgError::ArgMissing(..))
+            list_tasks(&tasks, &default_list(), fixed_today(), &Config::default(), args_iter_legend),
+            Result::Ok(..)
         ));
 
         assert!(matches!(
-            set_task_color(&mut tasks, args_iter_missing_2),
-            Result::Err(ArgError::ArgMissing(..))
+            list_tasks(&tasks, &default_list(), fixed_today(), &Config::default(), args_iter_reverse),
+            Result::Ok(..)
         ));
 
-        // Test correct behavior
         assert!(matches!(
-            set_task_color(&mut tasks, args_iter_correct_r),
+            list_tasks(&tasks, &default_list(), fixed_today(), &Config::default(), args_iter_group_by),
             Result::Ok(..)
         ));
-        assert_eq!(tasks[0].color, Some(Color::Red));
 
         assert!(matches!(
-            set_task_color(&mut tasks, args_iter_correct_y),
-            Result::Ok(..)
+            list_tasks(&tasks, &default_list(), fixed_today(), &Config::default(), args_iter_group_by_invalid),
+            Result::Err(ArgError::InvalidGrouping(..))
         ));
-        assert_eq!(tasks[0].color, Some(Color::Yellow));
 
         assert!(matches!(
-            set_task_color(&mut tasks, args_iter_correct_g),
-            Result::Ok(..)
+            list_tasks(&tasks, &default_list(), fixed_today(), &Config::default(), args_iter_unknown),
+            Result::Err(ArgError::UnknownOption(..))
         ));
-        assert_eq!(tasks[0].color, Some(Color::Green));
 
         assert!(matches!(
-            set_task_color(&mut tasks, args_iter_correct_b),
+            list_tasks(&tasks, &default_list(), fixed_today(), &Config::default(), args_iter_ids_only),
             Result::Ok(..)
         ));
-        assert_eq!(tasks[0].color, Some(Color::Blue));
 
         assert!(matches!(
-            set_task_color(&mut tasks, args_iter_correct_p),
-            Result::Ok(..)
+            list_tasks(&tasks, &default_list(), fixed_today(), &Config::default(), args_iter_color_invalid),
+            Result::Err(ArgError::InvalidColor(..))
         ));
-        assert_eq!(tasks[0].color, Some(Color::Purple));
 
+        let args_iter_stable: IntoIter<String> = vec![String::from("--stable")].into_iter();
         assert!(matches!(
-            set_task_color(&mut tasks, args_iter_correct_n),
-            Result::Ok(..)
+            list_tasks(&tasks, &default_list(), fixed_today(), &Config::default(), args_iter_stable),
+            Result::Err(ArgError::OptionRequires(..))
         ));
-        assert_eq!(tasks[0].color, None);
     }
 
     #[test]
-    fn test_add_note() {
-        let mut tasks = vec![Task::new( String::from("test") )];
+    fn test_list_ids_only() {
+        let mut tasks: Vec<Task> = ["a", "b", "c", "d"].iter().map(|name| Task::new(name.to_string(), fixed_today())).collect();
+        for (i, task) in tasks.iter_mut().enumerate() {
+            task.id = i as u64 + 11;
+        }
+        tasks[0].color = Some(Color::Red);
+        tasks[2].color = Some(Color::Red);
+        tasks[3].color = Some(Color::Blue);
 
-        let args_iter_incorrect_1: IntoIter<String> = vec![String::from("2"), String::from("red")].into_iter();
-        let args_iter_new: IntoIter<String> = vec![String::from("1"), String::from("Line1")].into_iter();
-        let args_iter_add: IntoIter<String> = vec![String::from("1"), String::from("Line2")].into_iter();
+        // The IDs printed for 'list --color red --ids-only', with and without '--reverse'
+        let mut selection = ListSelection::default();
+        selection.filter.parse_option("--color", &mut vec![String::from("red")].into_iter()).unwrap();
+        let positions = selection.positions(&tasks, fixed_today(), false);
+        assert_eq!(positions, vec![0, 2]);
+        assert_eq!(id_lines(&tasks, &positions, false), vec!["1", "3"]);
+        assert_eq!(id_lines(&tasks, &positions, true), vec!["id:11", "id:13"]);
+
+        selection.reverse = true;
+        assert_eq!(id_lines(&tasks, &selection.positions(&tasks, fixed_today(), false), false), vec!["3", "1"]);
+    }
+
+    #[test]
+    fn test_editor_command() {
+        assert_eq!(editor_command(Some(String::from("vim")), false), Ok((String::from("vim"), vec![])));
+        assert_eq!(
+            editor_command(Some(String::from("code --wait")), false),
+            Ok((String::from("code"), vec![String::from("--wait")]))
+        );
+        assert_eq!(editor_command(None, true), Ok((String::from("notepad"), vec![])));
+        assert_eq!(editor_command(Some(String::from(" ")), true), Ok((String::from("notepad"), vec![])));
+        assert_eq!(editor_command(None, false), Err(ArgError::EditorNotSet));
+        assert_eq!(editor_command(Some(String::new()), false), Err(ArgError::EditorNotSet));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_edit_note() {
+        let mut notes_dir = env::temp_dir();
+        notes_dir.push(format!("todo-rs-test-notes-{}", std::process::id()));
+
+        let mut tasks = vec![Task::new(String::from("test"), fixed_today())];
+        tasks[0].id = 4;
+        tasks[0].note = String::from("Line1");
+
+        let args_iter_no_editor: IntoIter<String> = vec![String::from("1")].into_iter();
+        let args_iter_failed: IntoIter<String> = vec![String::from("1")].into_iter();
+        let args_iter_not_found: IntoIter<String> = vec![String::from("1")].into_iter();
+        let args_iter_correct: IntoIter<String> = vec![String::from("1")].into_iter();
+        let args_iter_note: IntoIter<String> = vec![String::from("1"), String::from("Line2")].into_iter();
         let args_iter_clear: IntoIter<String> = vec![String::from("1"), String::from("clear")].into_iter();
 
         assert!(matches!(
-            add_note(&mut tasks, args_iter_incorrect_1),
-            Result::Err(ArgError::TaskNotFound)
+            edit_note(&mut tasks, &notes_dir, None, fixed_today(), args_iter_no_editor),
+            Result::Err(ArgError::EditorNotSet)
         ));
+        assert!(!tasks[0].note_file);
 
+        // If the editor fails, the note is not moved and no file is left behind
         assert!(matches!(
-            add_note(&mut tasks, args_iter_new),
-            Result::Ok(..)
+            edit_note(&mut tasks, &notes_dir, Some(String::from("false")), fixed_today(), args_iter_failed),
+            Result::Err(ArgError::EditorFailed(..))
+        ));
+        assert!(matches!(
+            edit_note(&mut tasks, &notes_dir, Some(String::from("todo-rs-no-such-editor")), fixed_today(), args_iter_not_found),
+            Result::Err(ArgError::EditorNotFound(..))
         ));
+        assert!(!tasks[0].note_file);
         assert_eq!(tasks[0].note, String::from("Line1"));
+        assert!(!note_filename(&notes_dir, 4).exists());
 
         assert!(matches!(
-            add_note(&mut tasks, args_iter_add),
+            edit_note(&mut tasks, &notes_dir, Some(String::from("true")), fixed_today(), args_iter_correct),
             Result::Ok(..)
         ));
-        assert_eq!(tasks[0].note, String::from("Line1\nLine2"));
+        assert!(tasks[0].note_file);
+        assert_eq!(tasks[0].note, String::new());
+        assert_eq!(read_note(&notes_dir, 4), String::from("Line1"));
+
+        assert!(matches!(
+            add_note(&mut tasks, fixed_today(), &Config::default(), args_iter_note),
+            Result::Err(ArgError::NoteInFile)
+        ));
 
         assert!(matches!(
-            add_note(&mut tasks, args_iter_clear),
+            add_note(&mut tasks, fixed_today(), &Config::default(), args_iter_clear),
             Result::Ok(..)
         ));
-        assert_eq!(tasks[0].note, String::from(""));
+        assert!(!tasks[0].note_file);
+
+        // A missing note file is an empty note
+        assert_eq!(read_note(&notes_dir, 5), String::new());
+
+        std::fs::remove_dir_all(&notes_dir).unwrap();
     }
 
     #[test]
     fn test_sort_tasks() {
+        let task = |name: &str, created: (i32, u32, u32), due: Option<(i32, u32, u32)>, color: Option<Color>| {
+            let mut task = Task::new(String::from(name), fixed_today());
+            task.creation_date = NaiveDate::from_ymd_opt(created.0, created.1, created.2).unwrap();
+            task.due_date = due.and_then(|(year, month, day)| NaiveDate::from_ymd_opt(year, month, day));
+            task.color = color;
+            task
+        };
         let mut tasks = vec![
-            Task {name: String::from("Task green 1"),  creation_date: NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(), due_date: NaiveDate::from_ymd_opt(2025, 8, 9), color: Some(Color::Green),  note: String::new()},
-            Task {name: String::from("Task purple 1"), creation_date: NaiveDate::from_ymd_opt(2024, 1, 7).unwrap(), due_date: None,                                color: Some(Color::Purple), note: String::new()},
-            Task {name: String::from("Task green 2"),  creation_date: NaiveDate::from_ymd_opt(2024, 5, 6).unwrap(), due_date: NaiveDate::from_ymd_opt(2025, 6, 1), color: Some(Color::Green),  note: String::new()},
-            Task {name: String::from("Task blue 1"),   creation_date: NaiveDate::from_ymd_opt(2024, 2, 7).unwrap(), due_date: NaiveDate::from_ymd_opt(2025, 6, 1), color: Some(Color::Blue),   note: String::new()},
-            Task {name: String::from("Task black 1"),  creation_date: NaiveDate::from_ymd_opt(2024, 5, 6).unwrap(), due_date: None,                                color: None,                note: String::new()},
-            Task {name: String::from("Task green 3"),  creation_date: NaiveDate::from_ymd_opt(2024, 8, 3).unwrap(), due_date: NaiveDate::from_ymd_opt(2024, 9, 8), color: Some(Color::Green),  note: String::new()},
-            Task {name: String::from("Task red 1"),    creation_date: NaiveDate::from_ymd_opt(2024, 2, 4).unwrap(), due_date: None,                                color: Some(Color::Red),    note: String::new()},
-            Task {name: String::from("Task black 2"),  creation_date: NaiveDate::from_ymd_opt(2024, 1, 4).unwrap(), due_date: NaiveDate::from_ymd_opt(2025, 6, 1), color: None,                note: String::new()},
-            Task {name: String::from("Task green 4"),  creation_date: NaiveDate::from_ymd_opt(2024, 5, 7).unwrap(), due_date: None,                                color: Some(Color::Green),  note: String::new()},
-            Task {name: String::from("Task green 5"),  creation_date: NaiveDate::from_ymd_opt(2024, 3, 5).unwrap(), due_date: NaiveDate::from_ymd_opt(2025, 1, 7), color: Some(Color::Green),  note: String::new()},
-            Task {name: String::from("Task red 2"),    creation_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), due_date: NaiveDate::from_ymd_opt(2025, 3, 9), color: Some(Color::Red),    note: String::new()},
+            task("Task green 1", (2024, 6, 1), Some((2025, 8, 9)), Some(Color::Green)),
+            task("Task purple 1", (2024, 1, 7), None, Some(Color::Purple)),
+            task("Task green 2", (2024, 5, 6), Some((2025, 6, 1)), Some(Color::Green)),
+            task("Task blue 1", (2024, 2, 7), Some((2025, 6, 1)), Some(Color::Blue)),
+            task("Task black 1", (2024, 5, 6), None, None),
+            task("Task green 3", (2024, 8, 3), Some((2024, 9, 8)), Some(Color::Green)),
+            task("Task red 1", (2024, 2, 4), None, Some(Color::Red)),
+            task("Task black 2", (2024, 1, 4), Some((2025, 6, 1)), None),
+            task("Task green 4", (2024, 5, 7), None, Some(Color::Green)),
+            task("Task green 5", (2024, 3, 5), Some((2025, 1, 7)), Some(Color::Green)),
+            task("Task red 2", (2024, 1, 1), Some((2025, 3, 9)), Some(Color::Red)),
         ];
 
         let args_iter_correct: IntoIter<String> = vec![].into_iter();
         let args_iter_too_many = vec![String::from("foo")].into_iter();
 
         assert!(matches!(
-            sort_tasks(&mut tasks, args_iter_too_many),
-            Result::Err(ArgError::TooManyArgs(..))
+            sort_tasks(&mut tasks, false, args_iter_too_many),
+            Result::Err(ArgError::InvalidSortKey(..))
         ));
 
         let order_expected = vec![
@@ -709,7 +5443,7 @@ mod tests {
         ];
 
         assert!(matches!(
-            sort_tasks(&mut tasks, args_iter_correct),
+            sort_tasks(&mut tasks, false, args_iter_correct),
             Result::Ok(..)
         ));
         for (task, name) in std::iter::zip(tasks, order_expected) {
@@ -717,4 +5451,141 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sort_tasks_deterministic() {
+        let mut tasks: Vec<Task> = (1..=6).map(|i| Task::new(format!("Task {}", i), fixed_today())).collect();
+        for (i, task) in tasks.iter_mut().enumerate() {
+            task.id = 6 - i as u64;
+            task.color = if i % 2 == 0 { Some(Color::Red) } else { None };
+        }
+        let mut reversed = tasks.clone();
+        reversed.reverse();
+
+        // The result does not depend on the order before sorting
+        assert!(matches!(sort_tasks(&mut tasks, false, std::iter::empty()), Result::Ok(..)));
+        assert!(matches!(sort_tasks(&mut reversed, false, std::iter::empty()), Result::Ok(..)));
+        assert_eq!(tasks, reversed);
+
+        // Sorting again after saving and loading does not change the file
+        let mut filename = env::temp_dir();
+        filename.push(format!("todo-rs-test-sort-{}", std::process::id()));
+        create_dir_all(&filename).unwrap();
+        filename.push("tasks.json");
+        save_file(&filename, &tasks, 7, &Meta::default()).unwrap();
+        let contents = read_to_string(&filename).unwrap();
+
+        let mut loaded = load_tasks(&filename).unwrap();
+        assert!(matches!(sort_tasks(&mut loaded.tasks, false, std::iter::empty()), Result::Ok(..)));
+        save_file(&filename, &loaded.tasks, loaded.next_id, &loaded.meta).unwrap();
+        assert_eq!(read_to_string(&filename).unwrap(), contents);
+
+        std::fs::remove_dir_all(filename.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_sort_tasks_keys() {
+        let mut tasks: Vec<Task> = ["b", "a", "c", "d"].iter().map(|name| Task::new(name.to_string(), fixed_today())).collect();
+        for (i, task) in tasks.iter_mut().enumerate() {
+            task.id = i as u64 + 1;
+        }
+        tasks[0].color = Some(Color::Red);
+        tasks[0].due_date = NaiveDate::from_ymd_opt(2025, 7, 10);
+        tasks[1].color = Some(Color::Red);
+        tasks[1].due_date = NaiveDate::from_ymd_opt(2025, 7, 20);
+        tasks[2].color = Some(Color::Blue);
+        tasks[2].due_date = NaiveDate::from_ymd_opt(2025, 7, 15);
+
+        let names = |tasks: &[Task]| tasks.iter().map(|task| task.name.clone()).collect::<Vec<String>>();
+
+        let args_iter_keys: IntoIter<String> = vec![String::from("color:desc"), String::from("due:desc")].into_iter();
+        sort_tasks(&mut tasks, false, args_iter_keys).unwrap();
+        assert_eq!(names(&tasks), vec!["c", "a", "b", "d"]);
+
+        let args_iter_name: IntoIter<String> = vec![String::from("name")].into_iter();
+        sort_tasks(&mut tasks, false, args_iter_name).unwrap();
+        assert_eq!(names(&tasks), vec!["a", "b", "c", "d"]);
+
+        // An invalid key leaves the tasks unchanged
+        let args_iter_invalid: IntoIter<String> = vec![String::from("due:desc"), String::from("priority:desc")].into_iter();
+        assert_eq!(sort_tasks(&mut tasks, false, args_iter_invalid), Err(ArgError::InvalidSortKey(String::from("priority:desc"))));
+        assert_eq!(names(&tasks), vec!["a", "b", "c", "d"]);
+
+        // Without keys, the default order is used
+        sort_tasks(&mut tasks, false, std::iter::empty()).unwrap();
+        assert_eq!(names(&tasks), vec!["b", "a", "c", "d"]);
+    }
+
+    #[test]
+    fn test_sort_by_rank_completed_last() {
+        let mut tasks = vec![Task::new(String::from("1"), fixed_today()), Task::new(String::from("2"), fixed_today()), Task::new(String::from("3"), fixed_today())];
+        tasks[0].color = Some(Color::Red);
+        tasks[0].completed_date = NaiveDate::from_ymd_opt(2025, 7, 15);
+        tasks[1].color = Some(Color::Blue);
+        tasks[2].color = Some(Color::Green);
+
+        let mut unchanged = tasks.clone();
+        sort_by_rank(&mut unchanged, false);
+        let names: Vec<&str> = unchanged.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, vec!["1", "3", "2"]);
+
+        sort_by_rank(&mut tasks, true);
+        let names: Vec<&str> = tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, vec!["3", "2", "1"]);
+
+        // Also with sort keys
+        sort_tasks(&mut tasks, true, vec![String::from("name")].into_iter()).unwrap();
+        let names: Vec<&str> = tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, vec!["2", "3", "1"]);
+    }
+
+    #[test]
+    fn test_sort_tasks_pinned() {
+        let mut tasks = vec![Task::new(String::from("1"), fixed_today()), Task::new(String::from("2"), fixed_today()), Task::new(String::from("3"), fixed_today()), Task::new(String::from("4"), fixed_today())];
+        tasks[0].color = Some(Color::Red);
+        tasks[1].color = Some(Color::Blue);
+        tasks[2].color = Some(Color::Green);
+
+        let args_iter_pin_1: IntoIter<String> = vec![String::from("4")].into_iter();
+        let args_iter_pin_2: IntoIter<String> = vec![String::from("2")].into_iter();
+        let args_iter_unpin: IntoIter<String> = vec![String::from("1")].into_iter();
+        let args_iter_too_many: IntoIter<String> = vec![String::from("1"), String::from("2")].into_iter();
+
+        assert!(matches!(
+            pin_task(&mut tasks, true, fixed_today(), args_iter_too_many),
+            Result::Err(ArgError::TooManyArgs(..))
+        ));
+        pin_task(&mut tasks, true, fixed_today(), args_iter_pin_1).unwrap();
+        pin_task(&mut tasks, true, fixed_today(), args_iter_pin_2).unwrap();
+        assert!(tasks[1].pinned && tasks[3].pinned);
+
+        // Pinned tasks come first, in their original order
+        sort_tasks(&mut tasks, false, std::iter::empty()).unwrap();
+        let names: Vec<&str> = tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, vec!["2", "4", "1", "3"]);
+
+        pin_task(&mut tasks, false, fixed_today(), args_iter_unpin).unwrap();
+        sort_tasks(&mut tasks, false, std::iter::empty()).unwrap();
+        let names: Vec<&str> = tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, vec!["4", "1", "3", "2"]);
+    }
+
+    #[test]
+    fn test_file_stamp() {
+        let mut filename = env::temp_dir();
+        filename.push(format!("todo-rs-test-stamp-{}.json", std::process::id()));
+        assert_eq!(file_stamp(&filename), None);
+
+        write(&filename, "[]").unwrap();
+        let stamp = file_stamp(&filename);
+        assert!(stamp.is_some());
+
+        // Replacing the file, as an editor may do, is noticed as well
+        let mut replacement = filename.clone();
+        replacement.set_extension("tmp");
+        write(&replacement, "[{}]").unwrap();
+        std::fs::rename(&replacement, &filename).unwrap();
+        assert_ne!(file_stamp(&filename), stamp);
+
+        remove_file(&filename).unwrap();
+    }
 }
@@ -0,0 +1,218 @@
+// Predicates to select tasks, shared by the commands that work on multiple tasks. Each function
+// returns a predicate on a single task, so predicates can be combined freely and tested without
+// parsing any arguments.
+use chrono::{Datelike, NaiveDate};
+
+use super::{ArgError, Result, Task, blocker_position};
+use crate::color::Color;
+
+// Tasks with a due date before 'today' that are not done yet
+pub fn overdue(today: NaiveDate) -> impl Fn(&Task) -> bool {
+    move |task| task.completed_date.is_none() && task.due_date.is_some_and(|date| date < today)
+}
+
+// Tasks with a due date on or before 'date'
+pub fn due_by(date: NaiveDate) -> impl Fn(&Task) -> bool {
+    move |task| task.due_date.is_some_and(|due_date| due_date <= date)
+}
+
+// Tasks that are not overdue or done, but due within 'days' days of 'today'. A window of 0 days
+// matches no tasks.
+pub fn due_soon(today: NaiveDate, days: u64) -> impl Fn(&Task) -> bool {
+    move |task| days > 0
+        && task.completed_date.is_none()
+        && task.due_date.is_some_and(|date| date >= today && (date - today).num_days() as u64 <= days)
+}
+
+pub fn has_color(color: Color) -> impl Fn(&Task) -> bool {
+    move |task| task.color.as_ref() == Some(&color)
+}
+
+pub fn done() -> impl Fn(&Task) -> bool {
+    |task| task.completed_date.is_some()
+}
+
+// Tasks marked as done on 'date'
+pub fn completed_on(date: NaiveDate) -> impl Fn(&Task) -> bool {
+    move |task| task.completed_date == Some(date)
+}
+
+// Tasks marked as done in the same (ISO) week as 'date'
+pub fn completed_in_week_of(date: NaiveDate) -> impl Fn(&Task) -> bool {
+    move |task| task.completed_date.is_some_and(|completed| completed.iso_week() == date.iso_week())
+}
+
+// Tasks that are hidden until a date after 'today'
+pub fn snoozed(today: NaiveDate) -> impl Fn(&Task) -> bool {
+    move |task| task.start_date.is_some_and(|date| date > today)
+}
+
+pub fn created_before(date: NaiveDate) -> impl Fn(&Task) -> bool {
+    move |task| task.creation_date < date
+}
+
+pub fn created_after(date: NaiveDate) -> impl Fn(&Task) -> bool {
+    move |task| task.creation_date > date
+}
+
+// Tasks that were added or changed on or after 'date'
+pub fn modified_since(date: NaiveDate) -> impl Fn(&Task) -> bool {
+    move |task| task.last_modified() >= date
+}
+
+// Tasks that are blocked by another task in 'tasks'
+pub fn blocked(tasks: &[Task]) -> impl Fn(&Task) -> bool + '_ {
+    move |task| blocker_position(tasks, task).is_some()
+}
+
+// Filter options to select tasks, shared by commands that work on multiple tasks
+#[derive(Debug, Default)]
+pub struct TaskFilter {
+    done: bool,
+    overdue: bool,
+    color: Option<Color>
+}
+
+impl TaskFilter {
+    // Parse a filter option and its value, if any. Returns false if it is not a filter option.
+    pub fn parse_option<T>(&mut self, option: &str, args_iter: &mut T) -> Result<bool>
+    where
+        T: Iterator<Item = String> {
+        match option {
+            "--done" => self.done = true,
+            "--overdue" => self.overdue = true,
+            "--color" => {
+                let color_name = args_iter.next().ok_or(ArgError::ArgMissing(String::from("color")))?;
+                // 'clear' is not a color to select tasks by
+                self.color = Some(Color::parse(&color_name)?.ok_or(ArgError::InvalidColor(color_name))?);
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub fn matches(&self, task: &Task, today: NaiveDate) -> bool {
+        (!self.done || done()(task))
+            && (!self.overdue || overdue(today)(task))
+            && self.color.clone().is_none_or(|color| has_color(color)(task))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 7, day).unwrap()
+    }
+
+    #[test]
+    fn test_due_date_predicates() {
+        let mut task = Task::new(String::from("test"), date(17));
+        assert!(!overdue(date(17))(&task));
+        assert!(!due_by(date(17))(&task));
+
+        task.due_date = Some(date(17));
+        assert!(!overdue(date(17))(&task));
+        assert!(overdue(date(18))(&task));
+        assert!(due_by(date(17))(&task));
+        assert!(!due_by(date(16))(&task));
+
+        assert!(due_soon(date(17), 3)(&task));
+        assert!(due_soon(date(14), 3)(&task));
+        assert!(!due_soon(date(13), 3)(&task));
+        assert!(!due_soon(date(18), 3)(&task));
+        assert!(!due_soon(date(17), 0)(&task));
+
+        // Tasks that are done are never overdue or due soon
+        task.completed_date = Some(date(16));
+        assert!(!overdue(date(18))(&task));
+        assert!(!due_soon(date(17), 3)(&task));
+        assert!(due_by(date(17))(&task));
+    }
+
+    #[test]
+    fn test_has_color() {
+        let mut task = Task::new(String::from("test"), date(17));
+        assert!(!has_color(Color::Red)(&task));
+
+        task.color = Some(Color::Red);
+        assert!(has_color(Color::Red)(&task));
+        assert!(!has_color(Color::Blue)(&task));
+    }
+
+    #[test]
+    fn test_completed_predicates() {
+        let mut task = Task::new(String::from("test"), date(17));
+        assert!(!done()(&task));
+        assert!(!completed_on(date(17))(&task));
+        assert!(!completed_in_week_of(date(17))(&task));
+
+        task.completed_date = Some(date(14)); // Monday
+        assert!(done()(&task));
+        assert!(completed_on(date(14))(&task));
+        assert!(!completed_on(date(17))(&task));
+        assert!(completed_in_week_of(date(20))(&task));
+        assert!(!completed_in_week_of(date(13))(&task));
+    }
+
+    #[test]
+    fn test_snoozed() {
+        let mut task = Task::new(String::from("test"), date(17));
+        assert!(!snoozed(date(17))(&task));
+
+        task.start_date = Some(date(18));
+        assert!(snoozed(date(17))(&task));
+        assert!(!snoozed(date(18))(&task));
+    }
+
+    #[test]
+    fn test_date_range_predicates() {
+        let mut task = Task::new(String::from("test"), date(17));
+        task.creation_date = date(10);
+        task.modified_date = None;
+
+        assert!(created_before(date(11))(&task));
+        assert!(!created_before(date(10))(&task));
+        assert!(created_after(date(9))(&task));
+        assert!(!created_after(date(10))(&task));
+
+        // Without a modification date, the creation date is used
+        assert!(modified_since(date(10))(&task));
+        assert!(!modified_since(date(11))(&task));
+        task.modified_date = Some(date(12));
+        assert!(modified_since(date(11))(&task));
+    }
+
+    #[test]
+    fn test_blocked() {
+        let mut tasks = vec![Task::new(String::from("test 1"), date(17)), Task::new(String::from("test 2"), date(17))];
+        tasks[0].id = 1;
+        tasks[1].id = 2;
+        tasks[1].blocked_by = Some(1);
+
+        assert!(!blocked(&tasks)(&tasks[0]));
+        assert!(blocked(&tasks)(&tasks[1]));
+        assert!(!blocked(&tasks[1..])(&tasks[1]));
+    }
+
+    #[test]
+    fn test_task_filter() {
+        let today = date(17);
+        let mut task = Task::new(String::from("test"), date(17));
+        task.color = Some(Color::Red);
+        task.due_date = Some(date(16));
+
+        let mut filter = TaskFilter::default();
+        assert!(filter.matches(&task, today));
+
+        let mut args_iter = vec![String::from("red")].into_iter();
+        assert_eq!(filter.parse_option("--color", &mut args_iter), Ok(true));
+        assert_eq!(filter.parse_option("--overdue", &mut args_iter), Ok(true));
+        assert_eq!(filter.parse_option("--foo", &mut args_iter), Ok(false));
+        assert!(filter.matches(&task, today));
+
+        assert_eq!(filter.parse_option("--done", &mut args_iter), Ok(true));
+        assert!(!filter.matches(&task, today));
+    }
+}
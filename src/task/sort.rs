@@ -0,0 +1,244 @@
+// Comparators to order tasks, for use with e.g. 'slice::sort_by'. They can be combined with
+// 'Ordering::then_with', e.g. 'by_color(a, b).then_with(|| by_due_date(a, b))'.
+use std::cmp::Ordering;
+
+use super::{ArgError, Result, Task};
+
+// Order by color, in the order of the colors of the rainbow. Tasks without a color come last.
+pub fn by_color(a: &Task, b: &Task) -> Ordering {
+    (a.color.is_none(), &a.color).cmp(&(b.color.is_none(), &b.color))
+}
+
+// Order by due date. Tasks without a due date come last.
+pub fn by_due_date(a: &Task, b: &Task) -> Ordering {
+    (a.due_date.is_none(), a.due_date).cmp(&(b.due_date.is_none(), b.due_date))
+}
+
+// Order by stable id, i.e. by the order in which tasks were added. As stable ids are unique,
+// this makes a sort deterministic when used as the last comparator.
+pub fn by_id(a: &Task, b: &Task) -> Ordering {
+    a.id.cmp(&b.id)
+}
+
+// Order by creation date, oldest first
+pub fn by_creation_date(a: &Task, b: &Task) -> Ordering {
+    a.creation_date.cmp(&b.creation_date)
+}
+
+// Order by name, alphabetically and ignoring case
+pub fn by_name(a: &Task, b: &Task) -> Ordering {
+    a.name.to_lowercase().cmp(&b.name.to_lowercase())
+}
+
+// Order by estimate, shortest first. Tasks without an estimate come last.
+pub fn by_estimate(a: &Task, b: &Task) -> Ordering {
+    (a.estimate.is_none(), a.estimate).cmp(&(b.estimate.is_none(), b.estimate))
+}
+
+// A key given to the 'sort' command, e.g. 'due:desc'
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SortKey {
+    field: SortField,
+    descending: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum SortField {
+    Color,
+    Due,
+    Created,
+    Name,
+    Estimate,
+}
+
+impl SortKey {
+    // The order of the 'sort' command without keys: by color, then by due date
+    pub const DEFAULT: [SortKey; 2] = [
+        SortKey { field: SortField::Color, descending: false },
+        SortKey { field: SortField::Due, descending: false },
+    ];
+
+    pub const FIELDS: [&'static str; 5] = ["color", "due", "created", "name", "estimate"];
+
+    // Parse 'field', 'field:asc' or 'field:desc'. Without a direction, the order is ascending.
+    pub fn parse(key: &str) -> Result<SortKey> {
+        let (name, direction) = key.split_once(':').unwrap_or((key, "asc"));
+        let field = match name {
+            "color" => SortField::Color,
+            "due" => SortField::Due,
+            "created" => SortField::Created,
+            "name" => SortField::Name,
+            "estimate" => SortField::Estimate,
+            _ => return Err(ArgError::InvalidSortKey(key.to_string())),
+        };
+        let descending = match direction {
+            "asc" => false,
+            "desc" => true,
+            _ => return Err(ArgError::InvalidSortKey(key.to_string())),
+        };
+        Ok(SortKey { field, descending })
+    }
+
+    // Order by this key. Tasks without a value for the key (e.g. without a due date) come last,
+    // in either direction.
+    pub fn compare(&self, a: &Task, b: &Task) -> Ordering {
+        let missing = |task: &Task| match self.field {
+            SortField::Color => task.color.is_none(),
+            SortField::Due => task.due_date.is_none(),
+            SortField::Estimate => task.estimate.is_none(),
+            SortField::Created | SortField::Name => false,
+        };
+        let order = match self.field {
+            SortField::Color => by_color(a, b),
+            SortField::Due => by_due_date(a, b),
+            SortField::Created => by_creation_date(a, b),
+            SortField::Name => by_name(a, b),
+            SortField::Estimate => by_estimate(a, b),
+        };
+        missing(a).cmp(&missing(b)).then(if self.descending { order.reverse() } else { order })
+    }
+}
+
+// Order tasks that are not done before tasks that are done
+pub fn completed_last(a: &Task, b: &Task) -> Ordering {
+    a.completed_date.is_some().cmp(&b.completed_date.is_some())
+}
+
+// Order pinned tasks before other tasks
+pub fn pinned_first(a: &Task, b: &Task) -> Ordering {
+    b.pinned.cmp(&a.pinned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use chrono::NaiveDate;
+
+    fn fixed_today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 7, 17).unwrap()
+    }
+
+    fn names(tasks: &[Task]) -> Vec<&str> {
+        tasks.iter().map(|task| task.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_by_color() {
+        let mut tasks = vec![
+            Task::new(String::from("none"), fixed_today()),
+            Task::new(String::from("blue"), fixed_today()),
+            Task::new(String::from("red"), fixed_today()),
+        ];
+        tasks[1].color = Some(Color::Blue);
+        tasks[2].color = Some(Color::Red);
+
+        tasks.sort_by(by_color);
+        assert_eq!(names(&tasks), vec!["red", "blue", "none"]);
+    }
+
+    #[test]
+    fn test_by_due_date() {
+        let mut tasks = vec![
+            Task::new(String::from("none"), fixed_today()),
+            Task::new(String::from("later"), fixed_today()),
+            Task::new(String::from("sooner"), fixed_today()),
+        ];
+        tasks[1].due_date = NaiveDate::from_ymd_opt(2025, 7, 20);
+        tasks[2].due_date = NaiveDate::from_ymd_opt(2025, 7, 10);
+
+        tasks.sort_by(by_due_date);
+        assert_eq!(names(&tasks), vec!["sooner", "later", "none"]);
+    }
+
+    #[test]
+    fn test_by_id() {
+        let mut tasks = vec![Task::new(String::from("second"), fixed_today()), Task::new(String::from("first"), fixed_today())];
+        tasks[0].id = 2;
+        tasks[1].id = 1;
+
+        tasks.sort_by(by_id);
+        assert_eq!(names(&tasks), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn test_completed_last() {
+        let mut tasks = vec![
+            Task::new(String::from("done"), fixed_today()),
+            Task::new(String::from("test 1"), fixed_today()),
+            Task::new(String::from("test 2"), fixed_today()),
+        ];
+        tasks[0].completed_date = NaiveDate::from_ymd_opt(2025, 7, 10);
+
+        tasks.sort_by(completed_last);
+        assert_eq!(names(&tasks), vec!["test 1", "test 2", "done"]);
+    }
+
+    #[test]
+    fn test_pinned_first() {
+        let mut tasks = vec![
+            Task::new(String::from("test 1"), fixed_today()),
+            Task::new(String::from("pinned 1"), fixed_today()),
+            Task::new(String::from("test 2"), fixed_today()),
+            Task::new(String::from("pinned 2"), fixed_today()),
+        ];
+        tasks[1].pinned = true;
+        tasks[3].pinned = true;
+
+        // Sorting is stable, so the order within both groups is kept
+        tasks.sort_by(pinned_first);
+        assert_eq!(names(&tasks), vec!["pinned 1", "pinned 2", "test 1", "test 2"]);
+    }
+
+    #[test]
+    fn test_combined() {
+        let mut tasks = vec![
+            Task::new(String::from("red later"), fixed_today()),
+            Task::new(String::from("none"), fixed_today()),
+            Task::new(String::from("red sooner"), fixed_today()),
+        ];
+        tasks[0].color = Some(Color::Red);
+        tasks[0].due_date = NaiveDate::from_ymd_opt(2025, 7, 20);
+        tasks[2].color = Some(Color::Red);
+        tasks[2].due_date = NaiveDate::from_ymd_opt(2025, 7, 10);
+
+        tasks.sort_by(|a, b| by_color(a, b).then_with(|| by_due_date(a, b)));
+        assert_eq!(names(&tasks), vec!["red sooner", "red later", "none"]);
+    }
+
+    #[test]
+    fn test_sort_key_parse() {
+        assert_eq!(SortKey::parse("due"), Ok(SortKey { field: SortField::Due, descending: false }));
+        assert_eq!(SortKey::parse("color:desc"), Ok(SortKey { field: SortField::Color, descending: true }));
+        assert_eq!(SortKey::parse("name:asc"), Ok(SortKey { field: SortField::Name, descending: false }));
+        assert_eq!(SortKey::parse("size"), Err(ArgError::InvalidSortKey(String::from("size"))));
+        assert_eq!(SortKey::parse("due:up"), Err(ArgError::InvalidSortKey(String::from("due:up"))));
+        assert_eq!(SortKey::parse("due:"), Err(ArgError::InvalidSortKey(String::from("due:"))));
+    }
+
+    #[test]
+    fn test_sort_key_compare() {
+        let mut tasks = vec![
+            Task::new(String::from("none"), fixed_today()),
+            Task::new(String::from("red later"), fixed_today()),
+            Task::new(String::from("blue"), fixed_today()),
+            Task::new(String::from("red sooner"), fixed_today()),
+        ];
+        tasks[1].color = Some(Color::Red);
+        tasks[1].due_date = NaiveDate::from_ymd_opt(2025, 7, 20);
+        tasks[2].color = Some(Color::Blue);
+        tasks[2].due_date = NaiveDate::from_ymd_opt(2025, 7, 15);
+        tasks[3].color = Some(Color::Red);
+        tasks[3].due_date = NaiveDate::from_ymd_opt(2025, 7, 10);
+
+        // Tasks without a due date come last in descending order as well
+        let due_desc = SortKey::parse("due:desc").unwrap();
+        tasks.sort_by(|a, b| due_desc.compare(a, b));
+        assert_eq!(names(&tasks), vec!["red later", "blue", "red sooner", "none"]);
+
+        let color_desc = SortKey::parse("color:desc").unwrap();
+        let due_asc = SortKey::parse("due").unwrap();
+        tasks.sort_by(|a, b| color_desc.compare(a, b).then_with(|| due_asc.compare(a, b)));
+        assert_eq!(names(&tasks), vec!["blue", "red sooner", "red later", "none"]);
+    }
+}